@@ -39,6 +39,10 @@ RUN mkdir -p src && echo "fn main() {}" > src/main.rs && \
 
 # 复制源代码 (替换临时的 src/main.rs)
 COPY src ./src
+# sqlx::migrate! 在编译时嵌入迁移文件
+COPY migrations ./migrations
+# 前端构建产物内嵌进二进制, 需先在 frontend/ 下执行 pnpm build
+COPY frontend/dist ./frontend/dist
 # 编译最终的动态链接二进制文件 (server 和 client)
 
 RUN cargo build --release --target x86_64-unknown-linux-gnu
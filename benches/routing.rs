@@ -0,0 +1,308 @@
+//! 路由吞吐量对比: 所有消息经过单个 Core actor 转发, 与客户端和比赛查分片路由表直接投递
+//!
+//! 每场比赛由服务端的 MatchRunner 驱动, 连接进程内的模拟 sponsor, sponsor 收到动作后
+//! 立即给出下一个状态; 两个 Agent 收到状态后立即回复动作.
+//! 转发基准只复现了 Core 查表转发的一跳, 没有重构前 Core 在热路径上等待的数据库写入
+//! (如 `update_agent_status`), 因此报告的加速比只反映路由本身, 低估了实际的差距.
+//! 运行: `cargo bench --bench routing -- [比赛数] [每场动作数]`
+
+use std::{
+    collections::HashMap,
+    env,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::{Stream, StreamExt};
+use tackle_box::{
+    connection::{
+        game_control::ControlType,
+        game_init_response::ResultType,
+        process_game_request::RequestType,
+        process_game_response::ResponseType,
+        sponsor_service_client::SponsorServiceClient,
+        sponsor_service_server::{SponsorService, SponsorServiceServer},
+        GameEndStatus, GameInitResponse, GameStateUpdate, ProcessGameRequest, ProcessGameResponse,
+    },
+    contracts::payloads::MatchMode,
+    runner::{connect_sponsor, CoreMessage, MatchChannels, MatchRunner, MatchSettings, Routes},
+};
+use tokio::sync::{
+    mpsc::{self, Receiver, Sender},
+    watch,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{
+    transport::{server::TcpIncoming, Channel, Server},
+    Request, Response, Status, Streaming,
+};
+use uuid::Uuid;
+
+/// 与服务端 Core 和比赛的通道容量一致
+const CORE_CAPACITY: usize = 8;
+const CHANNEL_CAPACITY: usize = 8;
+const GAME_TYPE: &str = "bench";
+
+type ProcessGameStream = Pin<Box<dyn Stream<Item = Result<ProcessGameResponse, Status>> + Send>>;
+
+/// 模拟 sponsor: 两人轮流行动, 每局 moves 个动作后结束
+struct MockSponsor {
+    moves: u64,
+}
+
+fn response(response_type: ResponseType) -> Result<ProcessGameResponse, Status> {
+    Ok(ProcessGameResponse {
+        response_type: Some(response_type),
+    })
+}
+
+fn state(moves: u64) -> Result<ProcessGameResponse, Status> {
+    response(ResponseType::StateUpdate(GameStateUpdate {
+        state: moves.to_string(),
+        is_over: false,
+        i_player: (moves % 2) as i32,
+    }))
+}
+
+#[tonic::async_trait]
+impl SponsorService for MockSponsor {
+    type ProcessGameStream = ProcessGameStream;
+
+    async fn process_game(
+        &self,
+        req: Request<Streaming<ProcessGameRequest>>,
+    ) -> Result<Response<Self::ProcessGameStream>, Status> {
+        let mut requests = req.into_inner();
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let per_game = self.moves;
+        tokio::spawn(async move {
+            let mut moves = 0;
+            while let Some(Ok(req)) = requests.next().await {
+                let replies = match req.request_type {
+                    // 第一次应答在连接时被丢弃
+                    Some(RequestType::Init(_)) => vec![
+                        response(ResponseType::InitResponse(GameInitResponse {
+                            r#type: ResultType::Success.into(),
+                        })),
+                        state(0),
+                    ],
+                    Some(RequestType::Action(_)) => {
+                        moves += 1;
+                        if moves < per_game {
+                            vec![state(moves)]
+                        } else {
+                            moves = 0;
+                            vec![response(ResponseType::EndStatus(GameEndStatus {
+                                payoffs: vec![1.0, -1.0],
+                            }))]
+                        }
+                    }
+                    Some(RequestType::Control(control))
+                        if control.r#type() == ControlType::Resume =>
+                    {
+                        vec![state(0)]
+                    }
+                    _ => break,
+                };
+                for reply in replies {
+                    if tx.send(reply).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+/// 在随机端口启动模拟 sponsor, 返回其地址
+async fn start_sponsor(moves: u64, streams: usize) -> SocketAddr {
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).expect("Failed to bind");
+    let addr = incoming
+        .local_addr()
+        .expect("Failed to get sponsor address");
+    let router = Server::builder()
+        .max_concurrent_streams(streams as u32)
+        .add_service(SponsorServiceServer::new(MockSponsor { moves }));
+    tokio::spawn(router.serve_with_incoming(incoming));
+    addr
+}
+
+/// Agent 发出动作的方式
+#[derive(Clone)]
+enum Route {
+    /// 发给 Core, 由 Core 查表转发
+    Relay(Sender<CoreMessage>),
+    /// 查表后直接发给比赛
+    Direct(Arc<Routes>),
+}
+
+/// 路由重构前的 Core: 状态和动作都经过它转发
+async fn relay(mut rx: Receiver<CoreMessage>, routes: Arc<Routes>) {
+    let mut clients: HashMap<Uuid, Sender<CoreMessage>> = HashMap::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            CoreMessage::ClientRegiser { agent_id, tx, .. } => {
+                clients.insert(agent_id, tx);
+            }
+            CoreMessage::GameState { agent_id, .. } => {
+                if let Some(tx) = clients.get(&agent_id) {
+                    let _ = tx.send(msg).await;
+                }
+            }
+            CoreMessage::AgentAction { match_id, .. } => {
+                if let Some(tx) = routes.matches.get(&match_id) {
+                    let _ = tx.send(msg).await;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 重构后的 Core 只处理比赛事件和结算
+async fn sink(mut rx: Receiver<CoreMessage>) {
+    while rx.recv().await.is_some() {}
+}
+
+async fn agent(agent_id: Uuid, route: Route, mut rx: Receiver<CoreMessage>) {
+    while let Some(msg) = rx.recv().await {
+        let CoreMessage::GameState {
+            match_id, state, ..
+        } = msg
+        else {
+            continue;
+        };
+        let action = CoreMessage::AgentAction {
+            agent_id,
+            match_id,
+            action: state,
+        };
+        let _ = match &route {
+            Route::Relay(core_tx) => core_tx.send(action).await.is_ok(),
+            Route::Direct(routes) => match routes.matches.get(&match_id) {
+                Some(match_runner) => match_runner.send(action).await.is_ok(),
+                None => false,
+            },
+        };
+    }
+}
+
+/// 返回所有比赛完成所用的时间
+async fn bench(direct: bool, client: &SponsorServiceClient<Channel>, matches: usize) -> Duration {
+    let routes = Arc::new(Routes::default());
+    let (core_tx, core_rx) = mpsc::channel(CORE_CAPACITY);
+    let route = if direct {
+        tokio::spawn(sink(core_rx));
+        Route::Direct(routes.clone())
+    } else {
+        tokio::spawn(relay(core_rx, routes.clone()));
+        Route::Relay(core_tx.clone())
+    };
+    let (_abort_tx, abort) = watch::channel(false);
+    let settings = MatchSettings {
+        total_games: 1,
+        seed: 0,
+        mode: MatchMode::Standard,
+    };
+    let mut runners = Vec::with_capacity(matches);
+    for _ in 0..matches {
+        let agent_ids = vec![Uuid::new_v4(), Uuid::new_v4()];
+        for &agent_id in &agent_ids {
+            let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+            // 转发时 MatchRunner 查到的客户端即是 Core
+            if direct {
+                routes.clients.insert(agent_id, tx);
+            } else {
+                routes.clients.insert(agent_id, core_tx.clone());
+                let _ = core_tx
+                    .send(CoreMessage::ClientRegiser {
+                        user_id: Uuid::nil(),
+                        agent_id,
+                        tx,
+                    })
+                    .await;
+            }
+            tokio::spawn(agent(agent_id, route.clone(), rx));
+        }
+        let match_id = Uuid::new_v4();
+        let (match_tx, match_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        routes.matches.insert(match_id, match_tx);
+        let (sponsor_tx, sponsor_rx) = connect_sponsor(client.clone(), GAME_TYPE, 0)
+            .await
+            .expect("Failed to connect to the mock sponsor");
+        let channels = MatchChannels {
+            match_rx,
+            core_tx: core_tx.clone(),
+            routes: routes.clone(),
+            sponsor_tx,
+            sponsor_rx,
+            abort: abort.clone(),
+        };
+        runners.push(MatchRunner::new(
+            match_id,
+            agent_ids,
+            "mock".to_string(),
+            GAME_TYPE.to_string(),
+            settings,
+            channels,
+        ));
+    }
+    let start = Instant::now();
+    let handles: Vec<_> = runners
+        .into_iter()
+        .map(|mut runner| tokio::spawn(async move { runner.run().await }))
+        .collect();
+    for handle in handles {
+        handle
+            .await
+            .expect("match runner panicked")
+            .expect("match failed");
+    }
+    let elapsed = start.elapsed();
+    // 释放 Sender, Core 与 Agent 任务随之退出
+    routes.clients.drain();
+    routes.matches.drain();
+    elapsed
+}
+
+fn main() {
+    // cargo bench 会传入 --bench, 只取数字参数
+    let mut numbers = env::args()
+        .skip(1)
+        .filter_map(|arg| arg.parse::<u64>().ok());
+    let matches = numbers.next().unwrap_or(500) as usize;
+    let actions = numbers.next().unwrap_or(200).max(1);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime");
+    let client = runtime.block_on(async {
+        let addr = start_sponsor(actions, matches).await;
+        SponsorServiceClient::connect(format!("http://{}", addr))
+            .await
+            .expect("Failed to connect to the mock sponsor")
+    });
+
+    println!(
+        "{} matches x {} actions, {} worker threads",
+        matches,
+        actions,
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+    println!("core relay omits the status writes the old core made per message");
+    let total = (matches as u64 * actions) as f64;
+    let mut baseline = None;
+    for (name, direct) in [("core relay", false), ("sharded direct", true)] {
+        let elapsed = runtime.block_on(bench(direct, &client, matches));
+        let throughput = total / elapsed.as_secs_f64();
+        let speedup = baseline.map_or(1.0, |base| throughput / base);
+        baseline.get_or_insert(throughput);
+        println!(
+            "{:<16} {:>10.2?} {:>12.0} actions/s {:>6.2}x",
+            name, elapsed, throughput, speedup
+        );
+    }
+}
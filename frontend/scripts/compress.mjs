@@ -0,0 +1,30 @@
+// 为构建产物生成 .br 和 .gz 预压缩文件, 由服务端按 Accept-Encoding 选择返回
+import { readdir, readFile, stat, writeFile } from 'node:fs/promises'
+import { join } from 'node:path'
+import { brotliCompressSync, constants, gzipSync } from 'node:zlib'
+
+const DIST = new URL('../dist/', import.meta.url).pathname
+const EXTENSIONS = ['.html', '.js', '.css', '.json', '.svg', '.txt', '.map']
+// 太小的文件压缩收益不大
+const MIN_SIZE = 1024
+
+async function* walk(dir) {
+  for (const entry of await readdir(dir, { withFileTypes: true })) {
+    const path = join(dir, entry.name)
+    if (entry.isDirectory()) yield* walk(path)
+    else yield path
+  }
+}
+
+for await (const path of walk(DIST)) {
+  if (!EXTENSIONS.some((ext) => path.endsWith(ext))) continue
+  if ((await stat(path)).size < MIN_SIZE) continue
+  const data = await readFile(path)
+  await writeFile(`${path}.gz`, gzipSync(data, { level: 9 }))
+  await writeFile(
+    `${path}.br`,
+    brotliCompressSync(data, {
+      params: { [constants.BROTLI_PARAM_QUALITY]: constants.BROTLI_MAX_QUALITY },
+    }),
+  )
+}
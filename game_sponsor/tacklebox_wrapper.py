@@ -49,7 +49,9 @@ class SponsorServiceServicer(pb2_grpc.SponsorServiceBase):
             print(f"Received request: {req}")
 
             if req.HasField("init"):
-                env = rlcard.make(req.init.game_type)
+                # 指定种子时对局可以完整复现
+                config = {'seed': req.init.seed} if req.init.HasField("seed") else {}
+                env = rlcard.make(req.init.game_type, config=config)
                 is_game_active = True
 
                 # 发送 INIT 响应
@@ -100,6 +102,8 @@ class SponsorServiceServicer(pb2_grpc.SponsorServiceBase):
                     is_game_active = False
                 elif control_type == pb2.GameControl.ControlType.RESUME and env is not None:
                     is_game_active = True
+                    if req.control.HasField("seed"):
+                        env.seed(req.control.seed)
                     state_dict, i_player = env.reset()
                     await stream.send_message(
                         pb2.ProcessGameResponse(
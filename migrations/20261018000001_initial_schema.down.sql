@@ -0,0 +1,12 @@
+-- 按依赖的逆序删除初始表结构
+
+DROP TABLE IF EXISTS STATS;
+DROP TABLE IF EXISTS PARTICIPANTS;
+DROP TABLE IF EXISTS TURNS;
+DROP TABLE IF EXISTS MATCHES;
+DROP TABLE IF EXISTS AGENTS;
+DROP TABLE IF EXISTS "users";
+DROP TABLE IF EXISTS GAMETYPES;
+DROP TYPE IF EXISTS MATCH_STATUS;
+DROP TYPE IF EXISTS AGENT_POLICY;
+DROP TYPE IF EXISTS AGENT_STATUS;
@@ -1,28 +1,9 @@
-V2
-```sql
-BEGIN;
-
--- ------------------------------
--- 1. DROP ALL TABLES AND TYPES
--- ------------------------------
-
-DROP TABLE IF EXISTS STATS;
-DROP TABLE IF EXISTS PARTICIPANTS;
-DROP TABLE IF EXISTS TURNS;
-DROP TABLE IF EXISTS MATCHES;
-DROP TABLE IF EXISTS AGENTS;
-DROP TABLE IF EXISTS "users";
-DROP TABLE IF EXISTS GAMETYPES;
-DROP TYPE IF EXISTS MATCH_STATUS;
-DROP TYPE IF EXISTS AGENT_POLICY;
-DROP TYPE IF EXISTS AGENT_STATUS;
--- ------------------------------
--- 2. CREATE TABLES (In dependency order)
--- ------------------------------
+-- 初始表结构, 与迁移引入前 src/repo/sql.md 中的 V2 相同
+-- 已按 sql.md 手工建库的部署中这些表和类型已经存在, 此时只记录为已应用, 之后的迁移照常执行
 
 -- GAMETYPE (Independent)
 -- Note: 'name' is the PK and is NOT database-generated.
-CREATE TABLE GAMETYPES (
+CREATE TABLE IF NOT EXISTS GAMETYPES (
     game_type_id  UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     name          VARCHAR(255) NOT NULL, -- PK (string name) - Manual input
     sponsor       VARCHAR(255) NOT NULL,
@@ -34,7 +15,7 @@ CREATE TABLE GAMETYPES (
 ---
 
 -- USER (id is DB-generated)
-CREATE TABLE "users" (
+CREATE TABLE IF NOT EXISTS "users" (
     user_id            UUID PRIMARY KEY DEFAULT gen_random_uuid(), -- PK (uuid id) - DB Generated
     username      VARCHAR(100) UNIQUE NOT NULL, -- UK
     password_hash VARCHAR(255) NOT NULL,
@@ -43,11 +24,18 @@ CREATE TABLE "users" (
     updated_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
 
-CREATE TYPE AGENT_POLICY AS ENUM ('Idle', 'AutoJoin', 'AutoNewAndJoin');
-CREATE TYPE AGENT_STATUS AS ENUM ('Idle', 'Ready', 'Running', 'Decommissioned');
+-- CREATE TYPE 没有 IF NOT EXISTS, 已存在时忽略
+DO $$ BEGIN
+    CREATE TYPE AGENT_POLICY AS ENUM ('Idle', 'AutoJoin', 'AutoNewAndJoin');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+DO $$ BEGIN
+    CREATE TYPE AGENT_STATUS AS ENUM ('Idle', 'Ready', 'Running', 'Decommissioned');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
 
 -- AGENT (id is DB-generated)
-CREATE TABLE AGENTS (
+CREATE TABLE IF NOT EXISTS AGENTS (
     agent_id      UUID PRIMARY KEY DEFAULT gen_random_uuid(), -- PK (uuid id) - DB Generated
     name          VARCHAR(255) NOT NULL,
     owner_id      UUID NOT NULL REFERENCES "users" (user_id),      -- FK (uuid owner_id)
@@ -68,8 +56,12 @@ CREATE TABLE AGENTS (
 ---
 
 -- MATCH (id is DB-generated)
-CREATE TYPE MATCH_STATUS AS ENUM ('Pending', 'Running', 'Completed', 'Cancelled');
-CREATE TABLE MATCHES (
+DO $$ BEGIN
+    CREATE TYPE MATCH_STATUS AS ENUM ('Pending', 'Running', 'Completed', 'Cancelled');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS MATCHES (
     match_id       UUID PRIMARY KEY DEFAULT gen_random_uuid(), -- PK (uuid id) - DB Generated
     name           VARCHAR(255) NOT NULL,
     password       VARCHAR(16),
@@ -86,7 +78,7 @@ CREATE TABLE MATCHES (
 ---
 
 -- TURN (id is DB-generated)
-CREATE TABLE TURNS (
+CREATE TABLE IF NOT EXISTS TURNS (
     turn_id        UUID PRIMARY KEY DEFAULT gen_random_uuid(), -- PK (uuid id) - DB Generated
     match_id       UUID NOT NULL REFERENCES MATCHES (match_id),        -- FK (uuid match_id)
     log            JSONB NOT NULL,
@@ -101,7 +93,7 @@ CREATE TABLE TURNS (
 ---
 
 -- PARTICIPATION (Composite PKs are FKs, so no DB-generated UUID needed)
-CREATE TABLE PARTICIPANTS (
+CREATE TABLE IF NOT EXISTS PARTICIPANTS (
     match_id       UUID NOT NULL REFERENCES MATCHES (match_id),      -- PK,FK
     agent_id       UUID NOT NULL REFERENCES AGENTS (agent_id),      -- PK,FK
     
@@ -111,7 +103,7 @@ CREATE TABLE PARTICIPANTS (
 ---
 
 -- STATS (Composite PKs are FKs, so no DB-generated UUID needed)
-CREATE TABLE STATS (
+CREATE TABLE IF NOT EXISTS STATS (
     game_type_id   UUID NOT NULL REFERENCES GAMETYPES (game_type_id),  -- PK,FK
     agent_id       UUID NOT NULL REFERENCES AGENTS (agent_id),               -- PK,FK
     rank           INT NOT NULL,
@@ -119,13 +111,3 @@ CREATE TABLE STATS (
 
     PRIMARY KEY (game_type_id, agent_id)
 );
-
-
-COMMIT;
-```
-
-data
-```sql
-INSERT INTO gametypes (name, sponsor, max_slots, min_slots) VALUES ('leduc-holdem', 'rlcard', 2, 2);
-
-```
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- 仍有 Agent 或比赛引用时会因外键失败
+DELETE FROM GAMETYPES WHERE name IN ('leduc-holdem', 'limit-holdem');
+
+ALTER TABLE GAMETYPES DROP CONSTRAINT GAMETYPES_NAME_KEY;
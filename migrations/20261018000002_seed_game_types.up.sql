@@ -0,0 +1,8 @@
+-- 游戏类型按名称查找, 名称需唯一
+ALTER TABLE GAMETYPES ADD CONSTRAINT GAMETYPES_NAME_KEY UNIQUE (name);
+
+-- 默认的游戏类型, 由 rlcard sponsor 提供
+INSERT INTO GAMETYPES (name, sponsor, max_slots, min_slots, description) VALUES
+    ('leduc-holdem', 'rlcard', 2, 2, 'Leduc Hold''em, 6 张牌的简化德州扑克'),
+    ('limit-holdem', 'rlcard', 2, 2, '两人限注德州扑克')
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,2 @@
+ALTER TABLE TURNS DROP COLUMN seed;
+ALTER TABLE MATCHES DROP COLUMN seed;
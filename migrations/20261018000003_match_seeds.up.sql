@@ -0,0 +1,4 @@
+-- 可复现的对局: 比赛种子和每局发给 sponsor 的种子
+
+ALTER TABLE MATCHES ADD COLUMN seed BIGINT; -- 比赛种子, 每局的种子由它派生
+ALTER TABLE TURNS ADD COLUMN seed BIGINT;   -- 本局发给 sponsor 的种子
@@ -0,0 +1,4 @@
+ALTER TABLE TURNS DROP COLUMN seats;
+ALTER TABLE MATCHES DROP COLUMN mode;
+
+DROP TYPE MATCH_MODE;
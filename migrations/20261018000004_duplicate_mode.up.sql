@@ -0,0 +1,6 @@
+-- 复式赛: 每副牌轮换座位各打一次, 每局记录各座位上的 Agent
+
+CREATE TYPE MATCH_MODE AS ENUM ('Standard', 'Duplicate');
+
+ALTER TABLE MATCHES ADD COLUMN mode MATCH_MODE NOT NULL DEFAULT 'Standard';
+ALTER TABLE TURNS ADD COLUMN seats UUID[]; -- 按座位顺序的 Agent
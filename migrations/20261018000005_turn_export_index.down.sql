@@ -0,0 +1 @@
+DROP INDEX TURNS_END_TIME_IDX;
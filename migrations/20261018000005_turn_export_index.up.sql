@@ -0,0 +1,2 @@
+-- 导出按结束时间分页
+CREATE INDEX TURNS_END_TIME_IDX ON TURNS (end_time, turn_id);
@@ -0,0 +1,9 @@
+DROP TABLE TOURNAMENT_BYES;
+DROP TABLE ENTRANTS;
+
+DROP INDEX MATCHES_TOURNAMENT_IDX;
+ALTER TABLE MATCHES DROP COLUMN round;
+ALTER TABLE MATCHES DROP COLUMN tournament_id;
+
+DROP TABLE TOURNAMENTS;
+DROP TYPE TOURNAMENT_FORMAT;
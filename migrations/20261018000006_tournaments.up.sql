@@ -0,0 +1,45 @@
+-- 锦标赛, 每轮的对局是普通的 MATCHES
+
+CREATE TYPE TOURNAMENT_FORMAT AS ENUM ('RoundRobin', 'Swiss', 'SingleElimination', 'DoubleElimination');
+
+-- TOURNAMENT (id is DB-generated)
+CREATE TABLE TOURNAMENTS (
+    tournament_id   UUID PRIMARY KEY DEFAULT gen_random_uuid(), -- PK (uuid id) - DB Generated
+    name            VARCHAR(255) NOT NULL,
+    password        VARCHAR(16),
+    game_type_id    UUID NOT NULL REFERENCES GAMETYPES (game_type_id),
+    creater_id      UUID NOT NULL REFERENCES "users" (user_id),
+    format          TOURNAMENT_FORMAT NOT NULL,
+    games_per_match INT NOT NULL CHECK (games_per_match > 0),
+    mode            MATCH_MODE NOT NULL DEFAULT 'Standard',
+    total_rounds    INT,                                           -- 瑞士轮的轮数, 其他赛制由人数决定
+    current_round   INT NOT NULL DEFAULT 0,
+    max_entrants    INT,
+    seed            BIGINT NOT NULL,                               -- 每场比赛的种子由它派生
+    status          MATCH_STATUS NOT NULL DEFAULT 'Pending',
+    winner_id       UUID REFERENCES AGENTS (agent_id),
+    created_at      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    end_time        TIMESTAMP WITH TIME ZONE
+);
+
+ALTER TABLE MATCHES ADD COLUMN tournament_id UUID REFERENCES TOURNAMENTS (tournament_id); -- 所属的锦标赛, Nullable
+ALTER TABLE MATCHES ADD COLUMN round INT;                                                 -- 锦标赛中的轮次, 从 1 开始
+CREATE INDEX MATCHES_TOURNAMENT_IDX ON MATCHES (tournament_id, round);
+
+-- ENTRANT (锦标赛报名, seeding 为种子序号, 越小越靠前)
+CREATE TABLE ENTRANTS (
+    tournament_id  UUID NOT NULL REFERENCES TOURNAMENTS (tournament_id), -- PK,FK
+    agent_id       UUID NOT NULL REFERENCES AGENTS (agent_id),           -- PK,FK
+    seeding        INT NOT NULL,
+
+    PRIMARY KEY (tournament_id, agent_id)
+);
+
+-- 轮空的记录, 轮空记一场胜利
+CREATE TABLE TOURNAMENT_BYES (
+    tournament_id  UUID NOT NULL REFERENCES TOURNAMENTS (tournament_id), -- PK,FK
+    round          INT NOT NULL,                                         -- PK
+    agent_id       UUID NOT NULL REFERENCES AGENTS (agent_id),           -- PK,FK
+
+    PRIMARY KEY (tournament_id, round, agent_id)
+);
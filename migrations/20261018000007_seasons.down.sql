@@ -0,0 +1,4 @@
+DROP TABLE SEASON_STANDINGS;
+DROP TABLE SEASON_MATCHES;
+DROP TABLE SEASON_RATINGS;
+DROP TABLE SEASONS;
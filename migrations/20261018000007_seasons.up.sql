@@ -0,0 +1,51 @@
+-- SEASON (按游戏类型划分的天梯赛季, 状态沿用 MATCH_STATUS: Pending 未开始, Running 进行中, Completed 已归档)
+CREATE TABLE SEASONS (
+    season_id      UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name           VARCHAR(255) NOT NULL,
+    game_type_id   UUID NOT NULL REFERENCES GAMETYPES (game_type_id),
+    creater_id     UUID NOT NULL REFERENCES "users" (user_id),
+    start_time     TIMESTAMP WITH TIME ZONE NOT NULL,
+    end_time       TIMESTAMP WITH TIME ZONE NOT NULL CHECK (end_time > start_time),
+    status         MATCH_STATUS NOT NULL DEFAULT 'Pending',
+    carry_over     DOUBLE PRECISION NOT NULL DEFAULT 0 CHECK (carry_over BETWEEN 0 AND 1), -- 结束时带入下赛季的分差比例, 0 为完全重置
+    recurring      BOOLEAN NOT NULL DEFAULT FALSE,                                           -- 结束时自动开启同样时长的下赛季
+    previous_id    UUID REFERENCES SEASONS (season_id),
+    created_at     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX SEASONS_GAME_TYPE_IDX ON SEASONS (game_type_id, start_time);
+
+-- 赛季内的 Elo 分数表
+CREATE TABLE SEASON_RATINGS (
+    season_id      UUID NOT NULL REFERENCES SEASONS (season_id),   -- PK,FK
+    agent_id       UUID NOT NULL REFERENCES AGENTS (agent_id),     -- PK,FK
+    rating         DOUBLE PRECISION NOT NULL,
+    played         INT NOT NULL DEFAULT 0,
+    won            INT NOT NULL DEFAULT 0,
+    drawn          INT NOT NULL DEFAULT 0,
+    lost           INT NOT NULL DEFAULT 0,
+    updated_time   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (season_id, agent_id)
+);
+
+-- 已计入赛季分数的比赛
+CREATE TABLE SEASON_MATCHES (
+    season_id      UUID NOT NULL REFERENCES SEASONS (season_id),   -- PK,FK
+    match_id       UUID NOT NULL REFERENCES MATCHES (match_id),    -- PK,FK
+
+    PRIMARY KEY (season_id, match_id)
+);
+
+-- 赛季结束时归档的最终排名
+CREATE TABLE SEASON_STANDINGS (
+    season_id      UUID NOT NULL REFERENCES SEASONS (season_id),   -- PK,FK
+    agent_id       UUID NOT NULL REFERENCES AGENTS (agent_id),     -- PK,FK
+    rank           INT NOT NULL,
+    rating         DOUBLE PRECISION NOT NULL,
+    played         INT NOT NULL,
+    won            INT NOT NULL,
+    drawn          INT NOT NULL,
+    lost           INT NOT NULL,
+
+    PRIMARY KEY (season_id, agent_id)
+);
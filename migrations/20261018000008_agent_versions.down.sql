@@ -0,0 +1,3 @@
+ALTER TABLE PARTICIPANTS DROP COLUMN version_id;
+
+DROP TABLE AGENT_VERSIONS;
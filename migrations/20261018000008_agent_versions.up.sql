@@ -0,0 +1,16 @@
+-- AGENT_VERSION (每个版本独立统计战绩, 切回旧版本时沿用旧记录)
+CREATE TABLE AGENT_VERSIONS (
+    version_id    UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    agent_id      UUID NOT NULL REFERENCES AGENTS (agent_id),
+    version       VARCHAR(50) NOT NULL,
+    played_games  INT DEFAULT 0 NOT NULL CHECK (played_games >= 0),
+    won_games     INT DEFAULT 0 NOT NULL CHECK (won_games >= 0),
+    created_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (agent_id, version)
+);
+
+-- 已有的战绩记在各 Agent 的当前版本上
+INSERT INTO AGENT_VERSIONS (agent_id, version, played_games, won_games, created_at)
+SELECT agent_id, version, played_games, won_games, created_at FROM AGENTS;
+
+ALTER TABLE PARTICIPANTS ADD COLUMN version_id UUID REFERENCES AGENT_VERSIONS (version_id); -- 比赛开始时的版本, 开始前为空
@@ -0,0 +1,10 @@
+ALTER TABLE PARTICIPANTS DROP COLUMN placement;
+ALTER TABLE MATCHES DROP COLUMN is_draw;
+
+ALTER TABLE AGENT_VERSIONS DROP COLUMN drawn_games;
+ALTER TABLE AGENTS DROP COLUMN drawn_games;
+
+ALTER TABLE GAMETYPES DROP COLUMN allow_draws;
+ALTER TABLE GAMETYPES DROP COLUMN settlement_rule;
+
+DROP TYPE SETTLEMENT_RULE;
@@ -0,0 +1,12 @@
+-- 按游戏类型配置的胜负规则, 平局和名次
+
+CREATE TYPE SETTLEMENT_RULE AS ENUM ('HighestScore', 'MostGamesWon');
+
+ALTER TABLE GAMETYPES ADD COLUMN settlement_rule SETTLEMENT_RULE NOT NULL DEFAULT 'HighestScore'; -- 比赛名次的计算方式
+ALTER TABLE GAMETYPES ADD COLUMN allow_draws BOOLEAN NOT NULL DEFAULT TRUE;                        -- 不允许平局时按加入顺序决出胜者
+
+ALTER TABLE AGENTS ADD COLUMN drawn_games INT DEFAULT 0 NOT NULL CHECK (drawn_games >= 0);
+ALTER TABLE AGENT_VERSIONS ADD COLUMN drawn_games INT DEFAULT 0 NOT NULL CHECK (drawn_games >= 0);
+
+ALTER TABLE MATCHES ADD COLUMN is_draw BOOLEAN NOT NULL DEFAULT FALSE; -- 并列第一且允许平局时没有胜者
+ALTER TABLE PARTICIPANTS ADD COLUMN placement INT;                     -- 结算后的名次, 并列时相同
@@ -0,0 +1,4 @@
+ALTER TABLE PARTICIPANTS DROP COLUMN rating_change;
+ALTER TABLE PARTICIPANTS DROP COLUMN seats;
+ALTER TABLE PARTICIPANTS DROP COLUMN games_won;
+ALTER TABLE PARTICIPANTS DROP COLUMN final_score;
@@ -0,0 +1,6 @@
+-- 每个参赛者的比赛结果, 结算时写入
+
+ALTER TABLE PARTICIPANTS ADD COLUMN final_score REAL;                -- 各局得分之和
+ALTER TABLE PARTICIPANTS ADD COLUMN games_won INT;                   -- 单局得分唯一最高的局数
+ALTER TABLE PARTICIPANTS ADD COLUMN seats INT[];                     -- 每局所在的座位
+ALTER TABLE PARTICIPANTS ADD COLUMN rating_change DOUBLE PRECISION;  -- 赛季计分时的分数变化, 不在赛季中为空
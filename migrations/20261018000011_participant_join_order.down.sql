@@ -0,0 +1 @@
+ALTER TABLE PARTICIPANTS DROP COLUMN joined_at;
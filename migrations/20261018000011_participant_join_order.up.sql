@@ -0,0 +1,4 @@
+-- 参赛者的加入顺序, 决定座位和不允许平局时的并列名次
+-- 已有的参赛者无法还原顺序, 取迁移时间
+
+ALTER TABLE PARTICIPANTS ADD COLUMN joined_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp();
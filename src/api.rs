@@ -1,4 +1,5 @@
 pub mod app;
+pub mod assets;
 pub mod error;
 pub mod extractor;
 pub mod handler;
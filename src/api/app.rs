@@ -1,20 +1,35 @@
 use crate::{
-    api::handler::{
-        handle_delete_agent, handle_get_agent, handle_get_agents, handle_get_game_types,
-        handle_get_match, handle_get_my_matches, handle_get_online_matches,
-        handle_get_participants, handle_get_turns, handle_join_match, handle_login, handle_me,
-        handle_new_agent, handle_new_match, handle_register, handle_update_agent,
+    api::{
+        assets::handle_static,
+        handler::{
+            handle_compare_versions, handle_delete_agent, handle_export_turns, handle_get_agent,
+            handle_get_agent_versions, handle_get_agents, handle_get_entrants,
+            handle_get_game_types, handle_get_match, handle_get_my_matches,
+            handle_get_online_matches, handle_get_participants, handle_get_replay,
+            handle_get_season, handle_get_season_standings, handle_get_seasons,
+            handle_get_standings, handle_get_tournament, handle_get_tournament_matches,
+            handle_get_tournaments, handle_get_turns, handle_head_to_head, handle_healthz,
+            handle_join_match, handle_join_tournament, handle_leave_match, handle_login, handle_me,
+            handle_metrics, handle_new_agent, handle_new_match, handle_new_season,
+            handle_new_tournament, handle_readyz, handle_register, handle_start_tournament,
+            handle_update_agent, handle_verify_game, trace_http, track_http,
+        },
+    },
+    core::{
+        agents::AgentService, auth::AuthService, health::HealthService, matches::MatchService,
+        metrics::MetricsService, replay::ReplayService, seasons::SeasonService,
+        stats::StatsService, tournaments::TournamentService,
     },
-    core::{agents::AgentService, auth::AuthService, matches::MatchService},
 };
 use axum::{
     extract::FromRef,
+    middleware,
     routing::{get, post},
     Router,
 };
 use std::{net::SocketAddr, sync::Arc};
-use tokio::net::TcpListener;
-use tracing::debug;
+use tokio::{net::TcpListener, sync::watch};
+use tracing::info;
 
 pub struct AppService {}
 
@@ -23,6 +38,12 @@ pub struct AppState {
     pub auth_service: Arc<AuthService>,
     pub agent_service: Arc<AgentService>,
     pub match_service: Arc<MatchService>,
+    pub replay_service: Arc<ReplayService>,
+    pub tournament_service: Arc<TournamentService>,
+    pub season_service: Arc<SeasonService>,
+    pub stats_service: Arc<StatsService>,
+    pub metrics_service: Arc<MetricsService>,
+    pub health_service: Arc<HealthService>,
 }
 
 impl FromRef<AppState> for AuthState {
@@ -55,12 +76,79 @@ impl FromRef<AppState> for AgentState {
 
 pub struct MatchState {
     pub match_service: Arc<MatchService>,
+    pub replay_service: Arc<ReplayService>,
 }
 
 impl FromRef<AppState> for MatchState {
     fn from_ref(input: &AppState) -> Self {
         MatchState {
             match_service: input.match_service.clone(),
+            replay_service: input.replay_service.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TournamentState {
+    pub tournament_service: Arc<TournamentService>,
+}
+
+impl FromRef<AppState> for TournamentState {
+    fn from_ref(input: &AppState) -> Self {
+        TournamentState {
+            tournament_service: input.tournament_service.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SeasonState {
+    pub season_service: Arc<SeasonService>,
+}
+
+impl FromRef<AppState> for SeasonState {
+    fn from_ref(input: &AppState) -> Self {
+        SeasonState {
+            season_service: input.season_service.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StatsState {
+    pub stats_service: Arc<StatsService>,
+}
+
+impl FromRef<AppState> for StatsState {
+    fn from_ref(input: &AppState) -> Self {
+        StatsState {
+            stats_service: input.stats_service.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsState {
+    pub metrics_service: Arc<MetricsService>,
+}
+
+impl FromRef<AppState> for MetricsState {
+    fn from_ref(input: &AppState) -> Self {
+        MetricsState {
+            metrics_service: input.metrics_service.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HealthState {
+    pub health_service: Arc<HealthService>,
+}
+
+impl FromRef<AppState> for HealthState {
+    fn from_ref(input: &AppState) -> Self {
+        HealthState {
+            health_service: input.health_service.clone(),
         }
     }
 }
@@ -80,6 +168,8 @@ impl AppService {
             .route("/delete", post(handle_delete_agent))
             .route("/update", post(handle_update_agent))
             .route("/get", post(handle_get_agent))
+            .route("/versions", post(handle_get_agent_versions))
+            .route("/compare", post(handle_compare_versions))
             .route("/agents", get(handle_get_agents));
         router
     }
@@ -88,31 +178,78 @@ impl AppService {
         let router = Router::new()
             .route("/new", post(handle_new_match))
             .route("/join", post(handle_join_match))
+            .route("/leave", post(handle_leave_match))
             .route("/get", post(handle_get_match))
             .route("/matches", get(handle_get_my_matches))
             .route("/turns", post(handle_get_turns))
+            .route("/replay", post(handle_get_replay))
+            .route("/verify", post(handle_verify_game))
+            .route("/export", post(handle_export_turns))
             .route("/participants", post(handle_get_participants))
             .route("/gametypes", get(handle_get_game_types))
             .route("/search", get(handle_get_online_matches));
         router
     }
 
+    pub fn tournament_router(&self) -> Router<AppState> {
+        Router::new()
+            .route("/new", post(handle_new_tournament))
+            .route("/join", post(handle_join_tournament))
+            .route("/start", post(handle_start_tournament))
+            .route("/get", post(handle_get_tournament))
+            .route("/entrants", post(handle_get_entrants))
+            .route("/standings", post(handle_get_standings))
+            .route("/matches", post(handle_get_tournament_matches))
+            .route("/tournaments", get(handle_get_tournaments))
+    }
+
+    pub fn season_router(&self) -> Router<AppState> {
+        Router::new()
+            .route("/new", post(handle_new_season))
+            .route("/get", post(handle_get_season))
+            .route("/standings", post(handle_get_season_standings))
+            .route("/seasons", get(handle_get_seasons))
+    }
+
+    pub fn stats_router(&self) -> Router<AppState> {
+        Router::new().route("/head-to-head", post(handle_head_to_head))
+    }
+
     pub fn api_router(&self) -> Router<AppState> {
         let router = Router::new()
             .nest("/auth", self.auth_router())
             .nest("/agent", self.agent_router())
-            .nest("/match", self.match_router());
+            .nest("/match", self.match_router())
+            .nest("/tournament", self.tournament_router())
+            .nest("/season", self.season_router())
+            .nest("/stats", self.stats_router());
         router
     }
 
-    pub async fn run(&self, app_state: AppState) {
+    /// 收到停机信号后不再接受新连接, 等待进行中的请求完成后返回
+    pub async fn run(
+        &self,
+        app_state: AppState,
+        addr: SocketAddr,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
         let router = Router::new()
             .nest("/api/v1", self.api_router())
+            .route("/metrics", get(handle_metrics))
+            .route("/healthz", get(handle_healthz))
+            .route("/readyz", get(handle_readyz))
+            .fallback(get(handle_static))
+            .layer(middleware::from_fn(track_http))
+            .layer(middleware::from_fn(trace_http))
             .with_state(app_state);
 
-        let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
         let listener = TcpListener::bind(addr).await.unwrap();
-        debug!("App begin to serve at localhost:3000");
-        axum::serve(listener, router).await.unwrap();
+        info!(%addr, "http server listening");
+        axum::serve(listener, router)
+            .with_graceful_shutdown(async move {
+                let _ = shutdown.wait_for(|&stop| stop).await;
+            })
+            .await
+            .unwrap();
     }
 }
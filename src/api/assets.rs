@@ -0,0 +1,120 @@
+//! 内嵌前端的静态文件服务, 未匹配的前端路由返回 index.html 交给前端路由处理
+
+use axum::{
+    body::Body,
+    http::{
+        header::{
+            ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH,
+            VARY,
+        },
+        HeaderMap, HeaderValue, StatusCode, Uri,
+    },
+    response::{IntoResponse, Response},
+};
+use rust_embed::{EmbeddedFile, RustEmbed};
+
+/// 前端构建产物, 需先在 frontend/ 下执行 `pnpm build`
+#[derive(RustEmbed)]
+#[folder = "frontend/dist/"]
+struct Asset;
+
+const INDEX: &str = "index.html";
+/// vite 输出到 assets/ 下的文件名带内容哈希, 可以长期缓存
+const IMMUTABLE_PREFIX: &str = "assets/";
+
+/// 优先级从高到低的预压缩格式: (Content-Encoding, 文件后缀)
+const ENCODINGS: [(&str, &str); 2] = [("br", ".br"), ("gzip", ".gz")];
+
+pub async fn handle_static(uri: Uri, headers: HeaderMap) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    let path = if path.is_empty() { INDEX } else { path };
+    if Asset::get(path).is_some() {
+        return serve(path, &headers);
+    }
+    // api 和带后缀的资源不存在时不回退, 避免把 html 当成脚本返回
+    let is_file = path
+        .rsplit('/')
+        .next()
+        .is_some_and(|name| name.contains('.'));
+    if path.starts_with("api/") || is_file || Asset::get(INDEX).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    serve(INDEX, &headers)
+}
+
+fn serve(path: &str, headers: &HeaderMap) -> Response {
+    let (file, encoding) = ENCODINGS
+        .iter()
+        .filter(|(encoding, _)| accepts(headers, encoding))
+        .find_map(|(encoding, suffix)| {
+            Asset::get(&format!("{}{}", path, suffix)).map(|file| (file, Some(*encoding)))
+        })
+        .or_else(|| Asset::get(path).map(|file| (file, None)))
+        .expect("asset checked before serving");
+    let etag = etag(&file, encoding);
+
+    let mut response = if if_none_match(headers, &etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Response::new(Body::from(file.data))
+    };
+    let response_headers = response.headers_mut();
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    if let Ok(value) = HeaderValue::from_str(mime.as_ref()) {
+        response_headers.insert(CONTENT_TYPE, value);
+    }
+    if let Some(encoding) = encoding {
+        response_headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+    let cache = if path.starts_with(IMMUTABLE_PREFIX) {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    response_headers.insert(CACHE_CONTROL, HeaderValue::from_static(cache));
+    response_headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(ETAG, value);
+    }
+    response
+}
+
+/// 按实际发送的内容计算, 不同压缩格式的 ETag 不同
+fn etag(file: &EmbeddedFile, encoding: Option<&str>) -> String {
+    let hash: String = file.metadata.sha256_hash()[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    match encoding {
+        Some(encoding) => format!("\"{}-{}\"", hash, encoding),
+        None => format!("\"{}\"", hash),
+    }
+}
+
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Accept-Encoding 中包含该编码且 q 不为 0
+fn accepts(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let q = parts
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            name.eq_ignore_ascii_case(encoding) && q > 0.0
+        })
+}
@@ -10,6 +10,7 @@ use axum::{
 use serde_json::json;
 use tackle_box::connection::{MatchPlayerResponse, ProcessGameRequest};
 use tokio::sync::mpsc::error::SendError;
+use tracing::error;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
@@ -78,8 +79,8 @@ impl IntoResponse for AppError {
             | AppError::SendError(_)
             | AppError::SendErrorClient(_)
             | AppError::MatchAborted(_) => {
-                // 打印到服务器日志，以便后端排查
-                eprintln!("Internal Error: {:?}", self);
+                // 记录到所在请求的 span 中，以便后端排查
+                error!(error = ?self, "internal error");
                 // 返回一个通用的 500 错误给前端
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -1,8 +1,15 @@
-use crate::{api::error::AppError, core::auth::AuthError};
+use crate::{
+    api::error::AppError,
+    core::auth::{AuthConfig, AuthError},
+};
 use axum::{extract::FromRequestParts, http::request::Parts};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{field, Span};
 use uuid::Uuid;
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -13,7 +20,16 @@ pub struct Claims {
     pub iat: usize,
 }
 
-const JWT_SECRET: &[u8] = b"hello world";
+static JWT_CONFIG: OnceLock<AuthConfig> = OnceLock::new();
+
+/// 启动时设置 jwt 密钥和有效期, 只有第一次调用生效
+pub fn init_jwt(config: AuthConfig) {
+    let _ = JWT_CONFIG.set(config);
+}
+
+fn jwt_config() -> &'static AuthConfig {
+    JWT_CONFIG.get().expect("jwt config is not initialized")
+}
 
 #[derive(Deserialize)]
 pub struct AuthenticatedUser {
@@ -40,6 +56,7 @@ impl<S: Sync> FromRequestParts<S> for AuthenticatedUser {
             .ok_or(AppError::Validation("Invaid token".to_string()))?;
 
         let claims = check_jwt(token)?;
+        Span::current().record("user_id", field::display(claims.user_id));
 
         // 4. 返回成功解析的用户 ID
         Ok(AuthenticatedUser {
@@ -53,7 +70,7 @@ pub async fn generate_jwt(username: &String, user_id: Uuid) -> Result<String, Au
         .duration_since(UNIX_EPOCH)
         .expect("Time went backwards")
         .as_secs() as usize;
-    let exp = now + 3600 * 24 * 30;
+    let exp = now + jwt_config().expiration;
     let claims = Claims {
         user_id,
         username: username.clone(),
@@ -61,12 +78,12 @@ pub async fn generate_jwt(username: &String, user_id: Uuid) -> Result<String, Au
         iat: now,
     };
     let header = Header::default();
-    let encoding_key = EncodingKey::from_secret(JWT_SECRET);
+    let encoding_key = EncodingKey::from_secret(jwt_config().jwt_secret.as_bytes());
     encode(&header, &claims, &encoding_key).map_err(|_| AuthError::JWTError)
 }
 
 pub fn check_jwt(token: &str) -> Result<Claims, AuthError> {
-    let decoding_key = DecodingKey::from_secret(JWT_SECRET);
+    let decoding_key = DecodingKey::from_secret(jwt_config().jwt_secret.as_bytes());
     let validation = Validation::new(Algorithm::HS256);
 
     let claims = decode::<Claims>(token, &decoding_key, &validation)
@@ -1,18 +1,38 @@
 use crate::{
     api::{
-        app::{AgentState, AuthState, MatchState},
+        app::{
+            AgentState, AuthState, HealthState, MatchState, MetricsState, SeasonState, StatsState,
+            TournamentState,
+        },
         error::AppError,
         extractor::{generate_jwt, AuthenticatedUser},
     },
     repo::users::GetUserDTO,
 };
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde_json::json;
-use tackle_box::contracts::payloads::{
-    DeleteAgentPayload, GetAgentPayload, GetMatchLogsPayload, GetMatchPayload,
-    GetParticipantsPayload, GetUserResponse, JoinMatchPayload, LoginPayload, LoginResponse,
-    NewAgentPayload, NewMatchPayload, RegisterPayload, RegisterResponse, UpdateAgentPayload,
+use tackle_box::{
+    contracts::payloads::{
+        CompareVersionsPayload, DeleteAgentPayload, ExportFormat, ExportTurnsPayload,
+        GetAgentPayload, GetMatchLogsPayload, GetMatchPayload, GetParticipantsPayload,
+        GetSeasonPayload, GetTournamentPayload, GetUserResponse, HeadToHeadPayload,
+        JoinMatchPayload, JoinTournamentPayload, LeaveMatchPayload, LoginPayload, LoginResponse,
+        MatchReplayPayload, MatchVerifyPayload, NewAgentPayload, NewMatchPayload, NewMatchResponse,
+        NewSeasonPayload, NewSeasonResponse, NewTournamentPayload, NewTournamentResponse,
+        RegisterPayload, RegisterResponse, UpdateAgentPayload,
+    },
+    metrics::{HTTP_DURATION, HTTP_REQUESTS},
 };
+use tokio::time::Instant;
+use tracing::{debug, field, info_span, Instrument};
+use uuid::Uuid;
 /*
 ====================
 Handle User Profile
@@ -130,6 +150,32 @@ pub async fn handle_get_agents(
     Ok((StatusCode::OK, Json(json!(agents))))
 }
 
+pub async fn handle_get_agent_versions(
+    _: AuthenticatedUser,
+    State(state): State<AgentState>,
+    Json(payload): Json<GetAgentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let versions = state.agent_service.get_versions(payload.agent_id).await?;
+    Ok((StatusCode::OK, Json(json!(versions))))
+}
+
+pub async fn handle_compare_versions(
+    _: AuthenticatedUser,
+    State(state): State<AgentState>,
+    Json(payload): Json<CompareVersionsPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let CompareVersionsPayload {
+        agent_id,
+        base,
+        target,
+    } = payload;
+    let comparison = state
+        .agent_service
+        .compare_versions(agent_id, base, target)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(comparison))))
+}
+
 /*
 ====================
 Match Manager Handler
@@ -158,8 +204,8 @@ pub async fn handle_new_match(
     State(state): State<MatchState>,
     Json(payload): Json<NewMatchPayload>,
 ) -> Result<impl IntoResponse, AppError> {
-    let _ = state.match_service.new_match(user_id, payload).await?;
-    Ok(StatusCode::OK)
+    let match_id = state.match_service.new_match(user_id, payload).await?;
+    Ok((StatusCode::OK, Json(json!(NewMatchResponse { match_id }))))
 }
 
 pub async fn handle_join_match(
@@ -179,17 +225,17 @@ pub async fn handle_join_match(
     Ok(StatusCode::OK)
 }
 
-// pub async fn handle_leave_match(
-//     AuthenticatedUser { user_id }: AuthenticatedUser,
-//     State(state): State<MatchState>,
-//     Json(payload): Json<LeaveMatchPayload>,
-// ) -> Result<impl IntoResponse, AppError> {
-//     state
-//         .match_service
-//         .leave_match(user_id, payload.agent_name)
-//         .await?;
-//     Ok(StatusCode::OK)
-// }
+pub async fn handle_leave_match(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<MatchState>,
+    Json(payload): Json<LeaveMatchPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .match_service
+        .leave_match(user_id, payload.match_id, payload.agent_ids)
+        .await?;
+    Ok(StatusCode::OK)
+}
 
 pub async fn handle_get_online_matches(
     AuthenticatedUser { user_id }: AuthenticatedUser,
@@ -211,6 +257,50 @@ pub async fn handle_get_turns(
     Ok((StatusCode::OK, Json(json!(turns))))
 }
 
+pub async fn handle_get_replay(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<MatchState>,
+    Json(payload): Json<MatchReplayPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let replays = state
+        .replay_service
+        .get_replay(user_id, payload.match_id, payload.i_turn)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(replays))))
+}
+
+pub async fn handle_verify_game(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<MatchState>,
+    Json(payload): Json<MatchVerifyPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = state
+        .replay_service
+        .verify_game(user_id, payload.match_id, payload.i_turn)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(result))))
+}
+
+pub async fn handle_export_turns(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<MatchState>,
+    Json(payload): Json<ExportTurnsPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let (content_type, file_name) = match payload.format {
+        ExportFormat::Jsonl => ("application/x-ndjson", "turns.jsonl"),
+        ExportFormat::Gzip => ("application/gzip", "turns.jsonl.gz"),
+    };
+    let turns = state.match_service.export_turns(user_id, payload).await?;
+    let headers = [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file_name),
+        ),
+    ];
+    Ok((StatusCode::OK, headers, Body::from_stream(turns)))
+}
+
 pub async fn handle_get_participants(
     AuthenticatedUser { user_id }: AuthenticatedUser,
     State(state): State<MatchState>,
@@ -236,3 +326,263 @@ pub async fn handle_get_game_types(
     let gametypes = state.match_service.get_gametypes().await?;
     Ok((StatusCode::OK, Json(json!(gametypes))))
 }
+
+/*
+====================
+Tournament Handler
+====================
+*/
+
+pub async fn handle_new_tournament(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<TournamentState>,
+    Json(payload): Json<NewTournamentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let tournament_id = state
+        .tournament_service
+        .new_tournament(user_id, payload)
+        .await?;
+    Ok((
+        StatusCode::OK,
+        Json(json!(NewTournamentResponse { tournament_id })),
+    ))
+}
+
+pub async fn handle_join_tournament(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<TournamentState>,
+    Json(payload): Json<JoinTournamentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .tournament_service
+        .join_tournament(
+            user_id,
+            payload.tournament_id,
+            payload.agent_ids,
+            payload.password,
+        )
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn handle_start_tournament(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<TournamentState>,
+    Json(payload): Json<GetTournamentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .tournament_service
+        .start_tournament(user_id, payload.tournament_id)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn handle_get_tournament(
+    _: AuthenticatedUser,
+    State(state): State<TournamentState>,
+    Json(payload): Json<GetTournamentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let tournament = state
+        .tournament_service
+        .get_tournament(payload.tournament_id)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(tournament))))
+}
+
+pub async fn handle_get_tournaments(
+    _: AuthenticatedUser,
+    State(state): State<TournamentState>,
+) -> Result<impl IntoResponse, AppError> {
+    let tournaments = state.tournament_service.get_tournaments().await?;
+    Ok((StatusCode::OK, Json(json!(tournaments))))
+}
+
+pub async fn handle_get_entrants(
+    _: AuthenticatedUser,
+    State(state): State<TournamentState>,
+    Json(payload): Json<GetTournamentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let entrants = state
+        .tournament_service
+        .get_entrants(payload.tournament_id)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(entrants))))
+}
+
+pub async fn handle_get_standings(
+    _: AuthenticatedUser,
+    State(state): State<TournamentState>,
+    Json(payload): Json<GetTournamentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let standings = state
+        .tournament_service
+        .get_standings(payload.tournament_id)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(standings))))
+}
+
+pub async fn handle_get_tournament_matches(
+    _: AuthenticatedUser,
+    State(state): State<TournamentState>,
+    Json(payload): Json<GetTournamentPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let matches = state
+        .tournament_service
+        .get_matches(payload.tournament_id)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(matches))))
+}
+
+/*
+====================
+Season Handler
+====================
+*/
+
+pub async fn handle_new_season(
+    AuthenticatedUser { user_id }: AuthenticatedUser,
+    State(state): State<SeasonState>,
+    Json(payload): Json<NewSeasonPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let season_id = state.season_service.new_season(user_id, payload).await?;
+    Ok((StatusCode::OK, Json(json!(NewSeasonResponse { season_id }))))
+}
+
+pub async fn handle_get_season(
+    _: AuthenticatedUser,
+    State(state): State<SeasonState>,
+    Json(payload): Json<GetSeasonPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let season = state.season_service.get_season(payload.season_id).await?;
+    Ok((StatusCode::OK, Json(json!(season))))
+}
+
+pub async fn handle_get_seasons(
+    _: AuthenticatedUser,
+    State(state): State<SeasonState>,
+) -> Result<impl IntoResponse, AppError> {
+    let seasons = state.season_service.get_seasons().await?;
+    Ok((StatusCode::OK, Json(json!(seasons))))
+}
+
+pub async fn handle_get_season_standings(
+    _: AuthenticatedUser,
+    State(state): State<SeasonState>,
+    Json(payload): Json<GetSeasonPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let standings = state
+        .season_service
+        .get_standings(payload.season_id)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(standings))))
+}
+
+/*
+====================
+Stats Handler
+====================
+*/
+
+pub async fn handle_head_to_head(
+    _: AuthenticatedUser,
+    State(state): State<StatsState>,
+    Json(payload): Json<HeadToHeadPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    let stats = state
+        .stats_service
+        .head_to_head(payload.agent_id, payload.opponent_id)
+        .await?;
+    Ok((StatusCode::OK, Json(json!(stats))))
+}
+
+/*
+====================
+Health Handler
+====================
+*/
+
+pub async fn handle_healthz(State(state): State<HealthState>) -> impl IntoResponse {
+    match state.health_service.liveness().await {
+        Some(core) => (StatusCode::OK, Json(json!({ "live": true, "core": core }))),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "live": false, "core": null })),
+        ),
+    }
+}
+
+pub async fn handle_readyz(State(state): State<HealthState>) -> impl IntoResponse {
+    let report = state.health_service.readiness().await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(json!(report)))
+}
+
+/*
+====================
+Metrics Handler
+====================
+*/
+
+pub async fn handle_metrics(State(state): State<MetricsState>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics_service.render(),
+    )
+}
+
+/// 按路由模板统计请求数和耗时, 未匹配路由的请求 (前端页面) 归到 fallback
+pub async fn track_http(req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("fallback", |path| path.as_str())
+        .to_string();
+    let start = Instant::now();
+    let response = next.run(req).await;
+    HTTP_DURATION.observe(&[&method, &route], start.elapsed());
+    HTTP_REQUESTS.inc(&[&method, &route, response.status().as_str()]);
+    response
+}
+
+const REQUEST_ID: &str = "x-request-id";
+
+/// 为每个请求创建带 request_id 的 span, 沿用客户端传入的 x-request-id 并在响应中返回
+pub async fn trace_http(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 64)
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("fallback", |path| path.as_str());
+    // user_id 由认证提取器填入
+    let span = info_span!(
+        "http_request",
+        %request_id,
+        method = %req.method(),
+        route,
+        user_id = field::Empty,
+    );
+    let start = Instant::now();
+    let mut response = next.run(req).instrument(span.clone()).await;
+    span.in_scope(|| {
+        debug!(
+            status = response.status().as_u16(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "request finished"
+        )
+    });
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID, value);
+    }
+    response
+}
@@ -0,0 +1,274 @@
+use clap::ValueEnum;
+use serde_json::json;
+use tackle_box::contracts::payloads::{
+    AgentPolicy, AgentVersionResponse, CompareVersionsPayload, CompareVersionsResponse,
+    DeleteAgentPayload, GetAgentPayload, GetAgentResponse, HeadToHeadPayload, HeadToHeadStats,
+    NewAgentPayload, UpdateAgentPayload,
+};
+
+use crate::{
+    error::ClientError,
+    output::{emit, or_dash, Table},
+    Context,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PolicyArg {
+    Idle,
+    AutoJoin,
+    AutoNewAndJoin,
+}
+
+impl From<PolicyArg> for AgentPolicy {
+    fn from(policy: PolicyArg) -> Self {
+        match policy {
+            PolicyArg::Idle => AgentPolicy::Idle,
+            PolicyArg::AutoJoin => AgentPolicy::AutoJoin,
+            PolicyArg::AutoNewAndJoin => AgentPolicy::AutoNewAndJoin,
+        }
+    }
+}
+
+pub struct AgentChanges {
+    pub rename: Option<String>,
+    pub game_type: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub policy: Option<PolicyArg>,
+}
+
+fn agents_table(agents: &[GetAgentResponse]) -> Table {
+    let mut table = Table::new(&[
+        "NAME", "AGENT_ID", "GAME", "VERSION", "STATUS", "POLICY", "PLAYED", "WON", "DRAWN",
+    ]);
+    for agent in agents {
+        table.push(vec![
+            agent.name.clone(),
+            agent.agent_id.to_string(),
+            agent.game_type_name.clone(),
+            agent.version.clone(),
+            format!("{:?}", agent.status),
+            format!("{:?}", agent.policy),
+            agent.played_games.to_string(),
+            agent.won_games.to_string(),
+            agent.drawn_games.to_string(),
+        ]);
+    }
+    table
+}
+
+pub async fn handle_create_agent(
+    ctx: &Context,
+    name: String,
+    game_type: String,
+    version: String,
+    description: Option<String>,
+    policy: PolicyArg,
+) -> Result<(), ClientError> {
+    let game_type = ctx.api.resolve_game_type(&game_type).await?;
+    let payload = NewAgentPayload {
+        name: name.clone(),
+        game_type_id: game_type.game_type_id,
+        version,
+        description,
+        policy: policy.into(),
+    };
+    ctx.api.post_unit("/agent/new", &payload).await?;
+    let agent = ctx.api.resolve_agent(&name).await?;
+    ctx.done(
+        &format!("Creating Agent {} successful!", name),
+        json!({ "agent_id": agent.agent_id }),
+    )
+}
+
+pub async fn handle_update_agent(
+    ctx: &Context,
+    name: String,
+    changes: AgentChanges,
+) -> Result<(), ClientError> {
+    let agent = ctx.api.resolve_agent(&name).await?;
+    let game_type_id = match changes.game_type {
+        Some(game_type) => ctx.api.resolve_game_type(&game_type).await?.game_type_id,
+        None => agent.game_type_id,
+    };
+    let payload = UpdateAgentPayload {
+        agent_id: agent.agent_id,
+        name: changes.rename.unwrap_or(agent.name),
+        game_type_id,
+        version: changes.version.unwrap_or(agent.version),
+        description: changes.description.or(agent.description),
+        policy: changes.policy.map(Into::into).unwrap_or(agent.policy),
+    };
+    ctx.api.post_unit("/agent/update", &payload).await?;
+    ctx.done(
+        &format!("Updating Agent {} successful!", name),
+        json!({ "agent_id": agent.agent_id }),
+    )
+}
+
+pub async fn handle_delete_agent(ctx: &Context, name: String) -> Result<(), ClientError> {
+    let agent = ctx.api.resolve_agent(&name).await?;
+    let payload = DeleteAgentPayload {
+        agent_id: agent.agent_id,
+    };
+    ctx.api.post_unit("/agent/delete", &payload).await?;
+    ctx.done(
+        &format!("Deleting Agent {} successful!", name),
+        json!({ "agent_id": agent.agent_id }),
+    )
+}
+
+pub async fn handle_show_agent(ctx: &Context, name: String) -> Result<(), ClientError> {
+    let agent = ctx.api.resolve_agent(&name).await?;
+    emit(ctx.output, &agent, |agent| {
+        let mut table = Table::new(&["KEY", "VALUE"]);
+        let rows = [
+            ("name", agent.name.clone()),
+            ("agent_id", agent.agent_id.to_string()),
+            ("game_type", agent.game_type_name.clone()),
+            ("owner", agent.owner_name.clone()),
+            ("version", agent.version.clone()),
+            ("description", or_dash(agent.description.as_ref())),
+            ("status", format!("{:?}", agent.status)),
+            ("policy", format!("{:?}", agent.policy)),
+            ("played_games", agent.played_games.to_string()),
+            ("won_games", agent.won_games.to_string()),
+            ("drawn_games", agent.drawn_games.to_string()),
+            ("created_at", agent.created_at.to_rfc3339()),
+            ("updated_at", agent.updated_at.to_rfc3339()),
+        ];
+        for (key, value) in rows {
+            table.push(vec![key.to_string(), value]);
+        }
+        table
+    })
+}
+
+/// 列出 Agent 的所有版本及各自的战绩
+pub async fn handle_agent_versions(ctx: &Context, name: String) -> Result<(), ClientError> {
+    let agent = ctx.api.resolve_agent(&name).await?;
+    let versions: Vec<AgentVersionResponse> = ctx
+        .api
+        .post(
+            "/agent/versions",
+            &GetAgentPayload {
+                agent_id: agent.agent_id,
+            },
+        )
+        .await?;
+    emit(ctx.output, &versions, |versions| {
+        let mut table = Table::new(&[
+            "VERSION", "PLAYED", "WON", "DRAWN", "WIN_RATE", "CREATED", "",
+        ]);
+        for v in versions {
+            table.push(vec![
+                v.version.clone(),
+                v.played_games.to_string(),
+                v.won_games.to_string(),
+                v.drawn_games.to_string(),
+                win_rate(v.won_games as i64, v.played_games as i64),
+                v.created_at.format("%Y-%m-%d %H:%M").to_string(),
+                if v.current { "current" } else { "" }.to_string(),
+            ]);
+        }
+        table
+    })
+}
+
+/// 对比同一 Agent 的两个版本对每个对手的战绩
+pub async fn handle_compare_versions(
+    ctx: &Context,
+    name: String,
+    base: String,
+    target: String,
+) -> Result<(), ClientError> {
+    let agent = ctx.api.resolve_agent(&name).await?;
+    let payload = CompareVersionsPayload {
+        agent_id: agent.agent_id,
+        base,
+        target,
+    };
+    let comparison: CompareVersionsResponse = ctx.api.post("/agent/compare", &payload).await?;
+    emit(ctx.output, &comparison, |c| {
+        let base = &c.base.version;
+        let target = &c.target.version;
+        let mut table = Table::new(&[
+            "OPPONENT",
+            &format!("{} W/P", base),
+            &format!("{} RATE", base),
+            &format!("{} W/P", target),
+            &format!("{} RATE", target),
+        ]);
+        table.push(vec![
+            "(all)".to_string(),
+            format!("{}/{}", c.base.won_games, c.base.played_games),
+            win_rate(c.base.won_games as i64, c.base.played_games as i64),
+            format!("{}/{}", c.target.won_games, c.target.played_games),
+            win_rate(c.target.won_games as i64, c.target.played_games as i64),
+        ]);
+        for o in &c.opponents {
+            table.push(vec![
+                o.opponent_name.clone(),
+                format!("{}/{}", o.base_won, o.base_played),
+                win_rate(o.base_won, o.base_played),
+                format!("{}/{}", o.target_won, o.target_played),
+                win_rate(o.target_won, o.target_played),
+            ]);
+        }
+        table
+    })
+}
+
+/// 按对手列出逐局战绩, 平均得分最低的对手排在最前
+pub async fn handle_head_to_head(
+    ctx: &Context,
+    name: String,
+    opponent: Option<String>,
+) -> Result<(), ClientError> {
+    let agent = ctx.api.resolve_agent(&name).await?;
+    let opponent_id = match opponent {
+        Some(opponent) => Some(ctx.api.resolve_agent(&opponent).await?.agent_id),
+        None => None,
+    };
+    let payload = HeadToHeadPayload {
+        agent_id: agent.agent_id,
+        opponent_id,
+    };
+    let stats: Vec<HeadToHeadStats> = ctx.api.post("/stats/head-to-head", &payload).await?;
+    emit(ctx.output, &stats, |stats| {
+        let mut table = Table::new(&[
+            "OPPONENT", "OWNER", "GAMES", "W", "L", "D", "WIN_RATE", "MEAN", "95% CI", "OPP_MEAN",
+        ]);
+        for s in stats {
+            let ci = match (s.ci_low, s.ci_high) {
+                (Some(low), Some(high)) => format!("[{:.3}, {:.3}]", low, high),
+                _ => "-".to_string(),
+            };
+            table.push(vec![
+                s.opponent_name.clone(),
+                s.owner_name.clone(),
+                s.games.to_string(),
+                s.won.to_string(),
+                s.lost.to_string(),
+                s.drawn.to_string(),
+                win_rate(s.won, s.games),
+                format!("{:.3}", s.mean_payoff),
+                ci,
+                format!("{:.3}", s.opponent_mean_payoff),
+            ]);
+        }
+        table
+    })
+}
+
+fn win_rate(won: i64, played: i64) -> String {
+    if played == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", won as f64 * 100.0 / played as f64)
+}
+
+pub async fn handle_list_agents(ctx: &Context) -> Result<(), ClientError> {
+    let agents: Vec<GetAgentResponse> = ctx.api.get("/agent/agents").await?;
+    emit(ctx.output, &agents, |agents| agents_table(agents))
+}
@@ -0,0 +1,219 @@
+use std::str::FromStr;
+
+use base64::prelude::*;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use tackle_box::{
+    connection::client_service_client::ClientServiceClient,
+    contracts::{
+        grpc::MatchMetadata,
+        payloads::{GetAgentResponse, GetGameTypeResponse},
+    },
+};
+use tonic::{
+    metadata::MetadataValue,
+    service::{interceptor::InterceptedService, Interceptor},
+    transport::Channel,
+    Request, Status,
+};
+use uuid::Uuid;
+
+use crate::{config::ActiveProfile, error::ClientError};
+
+/// 配置中的地址可省略协议, 默认补全为 http
+fn with_scheme(url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.trim_end_matches('/').to_string()
+    } else {
+        format!("http://{}", url.trim_end_matches('/'))
+    }
+}
+
+async fn process_error(resp: Response) -> Result<Response, ClientError> {
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(ClientError::ApiError(format!(
+            "Action failed: Status={}, Body={}",
+            status, body
+        )));
+    }
+    Ok(resp)
+}
+
+pub struct ApiClient {
+    http: Client,
+    profile: ActiveProfile,
+}
+
+impl ApiClient {
+    pub fn new(profile: ActiveProfile) -> Self {
+        Self {
+            http: Client::new(),
+            profile,
+        }
+    }
+
+    pub fn profile(&self) -> &ActiveProfile {
+        &self.profile
+    }
+
+    pub fn server_url(&self) -> String {
+        with_scheme(&self.profile.profile.server_url)
+    }
+
+    pub fn grpc_url(&self) -> String {
+        with_scheme(&self.profile.profile.grpc_url)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v1{}", self.server_url(), path)
+    }
+
+    fn authorized(&self, req: RequestBuilder) -> Result<RequestBuilder, ClientError> {
+        let token = self.profile.token()?;
+        Ok(req.header("Authorization", format!("Bearer {}", token)))
+    }
+
+    /// 无需登录的 POST 请求, 用于注册和登录
+    pub async fn post_anonymous<P: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        payload: &P,
+    ) -> Result<T, ClientError> {
+        let resp = self.http.post(self.url(path)).json(payload).send().await?;
+        let resp = process_error(resp).await?;
+        Ok(resp.json().await?)
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        let resp = self
+            .authorized(self.http.get(self.url(path)))?
+            .send()
+            .await?;
+        let resp = process_error(resp).await?;
+        Ok(resp.json().await?)
+    }
+
+    pub async fn post<P: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        payload: &P,
+    ) -> Result<T, ClientError> {
+        let resp = self
+            .authorized(self.http.post(self.url(path)))?
+            .json(payload)
+            .send()
+            .await?;
+        let resp = process_error(resp).await?;
+        Ok(resp.json().await?)
+    }
+
+    /// 返回原始响应的 POST 请求, 用于按块读取的下载
+    pub async fn post_stream<P: Serialize>(
+        &self,
+        path: &str,
+        payload: &P,
+    ) -> Result<Response, ClientError> {
+        let resp = self
+            .authorized(self.http.post(self.url(path)))?
+            .json(payload)
+            .send()
+            .await?;
+        process_error(resp).await
+    }
+
+    /// 服务端只返回状态码的 POST 请求
+    pub async fn post_unit<P: Serialize>(
+        &self,
+        path: &str,
+        payload: &P,
+    ) -> Result<(), ClientError> {
+        let resp = self
+            .authorized(self.http.post(self.url(path)))?
+            .json(payload)
+            .send()
+            .await?;
+        process_error(resp).await?;
+        Ok(())
+    }
+
+    pub async fn grpc_client(
+        &self,
+        metadata: MatchMetadata,
+    ) -> Result<ClientServiceClient<InterceptedService<Channel, AuthInterceptor>>, ClientError>
+    {
+        let token = self.profile.token()?;
+        let channel = Channel::from_shared(self.grpc_url())
+            .map_err(|e| ClientError::ApiError(e.to_string()))?
+            .connect()
+            .await?;
+        let interceptor = AuthInterceptor::new(&token, &metadata)?;
+        Ok(ClientServiceClient::with_interceptor(channel, interceptor))
+    }
+
+    /// 按名称或 ID 查找自己的 Agent
+    pub async fn resolve_agent(&self, name_or_id: &str) -> Result<GetAgentResponse, ClientError> {
+        let agents: Vec<GetAgentResponse> = self.get("/agent/agents").await?;
+        let id = Uuid::from_str(name_or_id).ok();
+        agents
+            .into_iter()
+            .find(|a| a.name == name_or_id || Some(a.agent_id) == id)
+            .ok_or_else(|| ClientError::NotFound(format!("agent {}", name_or_id)))
+    }
+
+    pub async fn resolve_agents(&self, names: &[String]) -> Result<Vec<Uuid>, ClientError> {
+        let mut agent_ids = Vec::with_capacity(names.len());
+        for name in names {
+            agent_ids.push(self.resolve_agent(name).await?.agent_id);
+        }
+        Ok(agent_ids)
+    }
+
+    /// 按名称或 ID 查找游戏类型
+    pub async fn resolve_game_type(
+        &self,
+        name_or_id: &str,
+    ) -> Result<GetGameTypeResponse, ClientError> {
+        let game_types: Vec<GetGameTypeResponse> = self.get("/match/gametypes").await?;
+        let id = Uuid::from_str(name_or_id).ok();
+        game_types
+            .into_iter()
+            .find(|g| g.name == name_or_id || Some(g.game_type_id) == id)
+            .ok_or_else(|| ClientError::NotFound(format!("game type {}", name_or_id)))
+    }
+}
+
+/// 为 gRPC 请求附加 JWT 与比赛元数据
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    auth_token: MetadataValue<tonic::metadata::Ascii>,
+    message_metadata: MetadataValue<tonic::metadata::Ascii>,
+}
+
+impl AuthInterceptor {
+    fn new(token: &str, metadata: &MatchMetadata) -> Result<Self, ClientError> {
+        let auth_token = MetadataValue::from_str(&format!("Bearer {}", token))
+            .map_err(|e| ClientError::ApiError(e.to_string()))?;
+        let metadata_bytes = serde_json::to_vec(metadata)?;
+        let message_metadata = MetadataValue::from_str(&BASE64_STANDARD.encode(metadata_bytes))
+            .map_err(|e| ClientError::ApiError(e.to_string()))?;
+        Ok(Self {
+            auth_token,
+            message_metadata,
+        })
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut req: Request<()>) -> Result<Request<()>, Status> {
+        req.metadata_mut()
+            .insert("authorization", self.auth_token.clone());
+        req.metadata_mut()
+            .insert("x-message-metadata", self.message_metadata.clone());
+        Ok(req)
+    }
+}
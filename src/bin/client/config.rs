@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ClientError;
+
+const SERVICE_NAME: &str = "TACKLEBOX";
+const DEFAULT_PROFILE: &str = "default";
+const DEFAULT_SERVICE_URL: &str = "127.0.0.1:3000";
+const DEFAULT_SERVICE_GRPC_URL: &str = "127.0.0.1:50050";
+
+/// 配置文件, 默认位于 `<config_dir>/tacklebox/config.toml`, 可用 TACKLE_BOX_CONFIG 覆盖
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ClientConfig {
+    pub current_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub server_url: String,
+    pub grpc_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// keyring 不可用时才写入配置文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            server_url: DEFAULT_SERVICE_URL.to_string(),
+            grpc_url: DEFAULT_SERVICE_GRPC_URL.to_string(),
+            username: None,
+            token: None,
+        }
+    }
+}
+
+/// 本次调用实际生效的 profile, 已合并命令行与环境变量覆盖
+#[derive(Debug, Clone)]
+pub struct ActiveProfile {
+    pub name: String,
+    pub profile: Profile,
+}
+
+impl ClientConfig {
+    pub fn path() -> Result<PathBuf, ClientError> {
+        if let Ok(path) = env::var("TACKLE_BOX_CONFIG") {
+            return Ok(PathBuf::from(path));
+        }
+        let dir = dirs::config_dir()
+            .ok_or_else(|| ClientError::Config("cannot locate config directory".to_string()))?;
+        Ok(dir.join("tacklebox").join("config.toml"))
+    }
+
+    pub fn load() -> Result<Self, ClientError> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content)
+            .map_err(|e| ClientError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self) -> Result<(), ClientError> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content =
+            toml::to_string_pretty(self).map_err(|e| ClientError::Config(e.to_string()))?;
+        open_private(&path)?.write_all(content.as_bytes())?;
+        Ok(())
+    }
+
+    /// 依次按 `--profile`、TACKLE_BOX_PROFILE、配置中的 current_profile 选择 profile
+    pub fn active_profile_name(&self, flag: Option<&str>) -> String {
+        flag.map(str::to_string)
+            .or_else(|| env::var("TACKLE_BOX_PROFILE").ok())
+            .or_else(|| self.current_profile.clone())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+    }
+
+    pub fn resolve(&self, flag: Option<&str>) -> Result<ActiveProfile, ClientError> {
+        let name = self.active_profile_name(flag);
+        let mut profile = match self.profiles.get(&name) {
+            Some(profile) => profile.clone(),
+            None if name == DEFAULT_PROFILE => Profile::default(),
+            None => return Err(ClientError::NotFound(format!("profile {}", name))),
+        };
+        if let Ok(url) = env::var("TACKLE_BOX_SERVER_URL") {
+            profile.server_url = url;
+        }
+        if let Ok(url) = env::var("TACKLE_BOX_GRPC_URL") {
+            profile.grpc_url = url;
+        }
+        Ok(ActiveProfile { name, profile })
+    }
+
+    /// 修改 profile, 不存在时以默认地址创建
+    pub fn profile_mut(&mut self, name: &str) -> &mut Profile {
+        self.profiles.entry(name.to_string()).or_default()
+    }
+}
+
+/// 配置文件中可能有 token, 只允许当前用户读写
+fn open_private(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // mode 只对新建的文件生效, 已有的文件先收紧权限再写入
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)
+}
+
+fn keyring_entry(profile_name: &str) -> Result<Entry, keyring::Error> {
+    Entry::new(SERVICE_NAME, &format!("profile:{}", profile_name))
+}
+
+impl Default for ActiveProfile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            profile: Profile::default(),
+        }
+    }
+}
+
+impl ActiveProfile {
+    /// TACKLE_BOX_TOKEN 优先, 其次是 keyring, 最后是配置文件中的 token
+    pub fn token(&self) -> Result<String, ClientError> {
+        if let Ok(token) = env::var("TACKLE_BOX_TOKEN") {
+            return Ok(token);
+        }
+        if let Ok(token) = keyring_entry(&self.name).and_then(|entry| entry.get_password()) {
+            return Ok(token);
+        }
+        self.profile.token.clone().ok_or(ClientError::NotLogin)
+    }
+}
+
+/// 保存登录凭证, keyring 不可用时退回到配置文件
+pub fn store_token(
+    config: &mut ClientConfig,
+    profile_name: &str,
+    username: &str,
+    token: &str,
+) -> Result<(), ClientError> {
+    let stored = keyring_entry(profile_name)
+        .and_then(|entry| entry.set_password(token))
+        .is_ok();
+    let profile = config.profile_mut(profile_name);
+    profile.username = Some(username.to_string());
+    if stored {
+        profile.token = None;
+    } else {
+        eprintln!(
+            "keyring is unavailable, token is stored in {} (readable only by you)",
+            ClientConfig::path()?.display()
+        );
+        profile.token = Some(token.to_string());
+    }
+    config.save()
+}
+
+pub fn clear_token(config: &mut ClientConfig, profile_name: &str) -> Result<(), ClientError> {
+    if let Ok(entry) = keyring_entry(profile_name) {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if let Some(profile) = config.profiles.get_mut(profile_name) {
+        profile.token = None;
+        profile.username = None;
+    }
+    config.save()
+}
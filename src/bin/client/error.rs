@@ -0,0 +1,32 @@
+use thiserror::Error;
+use tonic::Status;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("internal error")]
+    KeyRing(#[from] keyring::Error),
+    #[error("connection error")]
+    Connection(#[from] reqwest::Error),
+    #[error("serde error")]
+    Serde(#[from] serde_json::Error),
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+    #[error("api error with {0}")]
+    ApiError(String),
+    #[error("not login")]
+    NotLogin,
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("config error: {0}")]
+    Config(String),
+    #[error("grpc error")]
+    GrpcError(#[from] tonic::transport::Error),
+    #[error("grpc connect")]
+    GrpcConnectError(#[from] Status),
+    #[error("sub process error: {0}")]
+    SubProcess(String),
+    #[error("std handle error")]
+    StdinHandler,
+    #[error("sandbox error: {0}")]
+    Sandbox(String),
+}
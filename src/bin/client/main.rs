@@ -0,0 +1,586 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Parser, Subcommand};
+use serde_json::Value;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use crate::{
+    agents::{AgentChanges, PolicyArg},
+    api::ApiClient,
+    config::{ActiveProfile, ClientConfig},
+    error::ClientError,
+    matches::{ExportOptions, MatchOptions},
+    output::{print_json, OutputFormat},
+    runner::{AgentCommand, RunOptions},
+    sandbox::SandboxGame,
+    seasons::SeasonOptions,
+    tournaments::TournamentOptions,
+};
+
+mod agents;
+mod api;
+mod config;
+mod error;
+mod matches;
+mod output;
+mod profile;
+mod runner;
+mod sandbox;
+mod seasons;
+mod tournaments;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// 输出格式
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+    /// 使用的 profile, 也可通过 TACKLE_BOX_PROFILE 指定
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// 管理 Agent 注册、配置和密钥
+    Agent {
+        #[command(subcommand)]
+        command: AgentCommands,
+    },
+    /// 管理比赛的创建、状态和监控
+    Match {
+        #[command(subcommand)]
+        command: MatchCommands,
+    },
+    /// 创建和查看锦标赛
+    Tournament {
+        #[command(subcommand)]
+        command: TournamentCommands,
+    },
+    /// 查看和安排天梯赛季
+    Season {
+        #[command(subcommand)]
+        command: SeasonCommands,
+    },
+    /// 查询可用的游戏类型
+    Game {
+        #[command(subcommand)]
+        command: GameCommands,
+    },
+    /// 账号与服务端 profile 管理
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// 通用系统信息和健康检查
+    Info,
+}
+
+// --- Agent 子命令集 ---
+#[derive(Subcommand, Debug)]
+enum AgentCommands {
+    /// 运行一个本地 Agent 进程，通过 I/O 流接入比赛
+    ///
+    /// 例如: `agent run -a my-agent -- ./my_agent --depth 3`
+    Run {
+        /// 使用的 Agent (名称或 ID)
+        #[arg(short, long)]
+        agent: String,
+        #[command(flatten)]
+        options: RunOptions,
+        /// Agent 命令行, 单个 .py 文件会用 python 启动
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// 不经过服务端, 直接连接 sponsor 在本地对局
+    ///
+    /// 例如: `agent sandbox -g leduc-holdem -p "python bot.py" -p random`
+    Sandbox {
+        /// Sponsor 的 gRPC 地址
+        #[arg(long, default_value = "http://127.0.0.1:50051")]
+        sponsor: String,
+        /// 游戏类型, 直接传给 sponsor
+        #[arg(short, long)]
+        game_type: String,
+        /// 总共进行的场次
+        #[arg(short = 'n', long, default_value_t = 10)]
+        games: i32,
+        /// 按座位顺序指定玩家命令行, `random` 为内置随机玩家; 只给一个时对手为随机玩家
+        #[arg(short, long = "player", required = true)]
+        players: Vec<String>,
+        /// 单步行动超时秒数
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+        /// 比赛种子, 与服务端比赛的种子相同时按同样的牌局进行
+        #[arg(long)]
+        seed: Option<i64>,
+        #[command(flatten)]
+        options: RunOptions,
+    },
+    /// 创建一个新的 Agent
+    Create {
+        /// 新 Agent 的名称
+        name: String,
+        /// 游戏类型 (名称或 ID)
+        #[arg(short, long)]
+        game_type: String,
+        #[arg(short, long, default_value = "0.0.1")]
+        version: String,
+        #[arg(short, long)]
+        description: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = PolicyArg::Idle)]
+        policy: PolicyArg,
+    },
+    /// 更新一个Agent信息, 未指定的字段保持不变
+    Update {
+        /// Agent 名称
+        name: String,
+        /// 新名称
+        #[arg(long)]
+        rename: Option<String>,
+        #[arg(short, long)]
+        game_type: Option<String>,
+        #[arg(short, long)]
+        version: Option<String>,
+        #[arg(short, long)]
+        description: Option<String>,
+        #[arg(short, long, value_enum)]
+        policy: Option<PolicyArg>,
+    },
+    /// 弃用一个 Agent
+    Delete {
+        /// Agent 名称
+        name: String,
+    },
+    /// 查看 Agent 详情
+    Show {
+        /// Agent 名称
+        name: String,
+    },
+    /// 列出 Agent 的历史版本和各版本战绩
+    Versions {
+        /// Agent 名称
+        name: String,
+    },
+    /// 按对手统计逐局胜负和平均得分, 找出针对自己的对手
+    HeadToHead {
+        /// Agent 名称
+        name: String,
+        /// 只看指定对手 (名称或 ID)
+        #[arg(long)]
+        opponent: Option<String>,
+    },
+    /// 对比同一 Agent 的两个版本对各个对手的战绩
+    Compare {
+        /// Agent 名称
+        name: String,
+        /// 基准版本
+        base: String,
+        /// 对比版本
+        target: String,
+    },
+    /// 列出所有Agent
+    List,
+}
+
+// --- Match 子命令集 ---
+#[derive(Subcommand, Debug)]
+enum MatchCommands {
+    /// 提交一个新的比赛请求
+    Create {
+        /// 比赛名称
+        match_name: String,
+        /// 游戏类型 (名称或 ID)
+        #[arg(short, long)]
+        game_type: String,
+        /// 总共进行的场次
+        #[arg(short = 'n', long, default_value_t = 50)]
+        total_games: i32,
+        /// 参与的Agent, 可重复指定
+        #[arg(short, long = "agent")]
+        agents: Vec<String>,
+        #[command(flatten)]
+        options: MatchOptions,
+    },
+    /// 让自己的 Agent 加入比赛
+    Join {
+        match_id: Uuid,
+        /// Agent名称, 可重复指定
+        #[arg(short, long = "agent", required = true)]
+        agents: Vec<String>,
+        /// 密码
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+    /// 让自己的 Agent 退出尚未开始的比赛
+    Leave {
+        match_id: Uuid,
+        /// Agent名称, 可重复指定
+        #[arg(short, long = "agent", required = true)]
+        agents: Vec<String>,
+    },
+    /// 搜索等待加入的比赛
+    Search,
+    /// 列出自己 Agent 参与的比赛
+    List,
+    /// 查看比赛详情与参赛者
+    Show { match_id: Uuid },
+    /// 下载比赛的回合日志
+    Turns {
+        match_id: Uuid,
+        /// 写入的文件路径, 默认输出到终端
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// 实时监控一个比赛的状态和得分
+    Monitor { match_id: Uuid },
+    /// 逐步回放已结束的比赛
+    Replay {
+        match_id: Uuid,
+        /// 只回放指定的一局
+        #[arg(short, long)]
+        game: Option<i32>,
+        /// 按原始节奏播放的速度倍率, 不指定时一次性输出
+        #[arg(long)]
+        speed: Option<f32>,
+    },
+    /// 用存储的种子和动作重新模拟一局, 校验比赛结果
+    Verify {
+        match_id: Uuid,
+        /// 校验的局数
+        #[arg(short, long)]
+        game: i32,
+    },
+    /// 按条件批量导出回合数据 (JSON Lines), 用于离线训练
+    Export {
+        #[command(flatten)]
+        options: ExportOptions,
+    },
+}
+
+// --- Tournament 子命令集 ---
+#[derive(Subcommand, Debug)]
+enum TournamentCommands {
+    /// 创建一个锦标赛
+    Create {
+        /// 锦标赛名称
+        name: String,
+        /// 游戏类型 (名称或 ID)
+        #[arg(short, long)]
+        game_type: String,
+        /// 参赛的Agent, 可重复指定
+        #[arg(short, long = "agent")]
+        agents: Vec<String>,
+        #[command(flatten)]
+        options: TournamentOptions,
+    },
+    /// 让自己的 Agent 报名锦标赛
+    Join {
+        tournament_id: Uuid,
+        /// Agent名称, 可重复指定
+        #[arg(short, long = "agent", required = true)]
+        agents: Vec<String>,
+        /// 密码
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+    /// 停止报名并开始第一轮
+    Start { tournament_id: Uuid },
+    /// 列出所有锦标赛
+    List,
+    /// 查看锦标赛详情、排名和各轮比赛
+    Show { tournament_id: Uuid },
+}
+
+// --- Season 子命令集 ---
+#[derive(Subcommand, Debug)]
+enum SeasonCommands {
+    /// 安排一个赛季, 到点后自动开始和结束
+    Create {
+        /// 赛季名称
+        name: String,
+        /// 游戏类型 (名称或 ID)
+        #[arg(short, long)]
+        game_type: String,
+        #[command(flatten)]
+        options: SeasonOptions,
+    },
+    /// 列出所有赛季
+    List,
+    /// 查看赛季详情和排名
+    Show { season_id: Uuid },
+}
+
+// --- Game 子命令集 ---
+#[derive(Subcommand, Debug)]
+enum GameCommands {
+    /// 列出所有游戏类型
+    List,
+}
+
+// --- Profile 子命令集 ---
+#[derive(Subcommand, Debug)]
+enum ProfileCommands {
+    /// 注册
+    Register {
+        /// 用户名称
+        username: String,
+        /// 用户密码
+        password: String,
+        /// Email
+        email: String,
+    },
+    /// 登录, 凭证保存到当前 profile
+    Login {
+        /// 用户名称
+        username: String,
+        /// 用户密码
+        password: String,
+    },
+    /// 清除当前 profile 的登录凭证
+    Logout,
+    /// 新增或修改一个 profile
+    Add {
+        /// profile 名称
+        name: String,
+        /// HTTP 服务地址
+        #[arg(short, long)]
+        server_url: String,
+        /// gRPC 服务地址
+        #[arg(short, long)]
+        grpc_url: String,
+        /// 同时切换到该 profile
+        #[arg(long = "use")]
+        use_now: bool,
+    },
+    /// 删除一个 profile 及其凭证
+    Remove { name: String },
+    /// 切换默认 profile
+    Use { name: String },
+    /// 列出所有 profile
+    List,
+}
+
+pub struct Context {
+    pub api: ApiClient,
+    pub output: OutputFormat,
+}
+
+impl Context {
+    /// 输出写操作的结果: 表格模式打印提示, JSON 模式打印结构化结果
+    pub fn done(&self, message: &str, result: Value) -> Result<(), ClientError> {
+        match self.output {
+            OutputFormat::Table => {
+                println!("{}", message);
+                Ok(())
+            }
+            OutputFormat::Json => {
+                let mut result = result;
+                if let Value::Object(map) = &mut result {
+                    map.insert("success".to_string(), Value::Bool(true));
+                }
+                print_json(&result)
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ClientError> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    let config = ClientConfig::load()?;
+    let profile = match (&cli.command, config.resolve(cli.profile.as_deref())) {
+        (_, Ok(profile)) => profile,
+        // 当前 profile 失效时仍允许管理 profile
+        (Commands::Profile { .. }, Err(_)) => ActiveProfile::default(),
+        (_, Err(e)) => return Err(e),
+    };
+    let ctx = Context {
+        api: ApiClient::new(profile),
+        output: cli.output,
+    };
+    match cli.command {
+        Commands::Profile { command } => match command {
+            ProfileCommands::Login { username, password } => {
+                profile::handle_login(&ctx, username, password).await?
+            }
+            ProfileCommands::Register {
+                username,
+                password,
+                email,
+            } => profile::handle_register(&ctx, username, password, email).await?,
+            ProfileCommands::Logout => profile::handle_logout(&ctx)?,
+            ProfileCommands::Add {
+                name,
+                server_url,
+                grpc_url,
+                use_now,
+            } => profile::handle_add_profile(&ctx, name, server_url, grpc_url, use_now)?,
+            ProfileCommands::Remove { name } => profile::handle_remove_profile(&ctx, name)?,
+            ProfileCommands::Use { name } => profile::handle_use_profile(&ctx, name)?,
+            ProfileCommands::List => profile::handle_list_profiles(&ctx)?,
+        },
+        Commands::Agent { command } => match command {
+            AgentCommands::Sandbox {
+                sponsor,
+                game_type,
+                games,
+                players,
+                timeout,
+                seed,
+                options,
+            } => {
+                let game = SandboxGame {
+                    sponsor,
+                    game_type,
+                    games,
+                    action_timeout: Duration::from_secs(timeout),
+                    seed,
+                };
+                sandbox::handle_sandbox(&ctx, game, players, options).await?
+            }
+            AgentCommands::Create {
+                name,
+                game_type,
+                version,
+                description,
+                policy,
+            } => {
+                agents::handle_create_agent(&ctx, name, game_type, version, description, policy)
+                    .await?
+            }
+            AgentCommands::Update {
+                name,
+                rename,
+                game_type,
+                version,
+                description,
+                policy,
+            } => {
+                let changes = AgentChanges {
+                    rename,
+                    game_type,
+                    version,
+                    description,
+                    policy,
+                };
+                agents::handle_update_agent(&ctx, name, changes).await?
+            }
+            AgentCommands::Delete { name } => agents::handle_delete_agent(&ctx, name).await?,
+            AgentCommands::Show { name } => agents::handle_show_agent(&ctx, name).await?,
+            AgentCommands::Versions { name } => agents::handle_agent_versions(&ctx, name).await?,
+            AgentCommands::HeadToHead { name, opponent } => {
+                agents::handle_head_to_head(&ctx, name, opponent).await?
+            }
+            AgentCommands::Compare { name, base, target } => {
+                agents::handle_compare_versions(&ctx, name, base, target).await?
+            }
+            AgentCommands::List => agents::handle_list_agents(&ctx).await?,
+            AgentCommands::Run {
+                agent,
+                options,
+                command,
+            } => {
+                let command = AgentCommand::from_args(command, &options)?;
+                runner::handle_run_agent(&ctx, agent, command, options).await?
+            }
+        },
+        Commands::Match { command } => match command {
+            MatchCommands::Create {
+                match_name,
+                game_type,
+                total_games,
+                agents,
+                options,
+            } => {
+                matches::handle_create_match(
+                    &ctx,
+                    match_name,
+                    game_type,
+                    total_games,
+                    agents,
+                    options,
+                )
+                .await?
+            }
+            MatchCommands::Join {
+                match_id,
+                agents,
+                password,
+            } => matches::handle_join_match(&ctx, match_id, agents, password).await?,
+            MatchCommands::Leave { match_id, agents } => {
+                matches::handle_leave_match(&ctx, match_id, agents).await?
+            }
+            MatchCommands::Search => matches::handle_search_matches(&ctx).await?,
+            MatchCommands::List => matches::handle_list_matches(&ctx).await?,
+            MatchCommands::Show { match_id } => matches::handle_show_match(&ctx, match_id).await?,
+            MatchCommands::Turns { match_id, file } => {
+                matches::handle_match_turns(&ctx, match_id, file).await?
+            }
+            MatchCommands::Monitor { match_id } => {
+                matches::handle_monitor_match(&ctx, match_id).await?
+            }
+            MatchCommands::Replay {
+                match_id,
+                game,
+                speed,
+            } => matches::handle_replay_match(&ctx, match_id, game, speed).await?,
+            MatchCommands::Verify { match_id, game } => {
+                matches::handle_verify_match(&ctx, match_id, game).await?
+            }
+            MatchCommands::Export { options } => {
+                matches::handle_export_turns(&ctx, options).await?
+            }
+        },
+        Commands::Tournament { command } => match command {
+            TournamentCommands::Create {
+                name,
+                game_type,
+                agents,
+                options,
+            } => {
+                tournaments::handle_create_tournament(&ctx, name, game_type, agents, options)
+                    .await?
+            }
+            TournamentCommands::Join {
+                tournament_id,
+                agents,
+                password,
+            } => tournaments::handle_join_tournament(&ctx, tournament_id, agents, password).await?,
+            TournamentCommands::Start { tournament_id } => {
+                tournaments::handle_start_tournament(&ctx, tournament_id).await?
+            }
+            TournamentCommands::List => tournaments::handle_list_tournaments(&ctx).await?,
+            TournamentCommands::Show { tournament_id } => {
+                tournaments::handle_show_tournament(&ctx, tournament_id).await?
+            }
+        },
+        Commands::Season { command } => match command {
+            SeasonCommands::Create {
+                name,
+                game_type,
+                options,
+            } => seasons::handle_create_season(&ctx, name, game_type, options).await?,
+            SeasonCommands::List => seasons::handle_list_seasons(&ctx).await?,
+            SeasonCommands::Show { season_id } => {
+                seasons::handle_show_season(&ctx, season_id).await?
+            }
+        },
+        Commands::Game { command } => match command {
+            GameCommands::List => matches::handle_list_game_types(&ctx).await?,
+        },
+        Commands::Info => profile::handle_info(&ctx).await?,
+    }
+
+    Ok(())
+}
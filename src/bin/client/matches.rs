@@ -0,0 +1,641 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use serde_json::json;
+use tackle_box::{
+    connection::{match_monitor_response::EventType, MatchMonitorRequest, MatchReplayRequest},
+    contracts::{
+        grpc::MatchMetadata,
+        payloads::{
+            ExportCursor, ExportFormat, ExportTurnsPayload, GameReplay, GameVerification,
+            GetGameTypeResponse, GetMatchPayload, GetMatchResponse, GetOnlineMatchResponse,
+            GetParticipantsPayload, GetParticipantsResponse, JoinMatchPayload, LeaveMatchPayload,
+            MatchMode, MatchReplayPayload, MatchVerifyPayload, NewMatchPayload, NewMatchResponse,
+            TurnLogResponse,
+        },
+    },
+};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader},
+};
+use uuid::Uuid;
+
+use crate::{
+    error::ClientError,
+    output::{emit, or_dash, print_json, OutputFormat, Table},
+    Context,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ModeArg {
+    Standard,
+    /// 每副牌轮换座位各打一次
+    Duplicate,
+}
+
+impl From<ModeArg> for MatchMode {
+    fn from(mode: ModeArg) -> Self {
+        match mode {
+            ModeArg::Standard => MatchMode::Standard,
+            ModeArg::Duplicate => MatchMode::Duplicate,
+        }
+    }
+}
+
+/// 创建比赛时的可选设置
+#[derive(Args, Debug)]
+pub struct MatchOptions {
+    /// 密码
+    #[arg(short, long)]
+    pub password: Option<String>,
+    /// 比赛种子, 不指定时由服务端随机生成
+    #[arg(long)]
+    pub seed: Option<i64>,
+    /// 比赛模式, duplicate 时场次为牌副数
+    #[arg(short, long, value_enum, default_value_t = ModeArg::Standard)]
+    pub mode: ModeArg,
+}
+
+/// 导出回合时的过滤和输出设置
+#[derive(Args, Debug)]
+pub struct ExportOptions {
+    /// 游戏类型 (名称或 ID)
+    #[arg(short, long)]
+    pub game_type: Option<String>,
+    /// 只导出该 Agent 参与的比赛, 自己的 Agent 可用名称
+    #[arg(short, long)]
+    pub agent: Option<String>,
+    /// 起始时间 (RFC3339 或 YYYY-MM-DD), 包含
+    #[arg(long, value_parser = parse_time)]
+    pub since: Option<DateTime<Utc>>,
+    /// 截止时间 (RFC3339 或 YYYY-MM-DD), 不包含
+    #[arg(long, value_parser = parse_time)]
+    pub until: Option<DateTime<Utc>>,
+    /// 只导出自己的 Agent 参与的比赛
+    #[arg(long)]
+    pub mine: bool,
+    /// 以 gzip 压缩输出
+    #[arg(long)]
+    pub gzip: bool,
+    /// 写入的文件路径, 默认输出到终端
+    #[arg(short, long)]
+    pub file: Option<PathBuf>,
+    /// 最多导出的局数
+    #[arg(long)]
+    pub limit: Option<i64>,
+    /// 从文件的最后一局之后继续导出并追加
+    #[arg(long, requires = "file", conflicts_with = "gzip")]
+    pub resume: bool,
+}
+
+pub fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| date.and_time(Default::default()).and_utc())
+        .map_err(|_| format!("expected RFC3339 or YYYY-MM-DD, got {}", s))
+}
+
+#[derive(Serialize)]
+struct MatchDetail {
+    #[serde(flatten)]
+    one_match: GetMatchResponse,
+    participants: Vec<GetParticipantsResponse>,
+}
+
+fn matches_table(matches: &[GetMatchResponse]) -> Table {
+    let mut table = Table::new(&[
+        "NAME", "MATCH_ID", "GAME", "STATUS", "GAMES", "WINNER", "STARTED",
+    ]);
+    for m in matches {
+        table.push(vec![
+            m.match_name.clone(),
+            m.match_id.to_string(),
+            m.game_type_name.clone(),
+            format!("{:?}", m.status),
+            m.total_games.to_string(),
+            winner_or_draw(m),
+            m.start_time.format("%Y-%m-%d %H:%M").to_string(),
+        ]);
+    }
+    table
+}
+
+fn winner_or_draw(m: &GetMatchResponse) -> String {
+    if m.is_draw {
+        "(draw)".to_string()
+    } else {
+        or_dash(m.winner_agent_name.as_ref())
+    }
+}
+
+pub async fn handle_list_game_types(ctx: &Context) -> Result<(), ClientError> {
+    let game_types: Vec<GetGameTypeResponse> = ctx.api.get("/match/gametypes").await?;
+    emit(ctx.output, &game_types, |game_types| {
+        let mut table = Table::new(&[
+            "NAME",
+            "GAME_TYPE_ID",
+            "SPONSOR",
+            "SLOTS",
+            "RULE",
+            "DRAWS",
+            "DESCRIPTION",
+        ]);
+        for g in game_types {
+            table.push(vec![
+                g.name.clone(),
+                g.game_type_id.to_string(),
+                g.sponsor.clone(),
+                format!("{}-{}", g.min_slots, g.max_slots),
+                format!("{:?}", g.settlement_rule),
+                if g.allow_draws { "yes" } else { "no" }.to_string(),
+                or_dash(g.description.as_ref()),
+            ]);
+        }
+        table
+    })
+}
+
+pub async fn handle_create_match(
+    ctx: &Context,
+    name: String,
+    game_type: String,
+    total_games: i32,
+    with_agent_names: Vec<String>,
+    options: MatchOptions,
+) -> Result<(), ClientError> {
+    let MatchOptions {
+        password,
+        seed,
+        mode,
+    } = options;
+    let game_type = ctx.api.resolve_game_type(&game_type).await?;
+    let with_agent_ids = ctx.api.resolve_agents(&with_agent_names).await?;
+    let payload = NewMatchPayload {
+        name: name.clone(),
+        game_type_id: game_type.game_type_id,
+        total_games,
+        with_agent_ids,
+        password,
+        seed,
+        mode: mode.into(),
+    };
+    let NewMatchResponse { match_id } = ctx.api.post("/match/new", &payload).await?;
+    ctx.done(
+        &format!("Creating match {} successful! match_id: {}", name, match_id),
+        json!({ "match_id": match_id }),
+    )
+}
+
+pub async fn handle_join_match(
+    ctx: &Context,
+    match_id: Uuid,
+    agent_names: Vec<String>,
+    password: Option<String>,
+) -> Result<(), ClientError> {
+    let agent_ids = ctx.api.resolve_agents(&agent_names).await?;
+    let payload = JoinMatchPayload {
+        match_id,
+        agent_ids: agent_ids.clone(),
+        password,
+    };
+    ctx.api.post_unit("/match/join", &payload).await?;
+    ctx.done(
+        "Join match successful!",
+        json!({ "match_id": match_id, "agent_ids": agent_ids }),
+    )
+}
+
+pub async fn handle_leave_match(
+    ctx: &Context,
+    match_id: Uuid,
+    agent_names: Vec<String>,
+) -> Result<(), ClientError> {
+    let agent_ids = ctx.api.resolve_agents(&agent_names).await?;
+    let payload = LeaveMatchPayload {
+        match_id,
+        agent_ids: agent_ids.clone(),
+    };
+    ctx.api.post_unit("/match/leave", &payload).await?;
+    ctx.done(
+        "Leave match successful!",
+        json!({ "match_id": match_id, "agent_ids": agent_ids }),
+    )
+}
+
+pub async fn handle_search_matches(ctx: &Context) -> Result<(), ClientError> {
+    let matches: Vec<GetOnlineMatchResponse> = ctx.api.get("/match/search").await?;
+    emit(ctx.output, &matches, |matches| {
+        let mut table = Table::new(&[
+            "NAME", "MATCH_ID", "GAME", "CREATOR", "SLOTS", "GAMES", "LOCKED",
+        ]);
+        for m in matches {
+            table.push(vec![
+                m.match_name.clone(),
+                m.match_id.to_string(),
+                m.game_type_name.clone(),
+                m.creater_name.clone(),
+                format!("{}/{}", m.current_slots, m.max_slots),
+                m.total_games.to_string(),
+                if m.with_password { "yes" } else { "no" }.to_string(),
+            ]);
+        }
+        table
+    })
+}
+
+pub async fn handle_list_matches(ctx: &Context) -> Result<(), ClientError> {
+    let matches: Vec<GetMatchResponse> = ctx.api.get("/match/matches").await?;
+    emit(ctx.output, &matches, |matches| matches_table(matches))
+}
+
+pub async fn handle_show_match(ctx: &Context, match_id: Uuid) -> Result<(), ClientError> {
+    let one_match: GetMatchResponse = ctx
+        .api
+        .post("/match/get", &GetMatchPayload { match_id })
+        .await?;
+    let participants: Vec<GetParticipantsResponse> = ctx
+        .api
+        .post("/match/participants", &GetParticipantsPayload { match_id })
+        .await?;
+    let detail = MatchDetail {
+        one_match,
+        participants,
+    };
+    if let OutputFormat::Table = ctx.output {
+        match_summary_table(&detail).print();
+        if !detail.participants.is_empty() {
+            println!();
+            participants_table(&detail.participants).print();
+        }
+        return Ok(());
+    }
+    emit(ctx.output, &detail, match_summary_table)
+}
+
+fn match_summary_table(detail: &MatchDetail) -> Table {
+    let m = &detail.one_match;
+    let mut table = Table::new(&["KEY", "VALUE"]);
+    let rows = [
+        ("name", m.match_name.clone()),
+        ("match_id", m.match_id.to_string()),
+        ("game_type", m.game_type_name.clone()),
+        ("creator", m.creater_name.clone()),
+        ("status", format!("{:?}", m.status)),
+        ("mode", format!("{:?}", m.mode)),
+        ("total_games", m.total_games.to_string()),
+        ("winner", winner_or_draw(m)),
+        ("start_time", m.start_time.to_rfc3339()),
+        ("end_time", or_dash(m.end_time.map(|t| t.to_rfc3339()))),
+        ("seed", or_dash(m.seed.map(|s| s.to_string()))),
+    ];
+    for (key, value) in rows {
+        table.push(vec![key.to_string(), value]);
+    }
+    table
+}
+
+/// 参赛者及结算结果, 比赛未结束时结果列为 -
+fn participants_table(participants: &[GetParticipantsResponse]) -> Table {
+    let mut table = Table::new(&[
+        "PLACE",
+        "AGENT",
+        "AGENT_ID",
+        "VERSION",
+        "SCORE",
+        "GAMES_WON",
+        "SEATS",
+    ]);
+    for p in participants {
+        table.push(vec![
+            or_dash(p.placement),
+            p.agent_name.clone(),
+            p.agent_id.to_string(),
+            or_dash(p.version.as_ref()),
+            or_dash(p.final_score.map(|s| format!("{:.2}", s))),
+            or_dash(p.games_won),
+            or_dash(p.seats.as_ref().map(|seats| {
+                seats
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })),
+        ]);
+    }
+    table
+}
+
+/// 下载比赛的全部回合日志, 指定文件时写入文件
+pub async fn handle_match_turns(
+    ctx: &Context,
+    match_id: Uuid,
+    file: Option<PathBuf>,
+) -> Result<(), ClientError> {
+    let turns: Vec<TurnLogResponse> = ctx
+        .api
+        .post("/match/turns", &GetMatchPayload { match_id })
+        .await?;
+    if let Some(file) = file {
+        tokio::fs::write(&file, serde_json::to_vec_pretty(&turns)?).await?;
+        return ctx.done(
+            &format!("Saved {} turns to {}", turns.len(), file.display()),
+            json!({ "match_id": match_id, "turns": turns.len(), "file": file }),
+        );
+    }
+    emit(ctx.output, &turns, |turns| {
+        let mut table = Table::new(&["TURN", "TURN_ID", "STEPS", "SCORE_DELTAS"]);
+        for t in turns {
+            let steps = t.log.as_array().map(|l| l.len()).unwrap_or(0);
+            table.push(vec![
+                t.i_turn.to_string(),
+                t.turn_id.to_string(),
+                steps.to_string(),
+                t.score_deltas.to_string(),
+            ]);
+        }
+        table
+    })
+}
+
+/// 通过 gRPC 实时监控比赛, 比赛结束后服务端关闭流
+pub async fn handle_monitor_match(ctx: &Context, match_id: Uuid) -> Result<(), ClientError> {
+    let mut client = ctx
+        .api
+        .grpc_client(MatchMetadata::MatchMonitor { match_id })
+        .await?;
+    let resp = client.match_monitor(MatchMonitorRequest {}).await?;
+    let mut in_stream = resp.into_inner();
+    eprintln!("Monitoring match {} ...", match_id);
+    while let Some(msg) = in_stream.message().await? {
+        let Some(event) = msg.event_type else {
+            continue;
+        };
+        match ctx.output {
+            OutputFormat::Json => {
+                let line = match event {
+                    EventType::MatchUpdate(up) => json!({
+                        "timestamp": msg.timestamp,
+                        "type": "match_update",
+                        "current_status": up.current_status,
+                        "message": up.message,
+                    }),
+                    EventType::ScoreChange(s) => json!({
+                        "timestamp": msg.timestamp,
+                        "type": "score_change",
+                        "source_i_turn": s.source_i_turn,
+                        "agent_scores": s.agent_scores,
+                    }),
+                };
+                println!("{}", line);
+            }
+            OutputFormat::Table => match event {
+                EventType::MatchUpdate(up) => {
+                    println!("[{}] {}", up.current_status, up.message);
+                }
+                EventType::ScoreChange(s) => {
+                    println!(
+                        "Turn {} with Score Changes: {:?}",
+                        s.source_i_turn, s.agent_scores
+                    );
+                }
+            },
+        }
+    }
+    eprintln!("Match stream closed.");
+    Ok(())
+}
+
+fn print_step(
+    i_turn: i32,
+    seat: Option<i32>,
+    action: Option<&str>,
+    is_over: bool,
+    logs: &[String],
+) {
+    let seat = or_dash(seat);
+    match action {
+        Some(action) => println!("game {} seat {} -> {}", i_turn, seat, action),
+        None if is_over => println!("game {} over", i_turn),
+        None => println!("game {} seat {} to act", i_turn, seat),
+    }
+    for log in logs {
+        println!("    | {}", log);
+    }
+}
+
+/// 回放比赛; 指定 speed 时通过 gRPC 按原始节奏逐步播放
+pub async fn handle_replay_match(
+    ctx: &Context,
+    match_id: Uuid,
+    i_turn: Option<i32>,
+    speed: Option<f32>,
+) -> Result<(), ClientError> {
+    let Some(speed) = speed else {
+        let payload = MatchReplayPayload { match_id, i_turn };
+        let replays: Vec<GameReplay> = ctx.api.post("/match/replay", &payload).await?;
+        return match ctx.output {
+            OutputFormat::Json => print_json(&replays),
+            OutputFormat::Table => {
+                for replay in &replays {
+                    for step in &replay.steps {
+                        print_step(
+                            replay.i_turn,
+                            step.seat,
+                            step.action.as_deref(),
+                            step.is_over,
+                            &step.logs,
+                        );
+                    }
+                    println!("game {} payoffs: {}", replay.i_turn, replay.payoffs);
+                }
+                Ok(())
+            }
+        };
+    };
+
+    let mut client = ctx
+        .api
+        .grpc_client(MatchMetadata::MatchReplay { match_id })
+        .await?;
+    let resp = client
+        .match_replay(MatchReplayRequest { i_turn, speed })
+        .await?;
+    let mut in_stream = resp.into_inner();
+    while let Some(step) = in_stream.message().await? {
+        match ctx.output {
+            OutputFormat::Json => println!(
+                "{}",
+                json!({
+                    "i_turn": step.i_turn,
+                    "index": step.index,
+                    "seat": step.seat,
+                    "state": step.state,
+                    "action": step.action,
+                    "is_over": step.is_over,
+                    "timestamp": step.timestamp,
+                    "logs": step.logs,
+                    "payoffs": step.payoffs,
+                })
+            ),
+            OutputFormat::Table => {
+                print_step(
+                    step.i_turn,
+                    step.seat,
+                    step.action.as_deref(),
+                    step.is_over,
+                    &step.logs,
+                );
+                if !step.payoffs.is_empty() {
+                    println!("game {} payoffs: {:?}", step.i_turn, step.payoffs);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 让服务端用存储的种子和动作重新模拟一局, 检查结果是否一致
+pub async fn handle_verify_match(
+    ctx: &Context,
+    match_id: Uuid,
+    i_turn: i32,
+) -> Result<(), ClientError> {
+    let payload = MatchVerifyPayload { match_id, i_turn };
+    let result: GameVerification = ctx.api.post("/match/verify", &payload).await?;
+    emit(ctx.output, &result, |r| {
+        let mut table = Table::new(&["KEY", "VALUE"]);
+        let rows = [
+            ("match_id", r.match_id.to_string()),
+            ("game", r.i_turn.to_string()),
+            ("seed", r.seed.to_string()),
+            ("verified", r.verified.to_string()),
+            ("checked_states", r.checked_states.to_string()),
+            ("mismatch", or_dash(r.mismatch.as_ref())),
+        ];
+        for (key, value) in rows {
+            table.push(vec![key.to_string(), value]);
+        }
+        table
+    })
+}
+
+/// 流式导出回合数据, 用于离线训练
+pub async fn handle_export_turns(ctx: &Context, options: ExportOptions) -> Result<(), ClientError> {
+    let game_type_id = match &options.game_type {
+        Some(game_type) => Some(ctx.api.resolve_game_type(game_type).await?.game_type_id),
+        None => None,
+    };
+    // 其他用户的 Agent 只能用 ID 指定
+    let agent_id = match &options.agent {
+        Some(agent) => match Uuid::from_str(agent) {
+            Ok(agent_id) => Some(agent_id),
+            Err(_) => Some(ctx.api.resolve_agent(agent).await?.agent_id),
+        },
+        None => None,
+    };
+    let after = match (&options.file, options.resume) {
+        (Some(file), true) => resume_point(file).await?,
+        _ => None,
+    };
+    let format = if options.gzip {
+        ExportFormat::Gzip
+    } else {
+        ExportFormat::Jsonl
+    };
+    let payload = ExportTurnsPayload {
+        game_type_id,
+        agent_id,
+        from: options.since,
+        to: options.until,
+        own_only: options.mine,
+        format,
+        after,
+        limit: options.limit,
+    };
+    let mut resp = ctx.api.post_stream("/match/export", &payload).await?;
+
+    let Some(file) = options.file else {
+        let mut stdout = io::stdout();
+        while let Some(chunk) = resp.chunk().await? {
+            stdout.write_all(&chunk).await?;
+        }
+        stdout.flush().await?;
+        return Ok(());
+    };
+    let mut writer = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(options.resume)
+        .truncate(!options.resume)
+        .open(&file)
+        .await?;
+    let (mut bytes, mut lines) = (0, 0);
+    while let Some(chunk) = resp.chunk().await? {
+        writer.write_all(&chunk).await?;
+        bytes += chunk.len();
+        lines += chunk.iter().filter(|&&b| b == b'\n').count();
+    }
+    writer.flush().await?;
+    if options.gzip {
+        ctx.done(
+            &format!("Saved {} bytes to {}", bytes, file.display()),
+            json!({ "file": file, "bytes": bytes }),
+        )
+    } else {
+        ctx.done(
+            &format!("Saved {} turns to {}", lines, file.display()),
+            json!({ "file": file, "turns": lines, "bytes": bytes }),
+        )
+    }
+}
+
+/// 找到已导出文件中最后一个完整的回合作为游标, 中断留下的半行会被截掉
+async fn resume_point(file: &Path) -> Result<Option<ExportCursor>, ClientError> {
+    let mut reader = match File::open(file).await {
+        Ok(f) => BufReader::new(f),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let (mut offset, mut good_end) = (0u64, 0u64);
+    let mut cursor = None;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line).await?;
+        if n == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        offset += n as u64;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            good_end = offset;
+            continue;
+        }
+        match serde_json::from_slice::<ExportCursor>(&line) {
+            Ok(last) => {
+                cursor = Some(last);
+                good_end = offset;
+            }
+            Err(_) => break,
+        }
+    }
+    let len = tokio::fs::metadata(file).await?.len();
+    if good_end < len {
+        OpenOptions::new()
+            .write(true)
+            .open(file)
+            .await?
+            .set_len(good_end)
+            .await?;
+    }
+    Ok(cursor)
+}
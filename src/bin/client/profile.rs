@@ -0,0 +1,197 @@
+use serde::Serialize;
+use serde_json::json;
+use tackle_box::contracts::payloads::{
+    GetUserResponse, LoginPayload, LoginResponse, RegisterPayload,
+};
+
+use crate::{
+    config::{clear_token, store_token, ClientConfig, Profile},
+    error::ClientError,
+    output::{emit, or_dash, print_json, OutputFormat, Table},
+    Context,
+};
+
+#[derive(Serialize)]
+struct ProfileEntry {
+    name: String,
+    current: bool,
+    #[serde(flatten)]
+    profile: Profile,
+}
+
+pub async fn handle_login(
+    ctx: &Context,
+    username: String,
+    password: String,
+) -> Result<(), ClientError> {
+    let profile_name = &ctx.api.profile().name;
+    eprintln!(
+        "Attempting to log in as {} on profile {}...",
+        username, profile_name
+    );
+    let payload = LoginPayload {
+        username: username.clone(),
+        password,
+    };
+    let LoginResponse { user_id, token, .. } =
+        ctx.api.post_anonymous("/auth/login", &payload).await?;
+    let mut config = ClientConfig::load()?;
+    store_token(&mut config, profile_name, &username, &token)?;
+    ctx.done(
+        "Login successful!",
+        json!({ "user_id": user_id, "profile": profile_name }),
+    )
+}
+
+pub async fn handle_register(
+    ctx: &Context,
+    username: String,
+    password: String,
+    email: String,
+) -> Result<(), ClientError> {
+    let profile_name = &ctx.api.profile().name;
+    eprintln!("Attempting to register new user {}...", username);
+    let payload = RegisterPayload {
+        username: username.clone(),
+        password,
+        email,
+    };
+    let LoginResponse { user_id, token, .. } =
+        ctx.api.post_anonymous("/auth/register", &payload).await?;
+    let mut config = ClientConfig::load()?;
+    store_token(&mut config, profile_name, &username, &token)?;
+    ctx.done(
+        "Registration successful!",
+        json!({ "user_id": user_id, "profile": profile_name }),
+    )
+}
+
+pub fn handle_logout(ctx: &Context) -> Result<(), ClientError> {
+    let profile_name = &ctx.api.profile().name;
+    let mut config = ClientConfig::load()?;
+    clear_token(&mut config, profile_name)?;
+    ctx.done(
+        &format!("Logged out from profile {}", profile_name),
+        json!({ "profile": profile_name }),
+    )
+}
+
+pub fn handle_add_profile(
+    ctx: &Context,
+    name: String,
+    server_url: String,
+    grpc_url: String,
+    use_now: bool,
+) -> Result<(), ClientError> {
+    let mut config = ClientConfig::load()?;
+    let profile = config.profile_mut(&name);
+    profile.server_url = server_url;
+    profile.grpc_url = grpc_url;
+    if use_now {
+        config.current_profile = Some(name.clone());
+    }
+    config.save()?;
+    ctx.done(
+        &format!("Profile {} saved", name),
+        json!({ "profile": name, "current": use_now }),
+    )
+}
+
+pub fn handle_remove_profile(ctx: &Context, name: String) -> Result<(), ClientError> {
+    let mut config = ClientConfig::load()?;
+    if !config.profiles.contains_key(&name) {
+        return Err(ClientError::NotFound(format!("profile {}", name)));
+    }
+    clear_token(&mut config, &name)?;
+    config.profiles.remove(&name);
+    if config.current_profile.as_deref() == Some(name.as_str()) {
+        config.current_profile = None;
+    }
+    config.save()?;
+    ctx.done(
+        &format!("Profile {} removed", name),
+        json!({ "profile": name }),
+    )
+}
+
+pub fn handle_use_profile(ctx: &Context, name: String) -> Result<(), ClientError> {
+    let mut config = ClientConfig::load()?;
+    if !config.profiles.contains_key(&name) {
+        return Err(ClientError::NotFound(format!(
+            "profile {}, create it with `profile add` first",
+            name
+        )));
+    }
+    config.current_profile = Some(name.clone());
+    config.save()?;
+    ctx.done(
+        &format!("Switched to profile {}", name),
+        json!({ "profile": name }),
+    )
+}
+
+pub fn handle_list_profiles(ctx: &Context) -> Result<(), ClientError> {
+    let config = ClientConfig::load()?;
+    let current = config.active_profile_name(None);
+    let entries: Vec<ProfileEntry> = config
+        .profiles
+        .into_iter()
+        .map(|(name, mut profile)| {
+            // 不在输出中暴露凭证
+            profile.token = None;
+            ProfileEntry {
+                current: name == current,
+                name,
+                profile,
+            }
+        })
+        .collect();
+    emit(ctx.output, &entries, |entries| {
+        let mut table = Table::new(&["", "NAME", "SERVER", "GRPC", "USER"]);
+        for e in entries {
+            table.push(vec![
+                if e.current { "*" } else { "" }.to_string(),
+                e.name.clone(),
+                e.profile.server_url.clone(),
+                e.profile.grpc_url.clone(),
+                or_dash(e.profile.username.as_ref()),
+            ]);
+        }
+        table
+    })
+}
+
+/// 输出当前连接的服务端和登录用户
+pub async fn handle_info(ctx: &Context) -> Result<(), ClientError> {
+    let me = ctx.api.get::<GetUserResponse>("/auth/me").await;
+    let (user, error) = match me {
+        Ok(user) => (Some(user), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    match ctx.output {
+        OutputFormat::Json => print_json(&json!({
+            "profile": ctx.api.profile().name,
+            "server_url": ctx.api.server_url(),
+            "grpc_url": ctx.api.grpc_url(),
+            "user": user,
+            "error": error,
+        })),
+        OutputFormat::Table => {
+            let mut table = Table::new(&["KEY", "VALUE"]);
+            table.push(vec!["profile".into(), ctx.api.profile().name.clone()]);
+            table.push(vec!["server".into(), ctx.api.server_url()]);
+            table.push(vec!["grpc".into(), ctx.api.grpc_url()]);
+            match (user, error) {
+                (Some(user), _) => {
+                    table.push(vec!["user".into(), user.username]);
+                    table.push(vec!["user_id".into(), user.user_id.to_string()]);
+                    table.push(vec!["registered".into(), user.created_at.to_rfc3339()]);
+                }
+                (None, Some(error)) => table.push(vec!["user".into(), error]),
+                (None, None) => {}
+            }
+            table.print();
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use std::{path::PathBuf, process::ExitStatus, time::Duration};
+
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use tackle_box::{
+    connection::{
+        match_player_request::Payload, AgentLog, AgentResign, MatchPlayerRequest,
+        MatchPlayerResponse,
+    },
+    contracts::grpc::MatchMetadata,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    sync::mpsc::{self, Sender},
+    time::{self, Instant},
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::Status;
+use tracing::{debug, warn};
+
+use crate::{error::ClientError, Context};
+
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// 子进程稳定运行超过该时长后重置退避
+const BACKOFF_RESET: Duration = Duration::from_secs(60);
+/// 比赛结束后等待 Agent 处理 EOF 退出的时间, 超时则强制结束
+const EXIT_GRACE: Duration = Duration::from_secs(5);
+
+/// Agent 进程与 CLI 之间的通信格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Protocol {
+    /// 每行一个状态字符串, 每行一个动作; 只适用于不输出其他内容的 Agent
+    Raw,
+    /// 每行一个状态字符串; 输出以 ACTION/LOG/RESIGN/ERROR 开头, 其余行视为日志
+    Line,
+    /// 每行一个 JSON 帧, 携带比赛和座位信息
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RestartPolicy {
+    Never,
+    OnFailure,
+    Always,
+}
+
+/// 要启动的 Agent 进程
+#[derive(Debug, Clone)]
+pub struct AgentCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub envs: Vec<(String, String)>,
+}
+
+/// Agent 进程的启动与转发参数, `agent run` 和 `agent sandbox` 共用
+#[derive(Debug, Clone, Args)]
+pub struct RunOptions {
+    /// Agent 进程的工作目录
+    #[arg(long)]
+    pub cwd: Option<PathBuf>,
+    /// 传给 Agent 进程的环境变量, 可重复指定
+    #[arg(short, long = "env", value_name = "KEY=VALUE", value_parser = parse_env)]
+    pub envs: Vec<(String, String)>,
+    /// 与 Agent 进程的通信格式
+    #[arg(long, value_enum, default_value_t = Protocol::Line)]
+    pub protocol: Protocol,
+    /// 进程退出后的重启策略
+    #[arg(long, value_enum, default_value_t = RestartPolicy::Never)]
+    pub restart: RestartPolicy,
+    /// 最多重启次数, 默认不限
+    #[arg(long)]
+    pub max_restarts: Option<u32>,
+}
+
+/// JSON 协议下发给 Agent 的状态帧
+#[derive(Serialize)]
+struct StateFrame<'a> {
+    match_id: &'a str,
+    seat: i32,
+    i_turn: i32,
+    state: &'a str,
+}
+
+/// JSON 协议下 Agent 的输出帧, 不带 type 的 `{"action": ...}` 视为动作
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OutputFrame {
+    Typed(TypedFrame),
+    Action { action: String },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TypedFrame {
+    Action {
+        action: String,
+    },
+    Log {
+        message: String,
+    },
+    Resign {
+        #[serde(default)]
+        reason: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Agent 的一行输出
+#[derive(Debug, PartialEq)]
+pub enum AgentOutput {
+    Action(String),
+    Log(String),
+    /// 认输或出错, 比赛按弃权结算
+    Resign {
+        reason: String,
+        error: bool,
+    },
+}
+
+impl From<AgentOutput> for MatchPlayerRequest {
+    fn from(output: AgentOutput) -> Self {
+        let payload = match output {
+            AgentOutput::Action(action) => Payload::Action(action),
+            AgentOutput::Log(message) => Payload::Log(AgentLog { message }),
+            AgentOutput::Resign { reason, error } => Payload::Resign(AgentResign { reason, error }),
+        };
+        MatchPlayerRequest {
+            payload: Some(payload),
+        }
+    }
+}
+
+impl AgentCommand {
+    /// 解析 `--` 之后的命令行, 单个 `.py` 文件沿用以前的 python 启动方式
+    pub fn from_args(mut command: Vec<String>, options: &RunOptions) -> Result<Self, ClientError> {
+        if command.is_empty() {
+            return Err(ClientError::SubProcess("empty agent command".to_string()));
+        }
+        if command.len() == 1 && command[0].ends_with(".py") {
+            command.insert(0, "python".to_string());
+        }
+        let program = command.remove(0);
+        Ok(Self {
+            program,
+            args: command,
+            cwd: options.cwd.clone(),
+            envs: options.envs.clone(),
+        })
+    }
+
+    /// 按 shell 规则拆分一整条命令行, 例如 `"python bot.py --fast"`
+    pub fn parse(line: &str, options: &RunOptions) -> Result<Self, ClientError> {
+        let command = shlex::split(line)
+            .ok_or_else(|| ClientError::SubProcess(format!("invalid command line: {}", line)))?;
+        Self::from_args(command, options)
+    }
+
+    fn spawn(&self) -> Result<Child, ClientError> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::inherit())
+            .kill_on_drop(true);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        command
+            .spawn()
+            .map_err(|e| ClientError::SubProcess(format!("{}: {}", self.program, e)))
+    }
+}
+
+/// 解析 `KEY=VALUE` 形式的环境变量参数
+fn parse_env(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("invalid KEY=VALUE: {}", s))
+}
+
+impl Protocol {
+    fn encode(&self, resp: &MatchPlayerResponse) -> Result<Vec<u8>, ClientError> {
+        let mut line = match self {
+            Protocol::Raw | Protocol::Line => resp.state.clone().into_bytes(),
+            Protocol::Json => serde_json::to_vec(&StateFrame {
+                match_id: &resp.match_id,
+                seat: resp.seat,
+                i_turn: resp.i_turn,
+                state: &resp.state,
+            })?,
+        };
+        line.push(b'\n');
+        Ok(line)
+    }
+
+    fn decode(&self, line: &str) -> AgentOutput {
+        match self {
+            Protocol::Raw => AgentOutput::Action(line.to_string()),
+            Protocol::Line => {
+                let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+                let rest = rest.trim().to_string();
+                match keyword {
+                    "ACTION" => AgentOutput::Action(rest),
+                    "LOG" => AgentOutput::Log(rest),
+                    "RESIGN" => AgentOutput::Resign {
+                        reason: rest,
+                        error: false,
+                    },
+                    "ERROR" => AgentOutput::Resign {
+                        reason: rest,
+                        error: true,
+                    },
+                    // 误打印的内容不会被当作动作
+                    _ => AgentOutput::Log(line.to_string()),
+                }
+            }
+            Protocol::Json => match serde_json::from_str::<OutputFrame>(line) {
+                Ok(OutputFrame::Action { action })
+                | Ok(OutputFrame::Typed(TypedFrame::Action { action })) => {
+                    AgentOutput::Action(action)
+                }
+                Ok(OutputFrame::Typed(TypedFrame::Log { message })) => AgentOutput::Log(message),
+                Ok(OutputFrame::Typed(TypedFrame::Resign { reason })) => AgentOutput::Resign {
+                    reason,
+                    error: false,
+                },
+                Ok(OutputFrame::Typed(TypedFrame::Error { message })) => AgentOutput::Resign {
+                    reason: message,
+                    error: true,
+                },
+                Err(_) => AgentOutput::Log(line.to_string()),
+            },
+        }
+    }
+}
+
+/// 一个正在运行的 Agent 子进程
+struct AgentProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    started_at: Instant,
+}
+
+impl AgentProcess {
+    fn spawn(command: &AgentCommand) -> Result<Self, ClientError> {
+        let mut child = command.spawn()?;
+        let stdin = child.stdin.take().ok_or(ClientError::StdinHandler)?;
+        let stdout = child.stdout.take().ok_or(ClientError::StdinHandler)?;
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            started_at: Instant::now(),
+        })
+    }
+}
+
+/// 单个子进程的结束原因
+enum Exit {
+    /// 服务端关闭了流, 比赛结束
+    ServerClosed,
+    /// 子进程退出
+    Process(ExitStatus),
+}
+
+/// 状态来源与动作去向, 服务端比赛和本地 sandbox 共用
+struct Session<S> {
+    protocol: Protocol,
+    server: S,
+    tx: Sender<MatchPlayerRequest>,
+    /// 已下发但还没收到动作的状态, 子进程重启后重新下发
+    pending: Option<MatchPlayerResponse>,
+}
+
+impl<S> Session<S>
+where
+    S: Stream<Item = Result<MatchPlayerResponse, Status>> + Unpin,
+{
+    async fn forward_state(
+        &mut self,
+        agent: &mut AgentProcess,
+        resp: MatchPlayerResponse,
+    ) -> Result<(), ClientError> {
+        let line = self.protocol.encode(&resp)?;
+        self.pending = Some(resp);
+        if let Err(e) = agent.stdin.write_all(&line).await {
+            // 子进程已退出, 由 wait 分支处理
+            warn!("failed to write state to agent stdin: {}", e);
+        }
+        Ok(())
+    }
+
+    async fn forward_output(&mut self, line: &str) -> Result<(), ClientError> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let output = self.protocol.decode(line);
+        match &output {
+            AgentOutput::Action(_) => self.pending = None,
+            AgentOutput::Log(message) => eprintln!("[agent] {}", message),
+            AgentOutput::Resign { reason, error } => {
+                self.pending = None;
+                let kind = if *error { "error" } else { "resign" };
+                eprintln!("Agent reported {}: {}", kind, reason);
+            }
+        }
+        self.tx
+            .send(output.into())
+            .await
+            .map_err(|_| ClientError::ApiError("player stream closed".to_string()))
+    }
+
+    async fn drive(&mut self, agent: &mut AgentProcess) -> Result<Exit, ClientError> {
+        if let Some(resp) = self.pending.clone() {
+            eprintln!("Replaying pending state to restarted agent");
+            self.forward_state(agent, resp).await?;
+        }
+        let mut stdout_open = true;
+        loop {
+            tokio::select! {
+                resp = self.server.next() => match resp {
+                    Some(Ok(resp)) => {
+                        debug!("response is {:?}", &resp);
+                        self.forward_state(agent, resp).await?;
+                    }
+                    None => return Ok(Exit::ServerClosed),
+                    Some(Err(e)) => {
+                        eprintln!("gRPC response stream error: {}", e);
+                        return Ok(Exit::ServerClosed);
+                    }
+                },
+                line = agent.stdout.next_line(), if stdout_open => match line {
+                    Ok(Some(line)) => self.forward_output(&line).await?,
+                    Ok(None) => stdout_open = false,
+                    Err(e) => {
+                        eprintln!("Error reading from agent stdout: {}", e);
+                        stdout_open = false;
+                    }
+                },
+                status = agent.child.wait() => {
+                    let status = status?;
+                    // 进程退出前写出的动作仍然需要转发
+                    while let Ok(Some(line)) = agent.stdout.next_line().await {
+                        self.forward_output(&line).await?;
+                    }
+                    return Ok(Exit::Process(status));
+                }
+            }
+        }
+    }
+}
+
+pub async fn handle_run_agent(
+    ctx: &Context,
+    agent: String,
+    command: AgentCommand,
+    options: RunOptions,
+) -> Result<(), ClientError> {
+    let agent_id = ctx.api.resolve_agent(&agent).await?.agent_id;
+    let mut client = ctx
+        .api
+        .grpc_client(MatchMetadata::MatchPlayer { agent_id })
+        .await?;
+    debug!("successful make client");
+
+    let (tx, rx) = mpsc::channel(16);
+    let response = client
+        // request_stream 才是实现了 Stream Trait 的类型
+        .match_player(tonic::Request::new(ReceiverStream::new(rx)))
+        .await?;
+    debug!("get response successful");
+
+    run_bridge(&command, &options, response.into_inner(), tx).await
+}
+
+/// 启动 Agent 进程并在 states 与进程的 stdin/stdout 之间转发, 按策略重启,
+/// 直到 states 结束
+pub async fn run_bridge<S>(
+    command: &AgentCommand,
+    options: &RunOptions,
+    states: S,
+    actions: Sender<MatchPlayerRequest>,
+) -> Result<(), ClientError>
+where
+    S: Stream<Item = Result<MatchPlayerResponse, Status>> + Unpin,
+{
+    let mut session = Session {
+        protocol: options.protocol,
+        server: states,
+        tx: actions,
+        pending: None,
+    };
+    let mut restarts = 0u32;
+    let mut backoff = BACKOFF_BASE;
+    loop {
+        eprintln!(
+            "Launching agent: {} {}",
+            command.program,
+            command.args.join(" ")
+        );
+        let mut agent = AgentProcess::spawn(command)?;
+        let status = match session.drive(&mut agent).await? {
+            Exit::ServerClosed => {
+                // 关闭 stdin 发送 EOF, 让 Agent 优雅退出; shutdown 只会 flush 管道
+                let AgentProcess {
+                    mut child, stdin, ..
+                } = agent;
+                drop(stdin);
+                match time::timeout(EXIT_GRACE, child.wait()).await {
+                    Ok(status) => {
+                        eprintln!("Match stream closed, agent exited with {}", status?)
+                    }
+                    Err(_) => {
+                        eprintln!("Match stream closed, agent ignored EOF and was killed");
+                        child.kill().await?;
+                    }
+                }
+                return Ok(());
+            }
+            Exit::Process(status) => status,
+        };
+        eprintln!("Agent process exited with {}", status);
+
+        let restart = match options.restart {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !status.success(),
+            RestartPolicy::Always => true,
+        };
+        if !restart {
+            return Ok(());
+        }
+        if options.max_restarts.is_some_and(|max| restarts >= max) {
+            return Err(ClientError::SubProcess(format!(
+                "agent exceeded {} restarts",
+                restarts
+            )));
+        }
+        if agent.started_at.elapsed() >= BACKOFF_RESET {
+            backoff = BACKOFF_BASE;
+        }
+        restarts += 1;
+        eprintln!("Restarting agent in {:?} (restart #{})", backoff, restarts);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
@@ -0,0 +1,386 @@
+use std::time::Duration;
+
+use rand::seq::IndexedRandom;
+use serde::Serialize;
+use serde_json::Value;
+use tackle_box::{
+    connection::{
+        game_control::ControlType, game_init_response::ResultType, match_player_request::Payload,
+        process_game_request::RequestType, process_game_response::ResponseType,
+        sponsor_service_client::SponsorServiceClient, AgentResign, GameControl, GameEndStatus,
+        GameInitRequest, GameStateUpdate, MatchPlayerRequest, MatchPlayerResponse, PlayerAction,
+        ProcessGameRequest,
+    },
+    contracts::seed::game_seed,
+};
+use tokio::{
+    sync::mpsc::{self, Receiver, Sender},
+    task::JoinSet,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::{
+    error::ClientError,
+    output::{emit, or_dash, Table},
+    runner::{run_bridge, AgentCommand, AgentOutput, RunOptions},
+    Context,
+};
+
+/// 内置随机玩家的名称
+const RANDOM_PLAYER: &str = "random";
+
+enum PlayerSpec {
+    Agent(AgentCommand),
+    Random,
+}
+
+/// 本地比赛中的一个座位, 通过和服务端比赛相同的消息与玩家交互
+struct Seat {
+    name: String,
+    state_tx: Sender<Result<MatchPlayerResponse, Status>>,
+    action_rx: Receiver<MatchPlayerRequest>,
+}
+
+#[derive(Serialize)]
+struct SeatResult {
+    seat: usize,
+    player: String,
+    total: f32,
+    mean: f32,
+}
+
+/// 认输或出错的座位, 与服务端相同, 该局判负且不再进行后面的局
+#[derive(Serialize)]
+struct SandboxForfeit {
+    /// 弃权发生的局
+    game: i32,
+    seat: usize,
+    reason: String,
+    error: bool,
+}
+
+/// 已完成各局的 payoffs, 以及提前结束比赛的弃权
+struct Played {
+    payoffs: Vec<Vec<f32>>,
+    forfeit: Option<SandboxForfeit>,
+}
+
+#[derive(Serialize)]
+struct SandboxResult {
+    game_type: String,
+    seed: Option<i64>,
+    games_requested: i32,
+    /// 有弃权时少于请求的局数
+    games_completed: usize,
+    payoffs: Vec<Vec<f32>>,
+    forfeit: Option<SandboxForfeit>,
+    seats: Vec<SeatResult>,
+}
+
+/// 本地比赛的设置
+pub struct SandboxGame {
+    pub sponsor: String,
+    pub game_type: String,
+    pub games: i32,
+    /// 单步行动超时
+    pub action_timeout: Duration,
+    /// 比赛种子, 与服务端相同的派生方式, 给定后可以复现服务端的对局
+    pub seed: Option<i64>,
+}
+
+/// 直接连接 sponsor, 在本地进程之间进行若干局比赛, 不经过服务端
+pub async fn handle_sandbox(
+    ctx: &Context,
+    game: SandboxGame,
+    players: Vec<String>,
+    options: RunOptions,
+) -> Result<(), ClientError> {
+    let mut specs = players
+        .iter()
+        .map(|p| match p.as_str() {
+            RANDOM_PLAYER => Ok(PlayerSpec::Random),
+            line => AgentCommand::parse(line, &options).map(PlayerSpec::Agent),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // 只给了一个 Agent 时和随机玩家对局
+    if specs.len() == 1 {
+        specs.push(PlayerSpec::Random);
+    }
+
+    let mut tasks = JoinSet::new();
+    let mut seats: Vec<Seat> = Vec::new();
+    for spec in specs {
+        let (state_tx, state_rx) = mpsc::channel(16);
+        let (action_tx, action_rx) = mpsc::channel(16);
+        let name = match spec {
+            PlayerSpec::Agent(command) => {
+                let name = format!("{} {}", command.program, command.args.join(" "));
+                let options = options.clone();
+                tasks.spawn(async move {
+                    run_bridge(&command, &options, ReceiverStream::new(state_rx), action_tx).await
+                });
+                name
+            }
+            PlayerSpec::Random => {
+                tasks.spawn(random_player(state_rx, action_tx));
+                RANDOM_PLAYER.to_string()
+            }
+        };
+        seats.push(Seat {
+            name: name.trim().to_string(),
+            state_tx,
+            action_rx,
+        });
+    }
+
+    let result = play(&game, &mut seats).await;
+    // 关闭状态流, 让 Agent 进程收到 EOF 后退出
+    let names: Vec<String> = seats.into_iter().map(|seat| seat.name).collect();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(Err(e)) => eprintln!("Player stopped with error: {}", e),
+            Err(e) => eprintln!("Player task failed: {}", e),
+            Ok(Ok(())) => {}
+        }
+    }
+    let Played { payoffs, forfeit } = result?;
+
+    let seats = names
+        .into_iter()
+        .enumerate()
+        .map(|(seat, player)| {
+            let total: f32 = payoffs.iter().filter_map(|game| game.get(seat)).sum();
+            SeatResult {
+                seat,
+                player,
+                total,
+                mean: total / payoffs.len().max(1) as f32,
+            }
+        })
+        .collect();
+    let result = SandboxResult {
+        game_type: game.game_type,
+        seed: game.seed,
+        games_requested: game.games,
+        games_completed: payoffs.len(),
+        payoffs,
+        forfeit,
+        seats,
+    };
+    emit(ctx.output, &result, |result| {
+        let mut table = Table::new(&["SEAT", "PLAYER", "GAMES", "TOTAL", "MEAN", "FORFEIT"]);
+        for s in &result.seats {
+            let forfeit = result
+                .forfeit
+                .as_ref()
+                .filter(|f| f.seat == s.seat)
+                .map(|f| {
+                    let kind = if f.error { "error" } else { "resign" };
+                    format!("game {} {}: {}", f.game, kind, f.reason)
+                });
+            table.push(vec![
+                s.seat.to_string(),
+                s.player.clone(),
+                format!("{}/{}", result.games_completed, result.games_requested),
+                format!("{:.2}", s.total),
+                format!("{:.3}", s.mean),
+                or_dash(forfeit),
+            ]);
+        }
+        table
+    })
+}
+
+/// 按 MatchRunner 的方式驱动 sponsor, 返回已完成各局的 payoffs
+///
+/// 有座位认输或出错时记录弃权, 暂停 sponsor 并结束比赛
+async fn play(game: &SandboxGame, seats: &mut [Seat]) -> Result<Played, ClientError> {
+    let SandboxGame {
+        sponsor,
+        game_type,
+        games,
+        action_timeout,
+        seed,
+    } = game;
+    let (games, action_timeout) = (*games, *action_timeout);
+    let seed_of = |i_turn: i32| seed.map(|seed| game_seed(seed, i_turn) as u64);
+    let mut client = SponsorServiceClient::connect(sponsor.clone()).await?;
+    let (sponsor_tx, rx) = mpsc::channel(16);
+    let mut sponsor_rx = client
+        .process_game(tonic::Request::new(ReceiverStream::new(rx)))
+        .await?
+        .into_inner();
+    send(
+        &sponsor_tx,
+        RequestType::Init(GameInitRequest {
+            game_type: game_type.clone(),
+            seed: seed_of(0),
+        }),
+    )
+    .await?;
+    // 丢弃一次应答, 因为Python后段依赖至少一次回复来生成流
+    let _ = sponsor_rx.message().await?;
+
+    let match_id = Uuid::new_v4().to_string();
+    let mut payoffs = Vec::new();
+    let mut i_turn = 0;
+    while i_turn < games {
+        let resp = sponsor_rx
+            .message()
+            .await?
+            .ok_or_else(|| ClientError::Sandbox("sponsor closed the stream".to_string()))?;
+        match resp.response_type {
+            Some(ResponseType::InitResponse(init)) if init.r#type() == ResultType::Failed => {
+                return Err(ClientError::Sandbox(format!(
+                    "sponsor failed to init {}",
+                    game_type
+                )));
+            }
+            Some(ResponseType::InitResponse(_)) => {}
+            Some(ResponseType::StateUpdate(GameStateUpdate {
+                state,
+                is_over,
+                i_player,
+            })) => {
+                if is_over {
+                    continue;
+                }
+                let seated = seats.len();
+                let seat = seats.get_mut(i_player as usize).ok_or_else(|| {
+                    ClientError::Sandbox(format!(
+                        "sponsor asked for seat {} but only {} players are seated",
+                        i_player, seated
+                    ))
+                })?;
+                let state = MatchPlayerResponse {
+                    state,
+                    match_id: match_id.clone(),
+                    seat: i_player,
+                    i_turn,
+                };
+                if seat.state_tx.send(Ok(state)).await.is_err() {
+                    return Err(ClientError::Sandbox(format!("{} has exited", seat.name)));
+                }
+                let action = match next_action(seat, action_timeout, i_turn).await? {
+                    Ok(action) => action,
+                    Err((reason, error)) => {
+                        let kind = if error { "errored" } else { "resigned" };
+                        eprintln!(
+                            "Seat {} ({}) {} on game {}: {}",
+                            i_player, seat.name, kind, i_turn, reason
+                        );
+                        send(&sponsor_tx, RequestType::Control(pause())).await?;
+                        return Ok(Played {
+                            payoffs,
+                            forfeit: Some(SandboxForfeit {
+                                game: i_turn,
+                                seat: i_player as usize,
+                                reason,
+                                error,
+                            }),
+                        });
+                    }
+                };
+                send(&sponsor_tx, RequestType::Action(PlayerAction { action })).await?;
+            }
+            Some(ResponseType::EndStatus(GameEndStatus { payoffs: game })) => {
+                eprintln!("Game {}: {:?}", i_turn, game);
+                payoffs.push(game);
+                i_turn += 1;
+                let control = if i_turn == games {
+                    pause()
+                } else {
+                    GameControl {
+                        r#type: ControlType::Resume.into(),
+                        seed: seed_of(i_turn),
+                    }
+                };
+                send(&sponsor_tx, RequestType::Control(control)).await?;
+            }
+            None => {}
+        }
+    }
+    Ok(Played {
+        payoffs,
+        forfeit: None,
+    })
+}
+
+fn pause() -> GameControl {
+    GameControl {
+        r#type: ControlType::Pause.into(),
+        seed: None,
+    }
+}
+
+/// 等待座位给出动作; 认输时返回 `Err((reason, error))`, 按弃权结束对局
+async fn next_action(
+    seat: &mut Seat,
+    action_timeout: Duration,
+    i_turn: i32,
+) -> Result<Result<String, (String, bool)>, ClientError> {
+    let deadline = tokio::time::Instant::now() + action_timeout;
+    loop {
+        let request = tokio::time::timeout_at(deadline, seat.action_rx.recv())
+            .await
+            .map_err(|_| {
+                ClientError::Sandbox(format!("{} timed out on game {}", seat.name, i_turn))
+            })?
+            .ok_or_else(|| ClientError::Sandbox(format!("{} has exited", seat.name)))?;
+        match request.payload {
+            Some(Payload::Action(action)) => return Ok(Ok(action)),
+            Some(Payload::Resign(AgentResign { reason, error })) => {
+                return Ok(Err((reason, error)))
+            }
+            // 日志已由 bridge 打印
+            Some(Payload::Log(_)) | None => {}
+        }
+    }
+}
+
+async fn send(tx: &Sender<ProcessGameRequest>, request: RequestType) -> Result<(), ClientError> {
+    tx.send(ProcessGameRequest {
+        request_type: Some(request),
+    })
+    .await
+    .map_err(|_| ClientError::Sandbox("sponsor stream closed".to_string()))
+}
+
+/// 内置随机玩家, 从状态的 `legal_actions` 中随机选择一个动作
+async fn random_player(
+    mut states: Receiver<Result<MatchPlayerResponse, Status>>,
+    actions: Sender<MatchPlayerRequest>,
+) -> Result<(), ClientError> {
+    while let Some(Ok(resp)) = states.recv().await {
+        let action = choose_action(&resp.state).ok_or_else(|| {
+            ClientError::Sandbox("random player found no legal_actions in state".to_string())
+        })?;
+        if actions
+            .send(AgentOutput::Action(action).into())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn choose_action(state: &str) -> Option<String> {
+    let state: Value = serde_json::from_str(state).ok()?;
+    // rlcard 的 legal_actions 是以动作编号为键的对象, 也兼容数组
+    let candidates: Vec<String> = match state.get("legal_actions")? {
+        Value::Object(map) => map.keys().cloned().collect(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect(),
+        _ => return None,
+    };
+    candidates.choose(&mut rand::rng()).cloned()
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use serde::Serialize;
+use serde_json::json;
+use tackle_box::contracts::payloads::{
+    GetSeasonPayload, GetSeasonResponse, NewSeasonPayload, NewSeasonResponse, SeasonStanding,
+};
+use uuid::Uuid;
+
+use crate::{
+    error::ClientError,
+    matches::parse_time,
+    output::{emit, OutputFormat, Table},
+    Context,
+};
+
+/// 创建赛季时的时间和重置设置
+#[derive(Args, Debug)]
+pub struct SeasonOptions {
+    /// 开始时间 (RFC3339 或 YYYY-MM-DD), 默认立即开始
+    #[arg(long, value_parser = parse_time)]
+    pub start: Option<DateTime<Utc>>,
+    /// 赛季时长 (天)
+    #[arg(short, long, default_value_t = 30)]
+    pub days: i32,
+    /// 结束时带入下赛季的分差比例, 0 为完全重置
+    #[arg(long, default_value_t = 0.0)]
+    pub carry_over: f64,
+    /// 结束时自动开启同样时长的下赛季
+    #[arg(short, long)]
+    pub recurring: bool,
+}
+
+#[derive(Serialize)]
+struct SeasonDetail {
+    #[serde(flatten)]
+    season: GetSeasonResponse,
+    standings: Vec<SeasonStanding>,
+}
+
+pub async fn handle_create_season(
+    ctx: &Context,
+    name: String,
+    game_type: String,
+    options: SeasonOptions,
+) -> Result<(), ClientError> {
+    let game_type = ctx.api.resolve_game_type(&game_type).await?;
+    let payload = NewSeasonPayload {
+        name: name.clone(),
+        game_type_id: game_type.game_type_id,
+        start_time: options.start,
+        days: options.days,
+        carry_over: options.carry_over,
+        recurring: options.recurring,
+    };
+    let NewSeasonResponse { season_id } = ctx.api.post("/season/new", &payload).await?;
+    ctx.done(
+        &format!(
+            "Creating season {} successful! season_id: {}",
+            name, season_id
+        ),
+        json!({ "season_id": season_id }),
+    )
+}
+
+pub async fn handle_list_seasons(ctx: &Context) -> Result<(), ClientError> {
+    let seasons: Vec<GetSeasonResponse> = ctx.api.get("/season/seasons").await?;
+    emit(ctx.output, &seasons, |seasons| {
+        let mut table = Table::new(&[
+            "NAME",
+            "SEASON_ID",
+            "GAME",
+            "STATUS",
+            "START",
+            "END",
+            "MATCHES",
+            "RECURRING",
+        ]);
+        for s in seasons {
+            table.push(vec![
+                s.name.clone(),
+                s.season_id.to_string(),
+                s.game_type_name.clone(),
+                format!("{:?}", s.status),
+                s.start_time.format("%Y-%m-%d %H:%M").to_string(),
+                s.end_time.format("%Y-%m-%d %H:%M").to_string(),
+                s.matches.to_string(),
+                if s.recurring { "yes" } else { "" }.to_string(),
+            ]);
+        }
+        table
+    })
+}
+
+/// 赛季详情: 表格模式依次打印概要和排名
+pub async fn handle_show_season(ctx: &Context, season_id: Uuid) -> Result<(), ClientError> {
+    let payload = GetSeasonPayload { season_id };
+    let detail = SeasonDetail {
+        season: ctx.api.post("/season/get", &payload).await?,
+        standings: ctx.api.post("/season/standings", &payload).await?,
+    };
+    if let OutputFormat::Table = ctx.output {
+        summary_table(&detail).print();
+        println!();
+        standings_table(&detail.standings).print();
+        return Ok(());
+    }
+    emit(ctx.output, &detail, summary_table)
+}
+
+fn summary_table(detail: &SeasonDetail) -> Table {
+    let s = &detail.season;
+    let mut table = Table::new(&["KEY", "VALUE"]);
+    let rows = [
+        ("name", s.name.clone()),
+        ("season_id", s.season_id.to_string()),
+        ("game_type", s.game_type_name.clone()),
+        ("creator", s.creater_name.clone()),
+        ("status", format!("{:?}", s.status)),
+        ("start_time", s.start_time.to_rfc3339()),
+        ("end_time", s.end_time.to_rfc3339()),
+        ("carry_over", s.carry_over.to_string()),
+        ("recurring", s.recurring.to_string()),
+        (
+            "previous",
+            s.previous_id.map_or("-".to_string(), |id| id.to_string()),
+        ),
+        ("matches", s.matches.to_string()),
+    ];
+    for (key, value) in rows {
+        table.push(vec![key.to_string(), value]);
+    }
+    table
+}
+
+fn standings_table(standings: &[SeasonStanding]) -> Table {
+    let mut table = Table::new(&["RANK", "AGENT", "OWNER", "RATING", "PLAYED", "W", "D", "L"]);
+    for s in standings {
+        table.push(vec![
+            s.rank.to_string(),
+            s.agent_name.clone(),
+            s.owner_name.clone(),
+            format!("{:.1}", s.rating),
+            s.played.to_string(),
+            s.won.to_string(),
+            s.drawn.to_string(),
+            s.lost.to_string(),
+        ]);
+    }
+    table
+}
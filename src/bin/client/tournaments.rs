@@ -0,0 +1,279 @@
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use serde_json::json;
+use tackle_box::contracts::payloads::{
+    GetTournamentPayload, GetTournamentResponse, JoinTournamentPayload, NewTournamentPayload,
+    NewTournamentResponse, TournamentEntrantResponse, TournamentFormat, TournamentMatchResponse,
+    TournamentStanding,
+};
+use uuid::Uuid;
+
+use crate::{
+    error::ClientError,
+    matches::ModeArg,
+    output::{emit, or_dash, OutputFormat, Table},
+    Context,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum FormatArg {
+    /// 单循环
+    RoundRobin,
+    /// 瑞士轮
+    Swiss,
+    /// 单败淘汰
+    Single,
+    /// 双败淘汰
+    Double,
+}
+
+impl From<FormatArg> for TournamentFormat {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::RoundRobin => TournamentFormat::RoundRobin,
+            FormatArg::Swiss => TournamentFormat::Swiss,
+            FormatArg::Single => TournamentFormat::SingleElimination,
+            FormatArg::Double => TournamentFormat::DoubleElimination,
+        }
+    }
+}
+
+/// 创建锦标赛时的赛制设置
+#[derive(Args, Debug)]
+pub struct TournamentOptions {
+    /// 赛制
+    #[arg(short, long, value_enum, default_value_t = FormatArg::RoundRobin)]
+    pub format: FormatArg,
+    /// 每场比赛的局数
+    #[arg(short = 'n', long, default_value_t = 50)]
+    pub games_per_match: i32,
+    /// 瑞士轮的轮数, 默认按人数取 log2
+    #[arg(long)]
+    pub rounds: Option<i32>,
+    /// 报满后自动开始
+    #[arg(long)]
+    pub max_entrants: Option<i32>,
+    /// 密码
+    #[arg(short, long)]
+    pub password: Option<String>,
+    /// 锦标赛种子, 不指定时由服务端随机生成
+    #[arg(long)]
+    pub seed: Option<i64>,
+    /// 比赛模式, duplicate 时每场的局数为牌副数
+    #[arg(short, long, value_enum, default_value_t = ModeArg::Standard)]
+    pub mode: ModeArg,
+}
+
+#[derive(Serialize)]
+struct TournamentDetail {
+    #[serde(flatten)]
+    tournament: GetTournamentResponse,
+    entrants: Vec<TournamentEntrantResponse>,
+    standings: Vec<TournamentStanding>,
+    matches: Vec<TournamentMatchResponse>,
+}
+
+pub async fn handle_create_tournament(
+    ctx: &Context,
+    name: String,
+    game_type: String,
+    with_agent_names: Vec<String>,
+    options: TournamentOptions,
+) -> Result<(), ClientError> {
+    let TournamentOptions {
+        format,
+        games_per_match,
+        rounds,
+        max_entrants,
+        password,
+        seed,
+        mode,
+    } = options;
+    let game_type = ctx.api.resolve_game_type(&game_type).await?;
+    let with_agent_ids = ctx.api.resolve_agents(&with_agent_names).await?;
+    let payload = NewTournamentPayload {
+        name: name.clone(),
+        game_type_id: game_type.game_type_id,
+        format: format.into(),
+        games_per_match,
+        mode: mode.into(),
+        rounds,
+        max_entrants,
+        password,
+        seed,
+        with_agent_ids,
+    };
+    let NewTournamentResponse { tournament_id } = ctx.api.post("/tournament/new", &payload).await?;
+    ctx.done(
+        &format!(
+            "Creating tournament {} successful! tournament_id: {}",
+            name, tournament_id
+        ),
+        json!({ "tournament_id": tournament_id }),
+    )
+}
+
+pub async fn handle_join_tournament(
+    ctx: &Context,
+    tournament_id: Uuid,
+    agent_names: Vec<String>,
+    password: Option<String>,
+) -> Result<(), ClientError> {
+    let agent_ids = ctx.api.resolve_agents(&agent_names).await?;
+    let payload = JoinTournamentPayload {
+        tournament_id,
+        agent_ids: agent_ids.clone(),
+        password,
+    };
+    ctx.api.post_unit("/tournament/join", &payload).await?;
+    ctx.done(
+        &format!("Joined tournament {}", tournament_id),
+        json!({ "tournament_id": tournament_id, "agent_ids": agent_ids }),
+    )
+}
+
+pub async fn handle_start_tournament(
+    ctx: &Context,
+    tournament_id: Uuid,
+) -> Result<(), ClientError> {
+    ctx.api
+        .post_unit("/tournament/start", &GetTournamentPayload { tournament_id })
+        .await?;
+    ctx.done(
+        &format!("Tournament {} started", tournament_id),
+        json!({ "tournament_id": tournament_id }),
+    )
+}
+
+pub async fn handle_list_tournaments(ctx: &Context) -> Result<(), ClientError> {
+    let tournaments: Vec<GetTournamentResponse> = ctx.api.get("/tournament/tournaments").await?;
+    emit(ctx.output, &tournaments, |tournaments| {
+        let mut table = Table::new(&[
+            "NAME",
+            "TOURNAMENT_ID",
+            "GAME",
+            "FORMAT",
+            "STATUS",
+            "ROUND",
+            "ENTRANTS",
+            "WINNER",
+        ]);
+        for t in tournaments {
+            table.push(vec![
+                t.name.clone(),
+                t.tournament_id.to_string(),
+                t.game_type_name.clone(),
+                format!("{:?}", t.format),
+                format!("{:?}", t.status),
+                round_progress(t),
+                entrant_count(t),
+                or_dash(t.winner_agent_name.as_ref()),
+            ]);
+        }
+        table
+    })
+}
+
+fn round_progress(t: &GetTournamentResponse) -> String {
+    match t.total_rounds {
+        Some(total) => format!("{}/{}", t.current_round, total),
+        None => t.current_round.to_string(),
+    }
+}
+
+fn entrant_count(t: &GetTournamentResponse) -> String {
+    match t.max_entrants {
+        Some(max) => format!("{}/{}", t.entrants, max),
+        None => t.entrants.to_string(),
+    }
+}
+
+/// 锦标赛详情: 表格模式依次打印概要、排名和各轮比赛
+pub async fn handle_show_tournament(ctx: &Context, tournament_id: Uuid) -> Result<(), ClientError> {
+    let payload = GetTournamentPayload { tournament_id };
+    let detail = TournamentDetail {
+        tournament: ctx.api.post("/tournament/get", &payload).await?,
+        entrants: ctx.api.post("/tournament/entrants", &payload).await?,
+        standings: ctx.api.post("/tournament/standings", &payload).await?,
+        matches: ctx.api.post("/tournament/matches", &payload).await?,
+    };
+    if let OutputFormat::Table = ctx.output {
+        summary_table(&detail).print();
+        println!();
+        standings_table(&detail.standings).print();
+        if !detail.matches.is_empty() {
+            println!();
+            matches_table(&detail.matches).print();
+        }
+        return Ok(());
+    }
+    emit(ctx.output, &detail, summary_table)
+}
+
+fn summary_table(detail: &TournamentDetail) -> Table {
+    let t = &detail.tournament;
+    let mut table = Table::new(&["KEY", "VALUE"]);
+    let rows = [
+        ("name", t.name.clone()),
+        ("tournament_id", t.tournament_id.to_string()),
+        ("game_type", t.game_type_name.clone()),
+        ("creator", t.creater_name.clone()),
+        ("format", format!("{:?}", t.format)),
+        ("mode", format!("{:?}", t.mode)),
+        ("games_per_match", t.games_per_match.to_string()),
+        ("status", format!("{:?}", t.status)),
+        ("round", round_progress(t)),
+        ("entrants", entrant_count(t)),
+        ("winner", or_dash(t.winner_agent_name.as_ref())),
+        ("created_at", t.created_at.to_rfc3339()),
+        ("end_time", or_dash(t.end_time.map(|t| t.to_rfc3339()))),
+        ("seed", t.seed.to_string()),
+    ];
+    for (key, value) in rows {
+        table.push(vec![key.to_string(), value]);
+    }
+    table
+}
+
+fn standings_table(standings: &[TournamentStanding]) -> Table {
+    let mut table = Table::new(&[
+        "RANK", "AGENT", "SEED", "W", "D", "L", "BYE", "POINTS", "BUCHHOLZ", "SB", "SCORE", "OUT",
+    ]);
+    for s in standings {
+        table.push(vec![
+            s.rank.to_string(),
+            s.agent_name.clone(),
+            s.seeding.to_string(),
+            s.won.to_string(),
+            s.drawn.to_string(),
+            s.lost.to_string(),
+            s.byes.to_string(),
+            format!("{:.1}", s.points),
+            format!("{:.1}", s.buchholz),
+            format!("{:.2}", s.sonneborn_berger),
+            format!("{:.1}", s.score),
+            if s.eliminated { "yes" } else { "" }.to_string(),
+        ]);
+    }
+    table
+}
+
+fn matches_table(matches: &[TournamentMatchResponse]) -> Table {
+    let mut table = Table::new(&["ROUND", "MATCH_ID", "AGENTS", "STATUS", "WINNER"]);
+    for m in matches {
+        let winner = m
+            .winner_id
+            .and_then(|w| m.agent_ids.iter().position(|&id| id == w))
+            .map(|i| m.agent_names[i].clone());
+        table.push(vec![
+            m.round.to_string(),
+            or_dash(m.match_id.map(|id| id.to_string())),
+            m.agent_names.join(" vs "),
+            m.status
+                .as_ref()
+                .map_or("Bye".to_string(), |s| format!("{:?}", s)),
+            or_dash(winner.as_ref()),
+        ]);
+    }
+    table
+}
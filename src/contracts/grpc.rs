@@ -5,5 +5,6 @@ use uuid::Uuid;
 pub enum MatchMetadata {
     MatchMonitor { match_id: Uuid },
     MatchPlayer { agent_id: Uuid },
+    MatchReplay { match_id: Uuid },
     None,
 }
@@ -68,7 +68,7 @@ pub enum AgentPolicy {
     AutoNewAndJoin,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct GetAgentPayload {
     pub agent_id: Uuid,
 }
@@ -83,8 +83,10 @@ pub struct GetAgentResponse {
     pub owner_name: String,
     pub version: String,
     pub description: Option<String>,
+    /// 当前版本的战绩
     pub played_games: i32,
     pub won_games: i32,
+    pub drawn_games: i32,
     pub status: AgentStatus,
     pub policy: AgentPolicy,
     pub created_at: DateTime<Utc>,
@@ -100,7 +102,7 @@ pub struct NewAgentPayload {
     pub policy: AgentPolicy,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Serialize)]
 pub struct UpdateAgentPayload {
     pub agent_id: Uuid,
     pub name: String,
@@ -110,11 +112,49 @@ pub struct UpdateAgentPayload {
     pub policy: AgentPolicy,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Serialize)]
 pub struct DeleteAgentPayload {
     pub agent_id: Uuid,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AgentVersionResponse {
+    pub version_id: Uuid,
+    pub version: String,
+    pub played_games: i32,
+    pub won_games: i32,
+    pub drawn_games: i32,
+    pub created_at: DateTime<Utc>,
+    /// 是否为 Agent 当前的版本
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CompareVersionsPayload {
+    pub agent_id: Uuid,
+    pub base: String,
+    pub target: String,
+}
+
+/// 两个版本对同一对手的战绩
+#[derive(Serialize, Deserialize)]
+pub struct VersionOpponentStats {
+    pub opponent_id: Uuid,
+    pub opponent_name: String,
+    pub base_played: i64,
+    pub base_won: i64,
+    pub target_played: i64,
+    pub target_won: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CompareVersionsResponse {
+    pub agent_id: Uuid,
+    pub base: AgentVersionResponse,
+    pub target: AgentVersionResponse,
+    pub opponents: Vec<VersionOpponentStats>,
+}
+
 /*
 ====================
 Match Manager Payload
@@ -130,6 +170,16 @@ pub enum MatchStatus {
     Cancelled,
 }
 
+#[derive(Debug, Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[sqlx(type_name = "match_mode", rename_all = "PascalCase")]
+pub enum MatchMode {
+    /// 每局独立发牌
+    #[default]
+    Standard,
+    /// 复式赛: 每副牌按座位轮换各打一次, 抵消牌运
+    Duplicate,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct GetMatchPayload {
     pub match_id: Uuid,
@@ -143,6 +193,8 @@ pub struct GetMatchResponse {
     pub creater_name: String,
     pub winner_id: Option<Uuid>,
     pub winner_agent_name: Option<String>,
+    /// 并列第一且允许平局
+    pub is_draw: bool,
     pub game_type_id: Uuid,
     pub game_type_name: String,
     pub password: Option<String>,
@@ -152,6 +204,8 @@ pub struct GetMatchResponse {
     pub status: MatchStatus,
     pub start_time: DateTime<Utc>,
     pub end_time: Option<DateTime<Utc>>,
+    pub seed: Option<i64>,
+    pub mode: MatchMode,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -178,6 +232,16 @@ pub struct NewMatchPayload {
     pub total_games: i32,
     pub with_agent_ids: Vec<Uuid>,
     pub password: Option<String>,
+    /// 比赛种子, 不指定时随机生成
+    pub seed: Option<i64>,
+    /// 复式赛中 total_games 为牌副数, 实际局数再乘以参赛人数
+    #[serde(default)]
+    pub mode: MatchMode,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewMatchResponse {
+    pub match_id: Uuid,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -213,6 +277,64 @@ pub struct TurnLogResponse {
     pub log: Value,
     pub i_turn: i32,
     pub score_deltas: Value,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// 旧数据没有记录种子
+    pub seed: Option<i64>,
+    /// 按座位顺序的 Agent, 旧数据中没有记录
+    pub seats: Option<Vec<Uuid>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MatchReplayPayload {
+    pub match_id: Uuid,
+    /// 只回放指定的一局, 为空时返回全部
+    pub i_turn: Option<i32>,
+}
+
+/// 回放中的一步: 行动座位看到的状态, 以及它做出的动作
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayStep {
+    pub index: i32,
+    /// 旧格式日志中没有座位信息
+    pub seat: Option<i32>,
+    pub state: Option<String>,
+    pub action: Option<String>,
+    pub is_over: bool,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// 该步中当前用户可见的 Agent 日志
+    pub logs: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameReplay {
+    pub match_id: Uuid,
+    pub i_turn: i32,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub seed: Option<i64>,
+    pub steps: Vec<ReplayStep>,
+    /// 本局结束后各 Agent 的得分
+    pub payoffs: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MatchVerifyPayload {
+    pub match_id: Uuid,
+    pub i_turn: i32,
+}
+
+/// 用存储的种子和动作重新模拟一局后的比对结果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameVerification {
+    pub match_id: Uuid,
+    pub i_turn: i32,
+    pub seed: i64,
+    pub verified: bool,
+    /// 比对过的状态数
+    pub checked_states: i32,
+    /// 第一处不一致的描述
+    pub mismatch: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -226,6 +348,16 @@ pub struct GetParticipantsResponse {
     pub match_name: String,
     pub agent_id: Uuid,
     pub agent_name: String,
+    /// 比赛开始时 Agent 的版本
+    pub version: Option<String>,
+    /// 以下为结算结果, 未结束时为空
+    pub placement: Option<i32>,
+    pub final_score: Option<f32>,
+    pub games_won: Option<i32>,
+    /// 每局所在的座位
+    pub seats: Option<Vec<i32>>,
+    /// 赛季计分时的分数变化, 不属于结算结果: 结算后由赛季任务异步写入, 不在赛季中的比赛始终为空
+    pub rating_change: Option<f64>,
 }
 
 /*
@@ -242,6 +374,18 @@ pub struct GetGameTypeResponse {
     pub description: Option<String>,
     pub min_slots: i32,
     pub max_slots: i32,
+    pub settlement_rule: SettlementRule,
+    pub allow_draws: bool,
+}
+
+/// 比赛结算时的排名依据, 并列时以另一项为次要依据
+#[derive(Debug, Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "settlement_rule", rename_all = "PascalCase")]
+pub enum SettlementRule {
+    /// 总得分最高
+    HighestScore,
+    /// 赢下的局数最多
+    MostGamesWon,
 }
 
 /*
@@ -259,3 +403,268 @@ pub struct GetStatsResponse {
     pub rank: i32,
     pub updated_time: DateTime<Utc>,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct HeadToHeadPayload {
+    pub agent_id: Uuid,
+    /// 只看指定对手
+    pub opponent_id: Option<Uuid>,
+}
+
+/// 与某个对手同桌时逐局比较得分的统计, 均值的置信区间为 95%
+#[derive(Serialize, Deserialize)]
+pub struct HeadToHeadStats {
+    pub opponent_id: Uuid,
+    pub opponent_name: String,
+    pub owner_name: String,
+    pub games: i64,
+    pub won: i64,
+    pub lost: i64,
+    pub drawn: i64,
+    pub win_rate: f64,
+    pub mean_payoff: f64,
+    /// 少于两局时为空
+    pub ci_low: Option<f64>,
+    pub ci_high: Option<f64>,
+    pub opponent_mean_payoff: f64,
+}
+
+/// 导出文件格式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// 每行一个 `ExportedTurn`
+    #[default]
+    Jsonl,
+    /// gzip 压缩的 JSON Lines
+    Gzip,
+}
+
+/// 导出的分页游标, 取已导出的最后一条的 end_time 和 turn_id
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportCursor {
+    pub end_time: DateTime<Utc>,
+    pub turn_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ExportTurnsPayload {
+    pub game_type_id: Option<Uuid>,
+    /// 只导出该 Agent 参与的比赛
+    pub agent_id: Option<Uuid>,
+    /// 按每局的结束时间过滤, 左闭右开
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// 只导出自己的 Agent 参与的比赛
+    #[serde(default)]
+    pub own_only: bool,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// 从该游标之后继续导出
+    pub after: Option<ExportCursor>,
+    /// 最多导出的局数
+    pub limit: Option<i64>,
+}
+
+/// 导出中的一局, 其他用户 Agent 的日志已去除
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedTurn {
+    pub turn_id: Uuid,
+    pub match_id: Uuid,
+    pub match_name: String,
+    pub game_type_name: String,
+    pub i_turn: i32,
+    pub seed: Option<i64>,
+    pub seats: Option<Vec<Uuid>>,
+    pub score_deltas: Value,
+    pub log: Value,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+/*
+====================
+Tournament Payload
+====================
+*/
+
+#[derive(Debug, Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "tournament_format", rename_all = "PascalCase")]
+pub enum TournamentFormat {
+    /// 单循环, 每两名参赛者相遇一次
+    RoundRobin,
+    /// 瑞士轮, 每轮按积分相近配对, 不重复相遇
+    Swiss,
+    /// 单败淘汰
+    SingleElimination,
+    /// 双败淘汰, 输两场出局
+    DoubleElimination,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewTournamentPayload {
+    pub name: String,
+    pub game_type_id: Uuid,
+    pub format: TournamentFormat,
+    /// 每场比赛的局数
+    pub games_per_match: i32,
+    #[serde(default)]
+    pub mode: MatchMode,
+    /// 瑞士轮的轮数, 不指定时按人数取 log2
+    pub rounds: Option<i32>,
+    /// 报满后自动开始
+    pub max_entrants: Option<i32>,
+    pub password: Option<String>,
+    /// 锦标赛种子, 不指定时随机生成
+    pub seed: Option<i64>,
+    pub with_agent_ids: Vec<Uuid>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewTournamentResponse {
+    pub tournament_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetTournamentPayload {
+    pub tournament_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JoinTournamentPayload {
+    pub tournament_id: Uuid,
+    pub agent_ids: Vec<Uuid>,
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetTournamentResponse {
+    pub tournament_id: Uuid,
+    pub name: String,
+    pub game_type_id: Uuid,
+    pub game_type_name: String,
+    pub creater_id: Uuid,
+    pub creater_name: String,
+    pub format: TournamentFormat,
+    pub games_per_match: i32,
+    pub mode: MatchMode,
+    /// 瑞士轮以外的赛制在开始前为空
+    pub total_rounds: Option<i32>,
+    pub current_round: i32,
+    pub max_entrants: Option<i32>,
+    pub entrants: i64,
+    pub with_password: bool,
+    pub seed: i64,
+    pub status: MatchStatus,
+    pub winner_id: Option<Uuid>,
+    pub winner_agent_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TournamentEntrantResponse {
+    pub agent_id: Uuid,
+    pub agent_name: String,
+    pub owner_name: String,
+    pub seeding: i32,
+}
+
+/// 锦标赛中的一场比赛, 轮空时没有 match_id
+#[derive(Serialize, Deserialize)]
+pub struct TournamentMatchResponse {
+    pub round: i32,
+    pub match_id: Option<Uuid>,
+    pub match_name: Option<String>,
+    pub status: Option<MatchStatus>,
+    pub agent_ids: Vec<Uuid>,
+    pub agent_names: Vec<String>,
+    pub winner_id: Option<Uuid>,
+}
+
+/// 排名, 胜 1 分, 平 0.5 分, 轮空算胜
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TournamentStanding {
+    pub rank: i32,
+    pub agent_id: Uuid,
+    pub agent_name: String,
+    pub seeding: i32,
+    pub played: i32,
+    pub won: i32,
+    pub drawn: i32,
+    pub lost: i32,
+    pub byes: i32,
+    pub points: f64,
+    /// 对手积分之和, 瑞士轮的第一破同分
+    pub buchholz: f64,
+    /// 战胜对手积分之和加上战平对手积分的一半, 循环赛的第一破同分
+    pub sonneborn_berger: f64,
+    /// 所有对局的累计得分
+    pub score: f64,
+    /// 淘汰赛中已出局
+    pub eliminated: bool,
+}
+
+/*
+====================
+Season Payload
+====================
+*/
+
+#[derive(Serialize, Deserialize)]
+pub struct NewSeasonPayload {
+    pub name: String,
+    pub game_type_id: Uuid,
+    /// 不指定时立即开始
+    pub start_time: Option<DateTime<Utc>>,
+    /// 赛季时长, 单位为天
+    pub days: i32,
+    /// 结束时带入下赛季的分差比例, 0 为完全重置, 1 为原样保留
+    #[serde(default)]
+    pub carry_over: f64,
+    /// 结束时自动开启同样时长的下赛季
+    #[serde(default)]
+    pub recurring: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewSeasonResponse {
+    pub season_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetSeasonPayload {
+    pub season_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetSeasonResponse {
+    pub season_id: Uuid,
+    pub name: String,
+    pub game_type_id: Uuid,
+    pub game_type_name: String,
+    pub creater_id: Uuid,
+    pub creater_name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub status: MatchStatus,
+    pub carry_over: f64,
+    pub recurring: bool,
+    pub previous_id: Option<Uuid>,
+    /// 已计入分数的比赛数
+    pub matches: i64,
+}
+
+/// 赛季排名, 进行中的赛季按当前分数实时排序, 结束后为归档结果
+#[derive(Serialize, Deserialize)]
+pub struct SeasonStanding {
+    pub rank: i32,
+    pub agent_id: Uuid,
+    pub agent_name: String,
+    pub owner_name: String,
+    pub rating: f64,
+    pub played: i32,
+    pub won: i32,
+    pub drawn: i32,
+    pub lost: i32,
+}
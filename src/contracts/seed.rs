@@ -0,0 +1,11 @@
+/// 由比赛种子和局数派生每局的种子 (splitmix64), 结果保持非负以便存入 BIGINT
+pub fn game_seed(match_seed: i64, i_turn: i32) -> i64 {
+    let mut z = (match_seed as u64).wrapping_add(
+        (i_turn as u64)
+            .wrapping_add(1)
+            .wrapping_mul(0x9E37_79B9_7F4A_7C15),
+    );
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    ((z ^ (z >> 31)) >> 1) as i64
+}
@@ -1,7 +1,15 @@
 pub mod agents;
 pub mod auth;
+pub mod health;
 pub mod matches;
+pub mod metrics;
+pub mod pairing;
+pub mod persistence;
+pub mod replay;
+pub mod seasons;
+pub mod settlement;
 // pub mod user;
 pub mod client;
 pub mod core;
 pub mod stats;
+pub mod tournaments;
@@ -3,7 +3,10 @@ use crate::repo::{
     error::RepoError,
 };
 use std::sync::Arc;
-use tackle_box::contracts::payloads::{GetAgentResponse, NewAgentPayload, UpdateAgentPayload};
+use tackle_box::contracts::payloads::{
+    AgentVersionResponse, CompareVersionsResponse, GetAgentResponse, NewAgentPayload,
+    UpdateAgentPayload,
+};
 use uuid::Uuid;
 
 pub struct AgentService {
@@ -73,4 +76,38 @@ impl AgentService {
         let agents = self.repo.get_my_agents(user_id).await?;
         Ok(agents)
     }
+
+    pub async fn get_versions(
+        &self,
+        agent_id: Uuid,
+    ) -> Result<Vec<AgentVersionResponse>, RepoError> {
+        let versions = self.repo.get_versions(agent_id).await?;
+        Ok(versions)
+    }
+
+    /// 对比同一 Agent 的两个版本, 按共同对手逐一列出战绩
+    pub async fn compare_versions(
+        &self,
+        agent_id: Uuid,
+        base: String,
+        target: String,
+    ) -> Result<CompareVersionsResponse, RepoError> {
+        let versions = self.repo.get_versions(agent_id).await?;
+        let find = |version: &str| {
+            versions
+                .iter()
+                .find(|v| v.version == version)
+                .cloned()
+                .ok_or(RepoError::NotFound)
+        };
+        let base_version = find(&base)?;
+        let target_version = find(&target)?;
+        let opponents = self.repo.compare_versions(agent_id, &base, &target).await?;
+        Ok(CompareVersionsResponse {
+            agent_id,
+            base: base_version,
+            target: target_version,
+            opponents,
+        })
+    }
 }
@@ -3,24 +3,35 @@ use crate::{
         error::AppError,
         extractor::{check_jwt, Claims},
     },
-    core::core::CoreMessage,
+    core::{
+        core::{CoreMessage, Routes},
+        health::HealthService,
+        replay::ReplayService,
+    },
 };
 use base64::prelude::BASE64_STANDARD;
 use base64::prelude::*;
+use chrono::{DateTime, Utc};
 use futures_util::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::{pin::Pin, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, pin::Pin, sync::Arc, time::Duration};
 use tackle_box::{
     connection::{
         client_service_server::{self, ClientServiceServer},
-        MatchMonitorRequest, MatchMonitorResponse, MatchPlayerRequest, MatchPlayerResponse,
+        match_player_request::Payload,
+        AgentLog, AgentResign, MatchMonitorRequest, MatchMonitorResponse, MatchPlayerRequest,
+        MatchPlayerResponse, MatchReplayRequest, MatchReplayResponse,
     },
     contracts::grpc::MatchMetadata,
+    metrics::GRPC_STREAMS,
+};
+use tokio::sync::{
+    mpsc::{self, error::SendError, Sender},
+    watch,
 };
-use tokio::sync::mpsc::{self, Sender};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{transport::Server, Request, Response, Status, Streaming};
-use tracing::debug;
+use tracing::{debug, error, field, info, info_span, warn, Instrument, Span};
 use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -31,11 +42,21 @@ pub struct MessageMetadata {
 
 pub struct ClientService {
     core_tx: Sender<CoreMessage>,
+    routes: Arc<Routes>,
+    replay_service: Arc<ReplayService>,
 }
 
 impl ClientService {
-    pub async fn new(core_tx: Sender<CoreMessage>) -> Result<Arc<Self>, AppError> {
-        Ok(Arc::new(ClientService { core_tx }))
+    pub async fn new(
+        core_tx: Sender<CoreMessage>,
+        routes: Arc<Routes>,
+        replay_service: Arc<ReplayService>,
+    ) -> Result<Arc<Self>, AppError> {
+        Ok(Arc::new(ClientService {
+            core_tx,
+            routes,
+            replay_service,
+        }))
     }
 }
 
@@ -55,11 +76,18 @@ impl ClientServer {
 type MessageResult<T> = Result<Response<T>, Status>;
 type MatchMonitorStream = Pin<Box<dyn Stream<Item = Result<MatchMonitorResponse, Status>> + Send>>;
 type MatchPlayerStream = Pin<Box<dyn Stream<Item = Result<MatchPlayerResponse, Status>> + Send>>;
+type MatchReplayStream = Pin<Box<dyn Stream<Item = Result<MatchReplayResponse, Status>> + Send>>;
+
+/// 缺少时间戳时相邻两步的默认间隔
+const REPLAY_DEFAULT_GAP: Duration = Duration::from_millis(500);
+/// 回放时相邻两步的最长等待, 避免长时间思考让观众空等
+const REPLAY_MAX_GAP: Duration = Duration::from_secs(3);
 
 #[tonic::async_trait]
 impl client_service_server::ClientService for ClientServer {
     type MatchMonitorStream = MatchMonitorStream;
     type MatchPlayerStream = MatchPlayerStream;
+    type MatchReplayStream = MatchReplayStream;
 
     async fn match_monitor(
         &self,
@@ -75,12 +103,102 @@ impl client_service_server::ClientService for ClientServer {
             None => return Err(Status::aborted("no user auth information")),
         };
 
-        let monitor_stream: ReceiverStream<_> = ReceiverStream::new(rx);
+        debug!(%user_id, %match_id, "monitor stream opened");
+        self.client_service
+            .core_tx
+            .send(CoreMessage::MonitorRegister {
+                match_id,
+                tx: monitor_tx,
+            })
+            .await
+            .map_err(|_| Status::unavailable("core is not running"))?;
+
+        let guard = GRPC_STREAMS.track(&["monitor"]);
+        let monitor_stream = ReceiverStream::new(rx).map(move |event| {
+            let _ = &guard;
+            event
+        });
         Ok(Response::new(
             Box::pin(monitor_stream) as Self::MatchMonitorStream
         ))
     }
 
+    async fn match_replay(
+        &self,
+        req: Request<MatchReplayRequest>,
+    ) -> MessageResult<MatchReplayStream> {
+        let (user_id, match_id) = match req.extensions().get::<MessageMetadata>().cloned() {
+            Some(data) => match data.metadata {
+                MatchMetadata::MatchReplay { match_id } => (data.user_id, match_id),
+                _ => return Err(Status::aborted("type error")),
+            },
+            None => return Err(Status::aborted("no user auth information")),
+        };
+        let MatchReplayRequest { i_turn, speed } = req.into_inner();
+        let speed = if speed > 0.0 { speed } else { 1.0 };
+
+        let replays = self
+            .client_service
+            .replay_service
+            .get_replay(user_id, match_id, i_turn)
+            .await
+            .map_err(|e| Status::not_found(e.to_string()))?;
+        debug!(%user_id, %match_id, "replay stream opened");
+
+        let (replay_tx, rx) = mpsc::channel(8);
+        let guard = GRPC_STREAMS.track(&["replay"]);
+        tokio::spawn(
+            async move {
+                let _guard = guard;
+                for replay in replays {
+                    let last = replay.steps.len().saturating_sub(1);
+                    let mut prev_time: Option<DateTime<Utc>> = None;
+                    for (i, step) in replay.steps.into_iter().enumerate() {
+                        let gap = match (prev_time, step.timestamp) {
+                            (Some(prev), Some(now)) => (now - prev)
+                                .to_std()
+                                .unwrap_or_default()
+                                .min(REPLAY_MAX_GAP),
+                            (None, _) => Duration::ZERO,
+                            _ => REPLAY_DEFAULT_GAP,
+                        };
+                        tokio::time::sleep(gap.div_f32(speed)).await;
+                        prev_time = step.timestamp.or(prev_time);
+
+                        let payoffs = if i == last {
+                            serde_json::from_value(replay.payoffs.clone()).unwrap_or_default()
+                        } else {
+                            HashMap::new()
+                        };
+                        let resp = MatchReplayResponse {
+                            i_turn: replay.i_turn,
+                            index: step.index,
+                            seat: step.seat,
+                            state: step.state,
+                            action: step.action,
+                            is_over: step.is_over,
+                            timestamp: step
+                                .timestamp
+                                .map(|t| t.timestamp_millis())
+                                .unwrap_or_default(),
+                            logs: step.logs,
+                            payoffs,
+                        };
+                        if replay_tx.send(Ok(resp)).await.is_err() {
+                            debug!("viewer disconnected");
+                            return;
+                        }
+                    }
+                }
+            }
+            .instrument(info_span!("replay_stream", %user_id, %match_id)),
+        );
+
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::MatchReplayStream
+        ))
+    }
+
     async fn match_player(
         &self,
         req: Request<Streaming<MatchPlayerRequest>>,
@@ -104,12 +222,22 @@ impl client_service_server::ClientService for ClientServer {
             agent_id,
             match_id: None,
             core_tx,
+            routes: self.client_service.routes.clone(),
             client_tx,
             client_instream,
         };
-        tokio::spawn(async move {
-            client.run().await;
-        });
+        let guard = GRPC_STREAMS.track(&["player"]);
+        // match_id 在收到第一个状态时填入
+        let span = info_span!("player_stream", %user_id, %agent_id, match_id = field::Empty);
+        tokio::spawn(
+            async move {
+                let _guard = guard;
+                if let Err(e) = client.run().await {
+                    error!(error = ?e, "player stream failed");
+                }
+            }
+            .instrument(span),
+        );
 
         Ok(Response::new(
             Box::pin(out_stream) as Self::MatchPlayerStream
@@ -138,7 +266,6 @@ fn check_auth(mut req: Request<()>) -> Result<Request<()>, Status> {
     let metadata = match req.metadata().get("x-message-metadata") {
         Some(metadata_value) => {
             let base64_bytes = metadata_value.as_bytes();
-            debug!("serde base64 to bytes");
             if let Ok(decoded_bytes) = BASE64_STANDARD.decode(base64_bytes) {
                 let metadata: MatchMetadata = serde_json::from_slice(&decoded_bytes)
                     .map_err(|_| Status::aborted("serde error".to_string()))?;
@@ -155,13 +282,28 @@ fn check_auth(mut req: Request<()>) -> Result<Request<()>, Status> {
     Ok(req)
 }
 
-pub async fn run_client_server(service: Arc<ClientService>) -> Result<(), AppError> {
-    let addr = "[::]:50050".parse().unwrap();
+/// 收到停机信号后不再接受新连接, 已有的流在 Core 退出后关闭
+/// 同时提供标准的 grpc.health.v1 服务, 空服务名表示整个服务端
+pub async fn run_client_server(
+    service: Arc<ClientService>,
+    health: Arc<HealthService>,
+    addr: SocketAddr,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), AppError> {
     let server = ClientServer::new(service).await;
+    let (reporter, health_server) = tonic_health::server::health_reporter();
+    tokio::spawn(async move {
+        health
+            .report(reporter, &["", client_service_server::SERVICE_NAME])
+            .await;
+    });
     Server::builder()
+        .add_service(health_server)
         .add_service(ClientServiceServer::with_interceptor(server, check_auth))
-        .serve(addr)
-        .await;
+        .serve_with_shutdown(addr, async move {
+            let _ = shutdown.wait_for(|&stop| stop).await;
+        })
+        .await?;
     Ok(())
 }
 
@@ -171,6 +313,8 @@ struct Client {
     match_id: Option<Uuid>,
 
     core_tx: Sender<CoreMessage>,
+    /// 动作直接发给 MatchRunner, 不经过 Core
+    routes: Arc<Routes>,
     client_tx: Sender<Result<MatchPlayerResponse, Status>>,
     client_instream: Streaming<MatchPlayerRequest>,
 }
@@ -183,7 +327,7 @@ impl Client {
             tokio::select! {
                 msg = rx.recv() => {
                     let Some(msg) = msg else {
-                        tracing::info!("Internal channel closed. Shutting down client handler.");
+                        info!("core closed the connection");
                         break;
                     };
                     self.process_core_message(msg).await?;
@@ -195,17 +339,27 @@ impl Client {
                             self.porcess_client_resp(resp).await?;
                         }
                         Some(Err(e)) => {
-                            tracing::error!("Client stream error: {:?}", e);
+                            error!(error = ?e, "client stream error");
                             break;
                         }
                         None => {
-                            tracing::info!("Client stream closed normally. Shutting down client handler.");
+                            info!("client stream closed");
                             break;
                         }
                     }
                 },
             }
         }
+        // 比赛进行中断开时按出错弃权, 否则比赛会一直等待该 Agent 行动
+        if let Some(match_id) = self.match_id {
+            let resign = CoreMessage::AgentResign {
+                agent_id: self.agent_id,
+                match_id,
+                reason: "agent disconnected".to_string(),
+                error: true,
+            };
+            self.send_to_match(match_id, resign).await?;
+        }
         self.unregister().await?;
         Ok(())
     }
@@ -233,28 +387,80 @@ impl Client {
             CoreMessage::GameState {
                 agent_id,
                 match_id,
+                seat,
+                i_turn,
                 state,
             } => {
+                if self.match_id != Some(match_id) {
+                    Span::current().record("match_id", field::display(match_id));
+                }
                 self.match_id = Some(match_id);
                 self.client_tx
-                    .send(Ok(MatchPlayerResponse { state }))
+                    .send(Ok(MatchPlayerResponse {
+                        state,
+                        match_id: match_id.to_string(),
+                        seat,
+                        i_turn,
+                    }))
                     .await
                     .map_err(|e| AppError::Internal("trans error".to_string()))?;
             }
+            // 比赛已结束时, 之后的输出不再转发
+            CoreMessage::Rejected {
+                match_id,
+                reason,
+                closed,
+            } => {
+                warn!(%match_id, %reason, "agent output rejected");
+                if closed && self.match_id == Some(match_id) {
+                    self.match_id = None;
+                }
+            }
             _ => {}
         }
         Ok(())
     }
 
     async fn porcess_client_resp(&mut self, resp: MatchPlayerRequest) -> Result<(), AppError> {
-        let MatchPlayerRequest { action } = resp;
-        self.core_tx
-            .send(CoreMessage::AgentAction {
-                agent_id: self.agent_id,
-                match_id: self.match_id.unwrap(),
+        let MatchPlayerRequest { payload } = resp;
+        let agent_id = self.agent_id;
+        // 比赛开始前的输出没有归属, 直接丢弃
+        let Some(match_id) = self.match_id else {
+            return Ok(());
+        };
+        let msg = match payload {
+            Some(Payload::Action(action)) => CoreMessage::AgentAction {
+                agent_id,
+                match_id,
                 action,
-            })
-            .await?;
+            },
+            Some(Payload::Log(AgentLog { message })) => CoreMessage::AgentLog {
+                agent_id,
+                match_id,
+                message,
+            },
+            Some(Payload::Resign(AgentResign { reason, error })) => CoreMessage::AgentResign {
+                agent_id,
+                match_id,
+                reason,
+                error,
+            },
+            None => return Ok(()),
+        };
+        self.send_to_match(match_id, msg).await
+    }
+
+    /// 查不到比赛时交给 Core, 由 Core 转发或回复 Rejected
+    async fn send_to_match(&mut self, match_id: Uuid, msg: CoreMessage) -> Result<(), AppError> {
+        let msg = match self.routes.matches.get(&match_id) {
+            Some(match_runner) => match match_runner.send(msg).await {
+                Ok(()) => return Ok(()),
+                Err(SendError(msg)) => msg,
+            },
+            None => msg,
+        };
+        debug!(%match_id, "match route missing, sending through core");
+        self.core_tx.send(msg).await?;
         Ok(())
     }
 }
@@ -1,90 +1,131 @@
 use chrono::Utc;
-use futures_util::StreamExt;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::{clone, cmp::Ordering, collections::HashMap, iter::zip, sync::Arc};
+use futures_util::FutureExt;
+use std::{
+    any::Any, collections::HashMap, panic::AssertUnwindSafe, pin::Pin, sync::Arc,
+    time::Duration,
+};
 use tackle_box::{
     connection::{
-        GameControl, GameEndStatus, GameInitRequest, GameStateUpdate, PlayerAction, ProcessGameRequest, ProcessGameResponse, game_control::ControlType, process_game_request::RequestType, process_game_response::ResponseType, sponsor_service_client::SponsorServiceClient
+        match_monitor_response::EventType, sponsor_service_client::SponsorServiceClient,
+        MatchMonitorResponse, MatchUpdate,
+    },
+    contracts::payloads::AgentStatus,
+    metrics::ACTIVE_MATCHES,
+    runner::{connect_sponsor, MatchChannels, MatchRunner},
+};
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError, Receiver, Sender},
+        watch,
     },
-    contracts::payloads::{AgentStatus, MatchStatus},
+    time::{self, Instant, Sleep},
 };
-use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio_stream::wrappers::ReceiverStream;
-use tonic::{transport::Channel, Request, Streaming};
-use tracing::debug;
+use tonic::{transport::Channel, Status};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
-use crate::{
-    api::error::AppError,
-    repo::{
-        agents::AgentRepo,
-        matches::MatchRepo,
-        turns::{NewTurnDTO, TurnRepo},
-    },
+use crate::{api::error::AppError, core::persistence::Persistence};
+
+// 比赛任务和消息类型定义在库中, 基准测试可以直接使用
+pub use tackle_box::runner::{
+    CoreMessage, CoreStatus, GameSettlement, GameStreamType, MatchSettings, Routes, TurnLog,
 };
 
-pub enum CoreMessage {
-    ClientRegiser {
-        user_id: Uuid,
-        agent_id: Uuid,
-        tx: Sender<CoreMessage>,
-    },
-    ClientUnregiser {
-        user_id: Uuid,
-        agent_id: Uuid,
-    },
-    MatchStart {
-        match_id: Uuid,
-        agent_ids: Vec<Uuid>,
-        sponsor: String,
-        game_type: String,
-        total_games: i32,
-    },
-    AgentAction {
-        agent_id: Uuid,
-        match_id: Uuid,
-        action: String,
-    },
-    GameState {
+/// 消息处理失败时需要通知的一方
+#[derive(Debug, Clone, Copy)]
+enum Origin {
+    /// 客户端发来的消息, 通知客户端被拒绝
+    Client {
         agent_id: Uuid,
         match_id: Uuid,
-        state: String,
     },
-    MatchPause {
+    /// 比赛的开始与结算, 失败时取消比赛
+    Match {
         match_id: Uuid,
     },
-    MatchSettle {
-        match_id: Uuid,
-        settler: GameSettlement,
-    },
+    Core,
+}
+
+impl Origin {
+    fn of(msg: &CoreMessage) -> Self {
+        match msg {
+            CoreMessage::AgentAction {
+                agent_id, match_id, ..
+            }
+            | CoreMessage::AgentLog {
+                agent_id, match_id, ..
+            }
+            | CoreMessage::AgentResign {
+                agent_id, match_id, ..
+            } => Origin::Client {
+                agent_id: *agent_id,
+                match_id: *match_id,
+            },
+            CoreMessage::MatchStart { match_id, .. }
+            | CoreMessage::MatchPause { match_id, .. }
+            | CoreMessage::MatchSettle { match_id, .. } => Origin::Match {
+                match_id: *match_id,
+            },
+            _ => Origin::Core,
+        }
+    }
+}
+
+/// 错误的可读描述, Internal 的 Display 不含具体原因
+fn describe(e: &AppError) -> String {
+    match e {
+        AppError::Internal(message)
+        | AppError::Validation(message)
+        | AppError::MatchAborted(message) => message.clone(),
+        e => e.to_string(),
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
 }
 
 struct Connections {
-    clients: HashMap<Uuid, Sender<CoreMessage>>,
+    routes: Arc<Routes>,
     sponsors: HashMap<String, SponsorServiceClient<Channel>>,
-    matches: HashMap<Uuid, Sender<CoreMessage>>,
+    monitors: HashMap<Uuid, Vec<Sender<Result<MatchMonitorResponse, Status>>>>,
     tx: Sender<CoreMessage>,
     rx: Receiver<CoreMessage>,
+    /// 停机超时后通知所有 MatchRunner 中止
+    abort: watch::Sender<bool>,
 }
 
 struct Repos {
-    match_repo: Arc<MatchRepo>,
-    agent_repo: Arc<AgentRepo>,
-    turn_repo: Arc<TurnRepo>,
+    /// 状态更新和结算交给持久化队列, 不在 Core 中等待数据库
+    persistence: Persistence,
 }
 
+/// 停机时中止比赛后等待其保存对局的时间
+const ABORT_GRACE: Duration = Duration::from_secs(5);
+/// sponsor 在该时间内未完成握手时取消比赛
+const SPONSOR_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// RESTART_WINDOW 内 panic 超过 MAX_RESTARTS 次时放弃重启
+const MAX_RESTARTS: usize = 5;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
 pub struct Core {
     connections: Connections,
     repos: Repos,
+    /// 已收到停机信号, 不再开始新比赛
+    draining: bool,
+    /// 停机的截止时间与是否已中止比赛, supervise 重启 run 时沿用
+    drain_deadline: Option<Instant>,
+    aborted: bool,
 }
 
 impl Core {
     pub async fn new(
         sponsor_urls: HashMap<String, String>,
-        match_repo: Arc<MatchRepo>,
-        agent_repo: Arc<AgentRepo>,
-        turn_repo: Arc<TurnRepo>,
+        persistence: Persistence,
     ) -> Result<Self, AppError> {
         let (tx, rx) = mpsc::channel(8);
         let mut sponsors = HashMap::new();
@@ -92,80 +133,282 @@ impl Core {
             let sponsor = SponsorServiceClient::connect(url).await?;
             sponsors.insert(name, sponsor);
         }
-        let clients = HashMap::new();
-        let matches = HashMap::new();
+        let routes = Arc::new(Routes::default());
+        let monitors = HashMap::new();
+        let (abort, _) = watch::channel(false);
         Ok(Self {
             connections: Connections {
                 tx,
                 rx,
                 sponsors,
-                clients,
-                matches,
-            },
-            repos: Repos {
-                match_repo,
-                agent_repo,
-                turn_repo,
+                routes,
+                monitors,
+                abort,
             },
+            repos: Repos { persistence },
+            draining: false,
+            drain_deadline: None,
+            aborted: false,
         })
     }
 
     pub fn tx(&self) -> Sender<CoreMessage> {
         self.connections.tx.clone()
     }
-    pub async fn run(&mut self) -> Result<(), AppError> {
+
+    pub fn routes(&self) -> Arc<Routes> {
+        self.connections.routes.clone()
+    }
+
+    /// 运行 Core, panic 时重建状态后重新开始处理消息
+    ///
+    /// 短时间内反复 panic 说明状态无法恢复, 此时断开所有客户端并返回错误.
+    pub async fn supervise(
+        &mut self,
+        shutdown: watch::Receiver<bool>,
+        drain_timeout: Duration,
+    ) -> Result<(), AppError> {
+        let mut panics: Vec<Instant> = Vec::new();
         loop {
-            while let Some(msg) = self.connections.rx.recv().await {
-                match msg {
-                    CoreMessage::ClientRegiser {
-                        user_id,
-                        agent_id,
-                        tx,
-                    } => {
-                        self.process_client_register(agent_id, user_id, tx).await?;
-                    }
-                    CoreMessage::ClientUnregiser { user_id, agent_id } => {
-                        self.process_client_unregiser(agent_id, user_id).await?;
-                    }
-                    CoreMessage::AgentAction {
-                        agent_id,
-                        match_id,
-                        action,
-                    } => {
-                        self.process_agent_action(agent_id, match_id, action)
-                            .await?;
-                    }
-                    CoreMessage::GameState {
-                        agent_id,
-                        match_id,
-                        state,
-                    } => {
-                        self.process_game_state(agent_id, match_id, state).await?;
+            let run = self.run(shutdown.clone(), drain_timeout);
+            let panic = match AssertUnwindSafe(run).catch_unwind().await {
+                Ok(result) => return result,
+                Err(panic) => panic,
+            };
+            let now = Instant::now();
+            panics.retain(|at| now.duration_since(*at) < RESTART_WINDOW);
+            panics.push(now);
+            error!(
+                panic = panic_message(&*panic),
+                restarts = panics.len(),
+                "core panicked"
+            );
+            if panics.len() > MAX_RESTARTS {
+                self.close_clients().await;
+                return Err(AppError::Internal(format!(
+                    "core panicked {} times within {:?}",
+                    panics.len(),
+                    RESTART_WINDOW
+                )));
+            }
+            self.recover().await;
+        }
+    }
+
+    /// 处理消息直到收到停机信号且所有比赛结束
+    ///
+    /// 停机时不再开始新比赛, 等待进行中的比赛在 drain_timeout 内结束,
+    /// 超时后中止剩余比赛并保存已完成的对局. 退出时断开所有客户端和监控流.
+    pub async fn run(
+        &mut self,
+        mut shutdown: watch::Receiver<bool>,
+        drain_timeout: Duration,
+    ) -> Result<(), AppError> {
+        let deadline = time::sleep_until(self.drain_deadline.unwrap_or_else(Instant::now));
+        tokio::pin!(deadline);
+        loop {
+            if self.draining && self.connections.routes.matches.is_empty() {
+                break;
+            }
+            tokio::select! {
+                Some(msg) = self.connections.rx.recv() => {
+                    self.dispatch(msg).await;
+                }
+                _ = shutdown.changed(), if !self.draining => {
+                    info!(
+                        "shutting down, waiting for {} running matches",
+                        self.connections.routes.matches.len()
+                    );
+                    self.draining = true;
+                    self.reset_deadline(deadline.as_mut(), drain_timeout);
+                }
+                _ = &mut deadline, if self.draining => {
+                    if self.aborted {
+                        warn!(
+                            "{} matches did not stop in time",
+                            self.connections.routes.matches.len()
+                        );
+                        break;
                     }
-                    CoreMessage::MatchStart {
+                    warn!(
+                        "aborting {} matches still running after {:?}",
+                        self.connections.routes.matches.len(),
+                        drain_timeout
+                    );
+                    let _ = self.connections.abort.send(true);
+                    self.aborted = true;
+                    self.reset_deadline(deadline.as_mut(), ABORT_GRACE);
+                }
+            }
+        }
+        self.close_clients().await;
+        info!("core stopped");
+        Ok(())
+    }
+
+    fn reset_deadline(&mut self, deadline: Pin<&mut Sleep>, after: Duration) {
+        let at = Instant::now() + after;
+        self.drain_deadline = Some(at);
+        deadline.reset(at);
+    }
+
+    /// 单条消息出错只通知其来源, 不影响其他比赛; panic 时通知来源后交给 supervise 处理
+    async fn dispatch(&mut self, msg: CoreMessage) {
+        let origin = Origin::of(&msg);
+        match AssertUnwindSafe(self.process_message(msg))
+            .catch_unwind()
+            .await
+        {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => self.report(origin, e).await,
+            Err(panic) => {
+                let message = format!("core panicked: {}", panic_message(&*panic));
+                self.report(origin, AppError::Internal(message)).await;
+                std::panic::resume_unwind(panic);
+            }
+        }
+    }
+
+    /// 通知时不等待对方, 以免与正在向 Core 发送消息的一方互相等待
+    async fn report(&mut self, origin: Origin, e: AppError) {
+        match origin {
+            Origin::Client { agent_id, match_id } => {
+                warn!(%agent_id, %match_id, error = ?e, "client message rejected");
+                if let Some(client) = self.connections.routes.clients.get(&agent_id) {
+                    let reason = describe(&e);
+                    let _ = client.try_send(CoreMessage::Rejected {
                         match_id,
-                        agent_ids,
-                        sponsor,
-                        game_type,
-                        total_games,
-                    } => {
-                        self.process_match_start(
-                            match_id,
-                            agent_ids,
-                            sponsor,
-                            game_type,
-                            total_games,
-                        )
+                        reason,
+                        closed: true,
+                    });
+                }
+            }
+            Origin::Match { match_id } => {
+                error!(%match_id, error = ?e, "match failed");
+                self.fail_match(match_id, describe(&e)).await;
+            }
+            Origin::Core => error!(error = ?e, "core message failed"),
+        }
+    }
+
+    /// 比赛无法继续时取消并通知监控端
+    async fn fail_match(&mut self, match_id: Uuid, reason: String) {
+        self.process_match_event(
+            match_id,
+            EventType::MatchUpdate(MatchUpdate {
+                current_status: "Cancelled".to_string(),
+                message: reason,
+            }),
+        );
+        if let Err(e) = self
+            .repos
+            .persistence
+            .cancel(match_id, Vec::new(), Vec::new())
+            .await
+        {
+            warn!(%match_id, error = ?e, "failed to cancel match");
+        }
+        self.close_match(match_id);
+    }
+
+    /// panic 后重建状态: 移除已断开的客户端和监控, 取消已失去 MatchRunner 的比赛
+    async fn recover(&mut self) {
+        let routes = self.routes();
+        for (agent_id, _) in routes.clients.retain(|_, tx| !tx.is_closed()) {
+            if let Err(e) = self
+                .repos
+                .persistence
+                .set_agent_status(agent_id, AgentStatus::Idle)
+                .await
+            {
+                warn!(%agent_id, error = ?e, "failed to reset agent status");
+            }
+        }
+        for monitors in self.connections.monitors.values_mut() {
+            monitors.retain(|tx| !tx.is_closed());
+        }
+        for (match_id, _) in routes.matches.retain(|_, tx| !tx.is_closed()) {
+            self.fail_match(match_id, "match runner lost after core restart".to_string())
+                .await;
+        }
+        info!(
+            clients = routes.clients.len(),
+            matches = routes.matches.len(),
+            "core recovered"
+        );
+    }
+
+    async fn process_message(&mut self, msg: CoreMessage) -> Result<(), AppError> {
+        match msg {
+            CoreMessage::ClientRegiser {
+                user_id,
+                agent_id,
+                tx,
+            } => {
+                self.process_client_register(agent_id, user_id, tx).await?;
+            }
+            CoreMessage::ClientUnregiser { user_id, agent_id } => {
+                self.process_client_unregiser(agent_id, user_id).await?;
+            }
+            // 客户端直接投递失败时才经过 Core, 由 Core 转发或拒绝
+            msg @ (CoreMessage::AgentAction { match_id, .. }
+            | CoreMessage::AgentLog { match_id, .. }
+            | CoreMessage::AgentResign { match_id, .. }) => {
+                self.forward_to_match(match_id, msg).await?;
+            }
+            // 由 MatchRunner 直接发给客户端, 不会发给 Core
+            CoreMessage::GameState { .. } | CoreMessage::Rejected { .. } => {}
+            CoreMessage::MatchStart {
+                match_id,
+                agent_ids,
+                sponsor,
+                game_type,
+                settings,
+            } => {
+                if self.draining {
+                    warn!(%match_id, "server is shutting down, match cancelled");
+                    self.repos
+                        .persistence
+                        .cancel(match_id, agent_ids, Vec::new())
+                        .await?;
+                } else {
+                    let span = info_span!("match_start", %match_id, %game_type);
+                    self.process_match_start(match_id, agent_ids, sponsor, game_type, settings)
+                        .instrument(span)
                         .await?;
-                    }
-                    CoreMessage::MatchPause { match_id } => {
-                        self.process_match_pause(match_id).await?;
-                    }
-                    CoreMessage::MatchSettle { match_id, settler } => {
-                        self.process_match_settle(settler).await?;
-                    }
                 }
             }
+            CoreMessage::MatchPause {
+                match_id,
+                agent_ids,
+                logs,
+            } => {
+                self.repos
+                    .persistence
+                    .cancel(match_id, agent_ids, logs)
+                    .await?;
+                self.close_match(match_id);
+            }
+            CoreMessage::MatchSettle { match_id, settler } => {
+                self.repos.persistence.settle(settler).await?;
+                self.close_match(match_id);
+            }
+            CoreMessage::MonitorRegister { match_id, tx } => {
+                self.process_monitor_register(match_id, tx);
+            }
+            CoreMessage::MatchEvent { match_id, event } => {
+                self.process_match_event(match_id, event);
+            }
+            CoreMessage::SponsorLookup { sponsor, tx } => {
+                let _ = tx.send(self.connections.sponsors.get(&sponsor).cloned());
+            }
+            CoreMessage::Health { tx } => {
+                let _ = tx.send(CoreStatus {
+                    draining: self.draining,
+                    clients: self.connections.routes.clients.len(),
+                    matches: self.connections.routes.matches.len(),
+                });
+            }
         }
         Ok(())
     }
@@ -176,13 +419,12 @@ impl Core {
         user_id: Uuid,
         tx: Sender<CoreMessage>,
     ) -> Result<(), AppError> {
-        debug!("client regisered");
-        self.connections.clients.insert(agent_id, tx);
+        debug!(%agent_id, %user_id, "client registered");
+        self.connections.routes.clients.insert(agent_id, tx);
         self.repos
-            .agent_repo
-            .update_agent_status(agent_id, AgentStatus::Ready)
+            .persistence
+            .set_agent_status(agent_id, AgentStatus::Ready)
             .await?;
-        debug!("updated agent status");
         Ok(())
     }
 
@@ -191,31 +433,10 @@ impl Core {
         agent_id: Uuid,
         user_id: Uuid,
     ) -> Result<(), AppError> {
-        let _ = self.connections.clients.remove(&agent_id);
+        let _ = self.connections.routes.clients.remove(&agent_id);
         self.repos
-            .agent_repo
-            .update_agent_status(agent_id, AgentStatus::Idle)
-            .await?;
-        Ok(())
-    }
-
-    async fn process_game_state(
-        &mut self,
-        agent_id: Uuid,
-        match_id: Uuid,
-        state: String,
-    ) -> Result<(), AppError> {
-        let client = self
-            .connections
-            .clients
-            .get(&agent_id)
-            .ok_or(AppError::Internal("no found Error".to_string()))?;
-        client
-            .send(CoreMessage::GameState {
-                agent_id,
-                match_id,
-                state,
-            })
+            .persistence
+            .set_agent_status(agent_id, AgentStatus::Idle)
             .await?;
         Ok(())
     }
@@ -226,309 +447,159 @@ impl Core {
         agent_ids: Vec<Uuid>,
         sponsor: String,
         game_type: String,
-        total_games: i32,
+        settings: MatchSettings,
     ) -> Result<(), AppError> {
+        let MatchSettings { seed, mode, .. } = settings;
+        let Some(client) = self.connections.sponsors.get(&sponsor).cloned() else {
+            return Err(AppError::Internal(format!("sponsor {} not found", sponsor)));
+        };
         let (match_tx, match_rx) = mpsc::channel(8);
         let core_tx = self.tx();
-        let (sponsor_tx, sponsor_rx) = match self.connections.sponsors.get_mut(&sponsor) {
-            Some(sponsor) => {
-                let (sponser_tx, sponsor_rx) = mpsc::channel(16);
-                let init_req = ProcessGameRequest {
-                    request_type: Some(RequestType::Init(GameInitRequest {
-                        game_type: game_type.clone(),
-                    })),
-                };
-                let request_stream = ReceiverStream::new(sponsor_rx);
-                let resp = sponsor.process_game(Request::new(request_stream)).await?;
-                sponser_tx.send(init_req).await?;
-                let mut sponsor_instream = resp.into_inner();
-                // 丢弃一次应答, 因为Python后段依赖至少一次回复来生成流
-                let _ = sponsor_instream.next().await;
-                (sponser_tx, sponsor_instream)
-            }
-            None => return Err(AppError::Internal("Not Find Sponsor".to_string())),
-        };
-        self.connections.matches.insert(match_id, match_tx);
-        let mut match_runner = MatchRunner {
-            match_id,
-            agent_ids,
-            sponsor,
-            game_type,
-            total_games,
-            match_rx,
-            core_tx,
-            sponsor_tx,
-            sponsor_rx,
-            i_turn: 0,
-            turn_log: Some(Vec::new()),
-            game_logs: Some(Vec::new()),
-        };
+        self.connections.routes.matches.insert(match_id, match_tx);
+        self.repos.persistence.start(match_id).await?;
+        let guard = ACTIVE_MATCHES.track(&[&game_type]);
+        let span = info_span!(parent: None, "match", %match_id, %game_type, %sponsor, ?mode);
+        let routes = self.routes();
+        let abort = self.connections.abort.subscribe();
 
-        tokio::spawn(async move {
-            match match_runner.run().await {
-                Ok(_) => {}
-                Err(e) => {
-                    tracing::error!("{}", e.to_string());
+        tokio::spawn(
+            async move {
+                let _guard = guard;
+                // sponsor 握手可能很慢, 在比赛任务中进行, 不占用 Core
+                let handshake = time::timeout(
+                    SPONSOR_HANDSHAKE_TIMEOUT,
+                    connect_sponsor(client, &game_type, seed),
+                )
+                .await;
+                let (sponsor_tx, sponsor_rx) = match handshake {
+                    Ok(Ok(streams)) => streams,
+                    Ok(Err(e)) => {
+                        return cancel_match(&core_tx, match_id, agent_ids, e.to_string()).await
+                    }
+                    Err(_) => {
+                        let reason = "sponsor handshake timed out".to_string();
+                        return cancel_match(&core_tx, match_id, agent_ids, reason).await;
+                    }
+                };
+                let channels = MatchChannels {
+                    match_rx,
+                    core_tx: core_tx.clone(),
+                    routes,
+                    sponsor_tx,
+                    sponsor_rx,
+                    abort,
+                };
+                let mut match_runner = MatchRunner::new(
+                    match_id,
+                    agent_ids.clone(),
+                    sponsor,
+                    game_type,
+                    settings,
+                    channels,
+                );
+                match AssertUnwindSafe(match_runner.run()).catch_unwind().await {
+                    Ok(Ok(_)) => info!("match finished"),
+                    Ok(Err(e)) => error!(error = %e, "match aborted"),
+                    // 已完成的对局随 MatchRunner 丢失, 只取消比赛
+                    Err(panic) => {
+                        error!(panic = panic_message(&*panic), "match runner panicked");
+                        let _ = core_tx
+                            .send(CoreMessage::MatchPause {
+                                match_id,
+                                agent_ids,
+                                logs: Vec::new(),
+                            })
+                            .await;
+                    }
                 }
             }
-        });
+            .instrument(span),
+        );
         Ok(())
     }
 
-    async fn process_agent_action(
-        &mut self,
-        agent_id: Uuid,
-        match_id: Uuid,
-        action: String,
-    ) -> Result<(), AppError> {
-        debug!("core recv agent action");
+    async fn forward_to_match(&mut self, match_id: Uuid, msg: CoreMessage) -> Result<(), AppError> {
         let match_runner = self
             .connections
+            .routes
             .matches
             .get(&match_id)
-            .ok_or(AppError::Internal("no found Error".to_string()))?;
-        match_runner
-            .send(CoreMessage::AgentAction {
-                agent_id,
-                match_id,
-                action,
-            })
-            .await?;
-        debug!("send to match runner");
+            .ok_or_else(|| AppError::Internal(format!("match {} is not running", match_id)))?;
+        match_runner.send(msg).await?;
         Ok(())
     }
-    pub async fn process_match_settle(&self, settler: GameSettlement) -> Result<(), AppError> {
-        let GameSettlement {
-            match_id,
-            agent_ids,
-            logs,
-        } = settler;
-        let Repos {
-            agent_repo,
-            match_repo,
-            turn_repo,
-            ..
-        } = &self.repos;
-
-        let mut score_map: HashMap<Uuid, f32> = agent_ids.iter().map(|&id| (id, 0.0)).collect();
-
-        let mut tx = match_repo.get_transaction().await?;
-        for (i_turn, log) in logs.into_iter().enumerate() {
-            let TurnLog {
-                logs: turn_log,
-                payoffs,
-            } = log;
-            let score_deltas: HashMap<Uuid, f32> =
-                HashMap::from_iter(zip(agent_ids.clone(), payoffs));
-
-            for (id, score) in &score_deltas {
-                score_map.entry(*id).and_modify(|s| *s += score);
-            }
 
-            let turn = NewTurnDTO {
-                match_id,
-                i_turn: i_turn as i32,
-                log: json!(turn_log),
-                score_deltas: json!(score_deltas),
-                start_time: Utc::now(),
-                end_time: Utc::now(),
-            };
-            turn_repo.insert_turn(&mut tx, turn).await?;
-        }
-        let winner_id = score_map
-            .iter()
-            .max_by(|&(_, &v1), &(_, &v2)| v1.partial_cmp(&v2).unwrap_or(Ordering::Equal))
-            .map(|(k, _)| *k);
-        match winner_id {
-            Some(winner_id) => {
-                for agent_id in agent_ids {
-                    if winner_id == agent_id {
-                        agent_repo.agent_won(&mut tx, agent_id).await?
-                    } else {
-                        agent_repo.agent_failed(&mut tx, agent_id).await?
-                    }
-                }
-            }
-            None => {
-                for agent_id in agent_ids {
-                    agent_repo.agent_failed(&mut tx, agent_id).await?;
-                }
-            }
-        };
-
-        match_repo
-            .update_match_final_status(&mut tx, match_id, winner_id)
-            .await?;
-        tx.commit().await.unwrap();
-        Ok(())
+    fn process_monitor_register(
+        &mut self,
+        match_id: Uuid,
+        tx: Sender<Result<MatchMonitorResponse, Status>>,
+    ) {
+        debug!(%match_id, "monitor registered");
+        self.connections
+            .monitors
+            .entry(match_id)
+            .or_default()
+            .push(tx);
     }
 
-    async fn process_match_pause(&self, match_id: Uuid) -> Result<(), AppError> {
-        self.repos.match_repo.update_match_status(match_id, MatchStatus::Cancelled).await?;
-        Ok(())
+    fn process_match_event(&mut self, match_id: Uuid, event: EventType) {
+        let Some(monitors) = self.connections.monitors.get_mut(&match_id) else {
+            return;
+        };
+        let resp = MatchMonitorResponse {
+            timestamp: Utc::now().timestamp_millis(),
+            event_type: Some(event),
+        };
+        // 监控端消费过慢时丢弃本条事件, 不阻塞 Core
+        monitors.retain(|tx| !matches!(tx.try_send(Ok(resp.clone())), Err(TrySendError::Closed(_))));
     }
-}
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TurnLog {
-    pub logs: Vec<GameStreamType>,
-    pub payoffs: Vec<f32>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct GameSettlement {
-    pub match_id: Uuid,
-    pub agent_ids: Vec<Uuid>,
-    pub logs: Vec<TurnLog>,
-}
+    /// 停机时把在线的 Agent 置为空闲, 释放 Sender 以关闭客户端和监控流
+    async fn close_clients(&mut self) {
+        for (agent_id, _) in self.connections.routes.clients.drain() {
+            if let Err(e) = self
+                .repos
+                .persistence
+                .set_agent_status(agent_id, AgentStatus::Idle)
+                .await
+            {
+                warn!(%agent_id, error = ?e, "failed to reset agent status");
+            }
+        }
+        self.connections.monitors.clear();
+        self.connections.routes.matches.drain();
+    }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub enum GameStreamType {
-    State(String),
-    Action(String),
+    /// 比赛结束后移除路由与监控, 监控流随 Sender 释放而关闭
+    fn close_match(&mut self, match_id: Uuid) {
+        self.connections.routes.matches.remove(&match_id);
+        self.connections.monitors.remove(&match_id);
+    }
 }
 
-struct MatchRunner {
+/// 比赛未能开始时通知监控端并由 Core 取消比赛
+async fn cancel_match(
+    core_tx: &Sender<CoreMessage>,
     match_id: Uuid,
     agent_ids: Vec<Uuid>,
-    sponsor: String,
-    game_type: String,
-    total_games: i32,
-
-    match_rx: Receiver<CoreMessage>,
-    core_tx: Sender<CoreMessage>,
-    sponsor_tx: Sender<ProcessGameRequest>,
-    sponsor_rx: Streaming<ProcessGameResponse>,
-
-    i_turn: i32,
-    game_logs: Option<Vec<TurnLog>>,
-    turn_log: Option<Vec<GameStreamType>>,
+    reason: String,
+) {
+    error!(%reason, "failed to start match");
+    let _ = core_tx
+        .send(CoreMessage::MatchEvent {
+            match_id,
+            event: EventType::MatchUpdate(MatchUpdate {
+                current_status: "Cancelled".to_string(),
+                message: reason,
+            }),
+        })
+        .await;
+    let _ = core_tx
+        .send(CoreMessage::MatchPause {
+            match_id,
+            agent_ids,
+            logs: Vec::new(),
+        })
+        .await;
 }
 
-impl MatchRunner {
-    async fn run(&mut self) -> Result<(), AppError> {
-        let loop_result: Result<(), AppError> = async {
-            while self.i_turn < self.total_games {
-                let r = tokio::select! {
-                    Some(msg) = self.match_rx.recv() => {
-                        self.process_core_message(msg).await
-                    },
-                    Some(Ok(resp)) = self.sponsor_rx.next() => {
-                        self.process_sponsor_message(resp).await
-                    },
-                    else => {
-                        return Err(AppError::MatchAborted("Input stream/channel closed unexpectedly.".to_string()));
-                    }
-                };
-                r?; 
-            }
-            // 比赛正常完成
-            Ok(())
-        }.await;
-        if let Err(e) = loop_result {
-                // if e.is_connection_error() || e.is_match_aborted() { // 假设 AppError 有这些辅助方法
-                    
-                //     // 🚀 核心：更新比赛状态为 CANCELLED
-                //     self.core_tx.send(CoreMessage::MatchStatusUpdate {
-                //         match_id: self.match_id,
-                //         status: MatchStatus::Cancelled,
-                //     }).await?;
-                    
-                //     // 返回错误，但已经执行了清理/取消操作
-                //     return Err(e);
-                // } else {
-                //     // 如果是其他不应该导致取消的内部逻辑错误
-                //     return Err(e);
-                // }
-                self.core_tx.send(CoreMessage::MatchPause { match_id: self.match_id }).await?;
-                return Err(e);
-            }
-        self.core_tx
-            .send(CoreMessage::MatchSettle {
-                match_id: self.match_id,
-                settler: GameSettlement {
-                    match_id: self.match_id,
-                    agent_ids: self.agent_ids.clone(),
-                    logs: self.game_logs.take().unwrap(),
-                },
-            })
-            .await?;
-        Ok(())
-    }
-
-    async fn process_core_message(&mut self, msg: CoreMessage) -> Result<(), AppError> {
-        match msg {
-            CoreMessage::AgentAction {
-                agent_id,
-                match_id,
-                action,
-            } => {
-                self.turn_log
-                    .get_or_insert(vec![])
-                    .push(GameStreamType::Action(action.clone()));
-                self.sponsor_tx
-                    .send(ProcessGameRequest {
-                        request_type: Some(RequestType::Action(PlayerAction { action: action })),
-                    })
-                    .await?;
-            }
-            _ => return Err(AppError::Internal("unknow error".to_string())),
-        };
-        Ok(())
-    }
-
-    async fn process_sponsor_message(&mut self, resp: ProcessGameResponse) -> Result<(), AppError> {
-        let ProcessGameResponse { response_type } = resp;
-        match response_type {
-            Some(ResponseType::StateUpdate(data)) => {
-                let GameStateUpdate {
-                    state,
-                    is_over,
-                    i_player,
-                } = data;
-                let agent_id = self.agent_ids[i_player as usize];
-                self.turn_log
-                    .get_or_insert(vec![])
-                    .push(GameStreamType::State(state.clone()));
-                if !is_over {
-                    self.core_tx
-                        .send(CoreMessage::GameState {
-                            agent_id,
-                            match_id: self.match_id,
-                            state: state,
-                        })
-                        .await?;
-                }
-            }
-            Some(ResponseType::EndStatus(data)) => {
-                let GameEndStatus { payoffs } = data;
-                let turn_log = TurnLog {
-                    logs: self.turn_log.take().unwrap(),
-                    payoffs,
-                };
-                self.game_logs.get_or_insert(vec![]).push(turn_log);
-                self.i_turn += 1;
-                if self.i_turn == self.total_games {
-                    self.sponsor_tx
-                        .send(ProcessGameRequest {
-                            request_type: Some(RequestType::Control(GameControl {
-                                r#type: ControlType::Pause.into(),
-                            })),
-                        })
-                        .await?;
-                    // self.sponsor_tx.closed().await;
-                } else {
-                    self.sponsor_tx
-                        .send(ProcessGameRequest {
-                            request_type: Some(RequestType::Control(GameControl {
-                                r#type: ControlType::Resume.into(),
-                            })),
-                        })
-                        .await?;
-                }
-            }
-            _ => return Err(AppError::Internal("unknow error".to_string())),
-        };
-        Ok(())
-    }
-}
@@ -0,0 +1,157 @@
+//! 存活与就绪检查, 供 /healthz, /readyz 和 gRPC 健康服务使用
+
+use futures_util::future::join_all;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc::Sender, oneshot, watch},
+    time::timeout,
+};
+use tonic::transport::Endpoint;
+use tonic_health::{server::HealthReporter, ServingStatus};
+
+use crate::{
+    core::core::{CoreMessage, CoreStatus},
+    repo::game_type::GameTypeRepo,
+};
+
+/// 单项检查的超时时间, 超时视为失败
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// gRPC 健康状态的刷新间隔
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    /// 可以接受新比赛
+    pub ready: bool,
+    pub database: bool,
+    /// Core 未在超时内回复时为空
+    pub core: Option<CoreStatus>,
+    pub draining: bool,
+    pub sponsors: Vec<SponsorHealth>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SponsorHealth {
+    pub game_type: String,
+    pub sponsor: String,
+    pub connected: bool,
+}
+
+pub struct HealthService {
+    pool: Arc<PgPool>,
+    gametype_repo: Arc<GameTypeRepo>,
+    core_tx: Sender<CoreMessage>,
+    sponsor_urls: HashMap<String, String>,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl HealthService {
+    pub fn new(
+        pool: Arc<PgPool>,
+        gametype_repo: Arc<GameTypeRepo>,
+        core_tx: Sender<CoreMessage>,
+        sponsor_urls: HashMap<String, String>,
+        shutdown: watch::Receiver<bool>,
+    ) -> Self {
+        Self {
+            pool,
+            gametype_repo,
+            core_tx,
+            sponsor_urls,
+            shutdown,
+        }
+    }
+
+    /// 存活检查只看 Core, 数据库或 sponsor 故障不能靠重启恢复
+    pub async fn liveness(&self) -> Option<CoreStatus> {
+        self.core_status().await
+    }
+
+    /// sponsor 断开只影响对应的游戏类型, 在报告中列出但不影响就绪
+    pub async fn readiness(&self) -> HealthReport {
+        let (core, database, sponsors) =
+            tokio::join!(self.core_status(), self.database(), self.sponsors());
+        let draining = *self.shutdown.borrow() || core.is_some_and(|status| status.draining);
+        HealthReport {
+            ready: core.is_some() && database && !draining,
+            database,
+            core,
+            draining,
+            sponsors,
+        }
+    }
+
+    /// 定期把就绪状态同步到 gRPC 健康服务, 停机时立即置为 NOT_SERVING
+    pub async fn report(&self, reporter: HealthReporter, services: &[&str]) {
+        let mut shutdown = self.shutdown.clone();
+        loop {
+            let status = if self.readiness().await.ready {
+                ServingStatus::Serving
+            } else {
+                ServingStatus::NotServing
+            };
+            for service in services {
+                reporter.set_service_status(*service, status).await;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(REPORT_INTERVAL) => {}
+                // 停机信号只会由 false 变为 true
+                _ = shutdown.changed() => {
+                    for service in services {
+                        reporter
+                            .set_service_status(*service, ServingStatus::NotServing)
+                            .await;
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn core_status(&self) -> Option<CoreStatus> {
+        let (tx, rx) = oneshot::channel();
+        timeout(CHECK_TIMEOUT, async {
+            self.core_tx.send(CoreMessage::Health { tx }).await.ok()?;
+            rx.await.ok()
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    async fn database(&self) -> bool {
+        let ping = sqlx::query("SELECT 1").execute(&*self.pool);
+        matches!(timeout(CHECK_TIMEOUT, ping).await, Ok(Ok(_)))
+    }
+
+    /// 每个 sponsor 只探测一次, 再按游戏类型展开
+    async fn sponsors(&self) -> Vec<SponsorHealth> {
+        let names: Vec<&String> = self.sponsor_urls.keys().collect();
+        let probes = names.iter().map(|name| probe(&self.sponsor_urls[*name]));
+        let results = join_all(probes).await;
+        let connected: HashMap<&String, bool> = names.into_iter().zip(results).collect();
+        let game_types = match self.gametype_repo.get_game_types().await {
+            Ok(game_types) => game_types,
+            Err(_) => return Vec::new(),
+        };
+        game_types
+            .into_iter()
+            .map(|game_type| SponsorHealth {
+                connected: connected.get(&game_type.sponsor).copied().unwrap_or(false),
+                game_type: game_type.name,
+                sponsor: game_type.sponsor,
+            })
+            .collect()
+    }
+}
+
+/// 新建连接完成握手即视为可达, 不复用 Core 持有的连接
+async fn probe(url: &str) -> bool {
+    let endpoint = match Endpoint::from_shared(url.to_string()) {
+        Ok(endpoint) => endpoint.connect_timeout(CHECK_TIMEOUT),
+        Err(_) => return false,
+    };
+    matches!(timeout(CHECK_TIMEOUT, endpoint.connect()).await, Ok(Ok(_)))
+}
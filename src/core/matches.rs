@@ -1,24 +1,40 @@
-use std::sync::Arc;
+use flate2::{write::GzEncoder, Compression};
+use futures_util::{stream, Stream};
+use serde_json::{json, Value};
+use std::{collections::HashSet, io::Write, sync::Arc};
 use tackle_box::contracts::payloads::{
-    GetGameTypeResponse, GetMatchResponse, GetOnlineMatchResponse, GetParticipantsResponse,
-    MatchStatus, NewMatchPayload, TurnLogResponse,
+    ExportCursor, ExportFormat, ExportTurnsPayload, GetGameTypeResponse, GetMatchResponse,
+    GetOnlineMatchResponse, GetParticipantsResponse, MatchStatus, NewMatchPayload, TurnLogResponse,
 };
 use tokio::sync::mpsc::Sender;
 use uuid::Uuid;
 
 use crate::{
     api::error::AppError,
-    core::core::CoreMessage,
+    core::core::{CoreMessage, MatchSettings},
     repo::{
         agents::AgentRepo,
+        error::RepoError,
         game_type::GameTypeRepo,
         matches::{MatchRepo, NewMatchDTO},
         participation::ParticipationRepo,
-        turns::TurnRepo,
+        turns::{TurnFilterDTO, TurnRepo},
         users::UserRepo,
     },
 };
 
+/// 导出时每次从数据库读取的局数
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+/// 导出流在两页之间保存的状态
+struct ExportState {
+    filter: TurnFilterDTO,
+    cursor: Option<ExportCursor>,
+    remaining: i64,
+    encoder: Option<GzEncoder<Vec<u8>>>,
+    done: bool,
+}
+
 struct Repos {
     pub gametype_repo: Arc<GameTypeRepo>,
     pub user_repo: Arc<UserRepo>,
@@ -87,13 +103,15 @@ impl MatchService {
         &self,
         user_id: Uuid,
         one_match: NewMatchPayload,
-    ) -> Result<(), AppError> {
+    ) -> Result<Uuid, AppError> {
         let NewMatchPayload {
             name,
             game_type_id,
             total_games,
             with_agent_ids,
             password,
+            seed,
+            mode,
         } = one_match;
 
         let one_match = NewMatchDTO {
@@ -102,11 +120,17 @@ impl MatchService {
             total_games,
             creater_id: user_id,
             password: password.clone(),
+            seed: seed.unwrap_or_else(random_seed),
+            mode,
+            tournament_id: None,
+            round: None,
         };
-        let match_id = self.repos.match_repo.new_match(one_match).await?;
+        let mut tx = self.repos.match_repo.get_transaction().await?;
+        let match_id = self.repos.match_repo.new_match(&mut tx, one_match).await?;
+        tx.commit().await.map_err(RepoError::from)?;
         self.join_match(user_id, match_id, with_agent_ids, password)
             .await?;
-        Ok(())
+        Ok(match_id)
     }
 
     pub async fn join_match(
@@ -139,34 +163,44 @@ impl MatchService {
                 "exceeding max slots for this match".to_string(),
             ));
         } else {
+            let mut tx = self.repos.match_repo.get_transaction().await?;
             for agent_id in agent_ids {
                 self.repos
                     .participation_repo
-                    .insert_participant(match_id, agent_id)
+                    .insert_participant(&mut tx, match_id, agent_id)
                     .await?;
             }
+            tx.commit().await.map_err(RepoError::from)?;
         }
 
         if counts + join_agents_len >= one_match.min_slots {
             let GetMatchResponse {
+                game_type_id,
                 game_type_name,
                 total_games,
+                seed,
+                mode,
                 ..
             } = self.repos.match_repo.get_match(match_id).await?;
+            // 座位和并列时的名次都按加入顺序
             let agent_ids = self
                 .repos
                 .participation_repo
-                .get_participants(match_id)
-                .await?
-                .iter()
-                .map(|p| p.agent_id)
-                .collect();
+                .get_agent_ids(match_id)
+                .await?;
+            // 与锦标赛和回放校验一致, 使用游戏类型配置的 sponsor
+            let game_type = self.repos.gametype_repo.get_game_type(game_type_id).await?;
             self.start_match(
                 match_id,
                 agent_ids,
-                "rlcard".to_string(),
+                game_type.sponsor,
                 game_type_name,
-                total_games,
+                MatchSettings {
+                    total_games,
+                    // 旧比赛没有种子, 开始时补一个, 每局的种子仍会记录在回合中
+                    seed: seed.unwrap_or_else(random_seed),
+                    mode,
+                },
             )
             .await?;
         }
@@ -180,7 +214,7 @@ impl MatchService {
         agent_ids: Vec<Uuid>,
         sponsor: String,
         game_type: String,
-        total_games: i32,
+        settings: MatchSettings,
     ) -> Result<(), AppError> {
         self.senders
             .core_tx
@@ -189,23 +223,107 @@ impl MatchService {
                 agent_ids,
                 sponsor,
                 game_type,
-                total_games,
+                settings,
             })
             .await?;
         Ok(())
     }
 
-    // pub async fn leave_match(&self, user_id: Uuid, agent_name: &String) -> Result<(), AppError> {}
+    pub async fn leave_match(
+        &self,
+        user_id: Uuid,
+        match_id: Uuid,
+        agent_ids: Vec<Uuid>,
+    ) -> Result<(), AppError> {
+        let match_status = self.repos.match_repo.get_match_status(match_id).await?;
+        if match_status != MatchStatus::Pending {
+            return Err(AppError::Validation(
+                "match is not pending, cannot leave".to_string(),
+            ));
+        }
+        for agent_id in agent_ids {
+            let agent = self.repos.agent_repo.get_agent(agent_id).await?;
+            if agent.owner_id != user_id {
+                return Err(AppError::Validation(format!(
+                    "agent {} is not owned by you",
+                    agent.name
+                )));
+            }
+            self.repos
+                .participation_repo
+                .remove_participants(match_id, agent_id)
+                .await?;
+        }
+        Ok(())
+    }
 
     pub async fn get_match_logs(
         &self,
         user_id: Uuid,
         match_id: Uuid,
     ) -> Result<Vec<TurnLogResponse>, AppError> {
-        let turns = self.repos.turn_repo.get_turns(match_id).await?;
+        let mut turns = self.repos.turn_repo.get_turns(match_id).await?;
+        let my_agents: HashSet<Uuid> = self
+            .repos
+            .agent_repo
+            .get_my_agents(user_id)
+            .await?
+            .into_iter()
+            .map(|agent| agent.agent_id)
+            .collect();
+        for turn in turns.iter_mut() {
+            hide_foreign_logs(&mut turn.log, &my_agents);
+        }
         Ok(turns)
     }
 
+    /// 按条件流式导出回合, 逐页读取数据库, 不在内存中保留全部结果
+    pub async fn export_turns(
+        &self,
+        user_id: Uuid,
+        payload: ExportTurnsPayload,
+    ) -> Result<impl Stream<Item = Result<Vec<u8>, AppError>> + Send + 'static, AppError> {
+        let ExportTurnsPayload {
+            game_type_id,
+            agent_id,
+            from,
+            to,
+            own_only,
+            format,
+            after,
+            limit,
+        } = payload;
+        let my_agents: Arc<HashSet<Uuid>> = Arc::new(
+            self.repos
+                .agent_repo
+                .get_my_agents(user_id)
+                .await?
+                .into_iter()
+                .map(|agent| agent.agent_id)
+                .collect(),
+        );
+        let state = ExportState {
+            filter: TurnFilterDTO {
+                game_type_id,
+                agent_id,
+                from,
+                to,
+                owner_id: own_only.then_some(user_id),
+            },
+            cursor: after,
+            remaining: limit.unwrap_or(i64::MAX).max(0),
+            encoder: match format {
+                ExportFormat::Jsonl => None,
+                ExportFormat::Gzip => Some(GzEncoder::new(Vec::new(), Compression::default())),
+            },
+            done: false,
+        };
+        let turn_repo = self.repos.turn_repo.clone();
+        Ok(stream::try_unfold(state, move |state| {
+            export_page(turn_repo.clone(), my_agents.clone(), state)
+        }))
+    }
+
     pub async fn get_participants(
         &self,
         _user_id: Uuid,
@@ -224,3 +342,74 @@ impl MatchService {
         Ok(game_types)
     }
 }
+
+/// 读取并编码下一页, 最后一页时结束 gzip 流
+async fn export_page(
+    turn_repo: Arc<TurnRepo>,
+    my_agents: Arc<HashSet<Uuid>>,
+    mut state: ExportState,
+) -> Result<Option<(Vec<u8>, ExportState)>, AppError> {
+    if state.done {
+        return Ok(None);
+    }
+    let size = EXPORT_PAGE_SIZE.min(state.remaining);
+    let turns = if size > 0 {
+        turn_repo
+            .export_turns(&state.filter, state.cursor.as_ref(), size)
+            .await?
+    } else {
+        Vec::new()
+    };
+    state.done = (turns.len() as i64) < size || size == 0;
+    state.remaining -= turns.len() as i64;
+    if let Some(last) = turns.last() {
+        state.cursor = Some(ExportCursor {
+            end_time: last.end_time,
+            turn_id: last.turn_id,
+        });
+    }
+
+    let mut chunk = Vec::new();
+    for mut turn in turns {
+        hide_foreign_logs(&mut turn.log, &my_agents);
+        serde_json::to_writer(&mut chunk, &turn)?;
+        chunk.push(b'\n');
+    }
+    let io_error = |e: std::io::Error| AppError::Internal(e.to_string());
+    let chunk = match state.encoder.as_mut() {
+        None => chunk,
+        Some(encoder) => {
+            encoder.write_all(&chunk).map_err(io_error)?;
+            if state.done {
+                let encoder = state.encoder.take().unwrap();
+                encoder.finish().map_err(io_error)?
+            } else {
+                std::mem::take(encoder.get_mut())
+            }
+        }
+    };
+    Ok(Some((chunk, state)))
+}
+
+/// 随机的比赛种子, 保持非负以便存入 BIGINT
+pub fn random_seed() -> i64 {
+    (rand::random::<u64>() >> 1) as i64
+}
+
+/// Agent 日志只对其所有者可见, 其他人看到的回合日志中去掉这些条目
+///
+/// 直接在 JSON 上过滤, 无法识别的格式可能含有他人的日志, 整体隐藏.
+fn hide_foreign_logs(log: &mut Value, my_agents: &HashSet<Uuid>) {
+    let Some(entries) = log.as_array_mut() else {
+        *log = json!([]);
+        return;
+    };
+    entries.retain(|entry| match entry.get("Log") {
+        Some(entry) => entry
+            .get("agent_id")
+            .and_then(Value::as_str)
+            .and_then(|agent_id| Uuid::parse_str(agent_id).ok())
+            .is_some_and(|agent_id| my_agents.contains(&agent_id)),
+        None => true,
+    });
+}
@@ -0,0 +1,55 @@
+//! 采样运行时状态并输出所有指标, 指标本身定义在 `tackle_box::metrics`
+
+use std::sync::Arc;
+
+use sqlx::PgPool;
+use tackle_box::metrics::{
+    ACTION_LATENCY, ACTIVE_MATCHES, CORE_QUEUE_DEPTH, DB_POOL_CONNECTIONS, DB_POOL_MAX,
+    GRPC_STREAMS, HTTP_DURATION, HTTP_REQUESTS, PERSISTENCE_QUEUE_DEPTH, SETTLEMENT_DURATION,
+    SPONSOR_RTT,
+};
+use tokio::sync::mpsc::Sender;
+
+use crate::core::{core::CoreMessage, persistence::Persistence};
+
+pub struct MetricsService {
+    pool: Arc<PgPool>,
+    core_tx: Sender<CoreMessage>,
+    persistence: Persistence,
+}
+
+impl MetricsService {
+    pub fn new(pool: Arc<PgPool>, core_tx: Sender<CoreMessage>, persistence: Persistence) -> Self {
+        Self {
+            pool,
+            core_tx,
+            persistence,
+        }
+    }
+
+    /// 采样连接池, Core 队列和持久化队列后输出所有指标
+    pub fn render(&self) -> String {
+        let depth = self.core_tx.max_capacity() - self.core_tx.capacity();
+        CORE_QUEUE_DEPTH.set(&[], depth as i64);
+        PERSISTENCE_QUEUE_DEPTH.set(&[], self.persistence.depth() as i64);
+        let size = self.pool.size() as i64;
+        let idle = self.pool.num_idle() as i64;
+        DB_POOL_CONNECTIONS.set(&["idle"], idle);
+        DB_POOL_CONNECTIONS.set(&["in_use"], size - idle);
+        DB_POOL_MAX.set(&[], self.pool.options().get_max_connections() as i64);
+
+        let mut out = String::new();
+        HTTP_REQUESTS.render(&mut out);
+        HTTP_DURATION.render(&mut out);
+        GRPC_STREAMS.render(&mut out);
+        ACTIVE_MATCHES.render(&mut out);
+        ACTION_LATENCY.render(&mut out);
+        SPONSOR_RTT.render(&mut out);
+        SETTLEMENT_DURATION.render(&mut out);
+        CORE_QUEUE_DEPTH.render(&mut out);
+        PERSISTENCE_QUEUE_DEPTH.render(&mut out);
+        DB_POOL_CONNECTIONS.render(&mut out);
+        DB_POOL_MAX.render(&mut out);
+        out
+    }
+}
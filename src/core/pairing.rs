@@ -0,0 +1,537 @@
+//! 锦标赛的配对与排名, 只根据已结束的比赛计算, 不访问数据库
+
+use std::collections::{HashMap, HashSet};
+
+use tackle_box::contracts::payloads::{
+    MatchStatus, TournamentEntrantResponse, TournamentFormat, TournamentStanding,
+};
+use uuid::Uuid;
+
+use crate::repo::tournaments::{TournamentByeDTO, TournamentMatchDTO, TournamentScoreDTO};
+
+/// 瑞士轮回溯配对的步数上限, 超出后按排名顺序配对, 允许重复相遇
+const SWISS_SEARCH_LIMIT: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pairing {
+    Match(Uuid, Uuid),
+    Bye(Uuid),
+}
+
+/// 淘汰赛中出局所需的负场数
+fn max_losses(format: TournamentFormat) -> Option<i32> {
+    match format {
+        TournamentFormat::SingleElimination => Some(1),
+        TournamentFormat::DoubleElimination => Some(2),
+        TournamentFormat::RoundRobin | TournamentFormat::Swiss => None,
+    }
+}
+
+pub fn is_finished(status: &MatchStatus) -> bool {
+    matches!(status, MatchStatus::Completed | MatchStatus::Cancelled)
+}
+
+/// 开始时确定的总轮数, 淘汰赛的轮数取决于结果, 返回 None
+pub fn total_rounds(format: TournamentFormat, entrants: usize, rounds: Option<i32>) -> Option<i32> {
+    let round_robin = if entrants.is_multiple_of(2) {
+        entrants as i32 - 1
+    } else {
+        entrants as i32
+    };
+    match format {
+        TournamentFormat::RoundRobin => Some(round_robin),
+        TournamentFormat::Swiss => {
+            let default = (entrants as f64).log2().ceil() as i32;
+            // 超过循环赛的轮数后必然重复相遇
+            Some(rounds.unwrap_or(default).clamp(1, round_robin))
+        }
+        TournamentFormat::SingleElimination | TournamentFormat::DoubleElimination => None,
+    }
+}
+
+/// 淘汰赛必须分出胜负, 没有胜者 (平局或取消) 时种子靠前的晋级
+fn winner_of(
+    format: TournamentFormat,
+    one_match: &TournamentMatchDTO,
+    seeding: &HashMap<Uuid, i32>,
+) -> Option<Uuid> {
+    match (one_match.winner_id, max_losses(format)) {
+        (Some(winner_id), _) => Some(winner_id),
+        (None, Some(_)) => one_match
+            .agent_ids
+            .iter()
+            .min_by_key(|id| seeding.get(*id))
+            .copied(),
+        (None, None) => None,
+    }
+}
+
+#[derive(Default)]
+struct Record {
+    played: i32,
+    won: i32,
+    drawn: i32,
+    lost: i32,
+    byes: i32,
+    /// (对手, 本方得分: 胜 1 平 0.5 负 0)
+    opponents: Vec<(Uuid, f64)>,
+    last_round: i32,
+}
+
+impl Record {
+    fn points(&self) -> f64 {
+        self.won as f64 + self.drawn as f64 * 0.5 + self.byes as f64
+    }
+}
+
+/// 计算排名, 返回按名次排序的结果
+pub fn standings(
+    format: TournamentFormat,
+    entrants: &[TournamentEntrantResponse],
+    matches: &[TournamentMatchDTO],
+    byes: &[TournamentByeDTO],
+    scores: &[TournamentScoreDTO],
+) -> Vec<TournamentStanding> {
+    let seeding: HashMap<Uuid, i32> = entrants.iter().map(|e| (e.agent_id, e.seeding)).collect();
+    let mut records: HashMap<Uuid, Record> = entrants
+        .iter()
+        .map(|e| (e.agent_id, Record::default()))
+        .collect();
+
+    for one_match in matches.iter().filter(|m| is_finished(&m.status)) {
+        let winner_id = winner_of(format, one_match, &seeding);
+        for &agent_id in &one_match.agent_ids {
+            let Some(record) = records.get_mut(&agent_id) else {
+                continue;
+            };
+            let result = match winner_id {
+                Some(winner_id) if winner_id == agent_id => {
+                    record.won += 1;
+                    1.0
+                }
+                Some(_) => {
+                    record.lost += 1;
+                    0.0
+                }
+                None => {
+                    record.drawn += 1;
+                    0.5
+                }
+            };
+            record.played += 1;
+            record.last_round = record.last_round.max(one_match.round);
+            for &opponent in one_match.agent_ids.iter().filter(|&&id| id != agent_id) {
+                record.opponents.push((opponent, result));
+            }
+        }
+    }
+    for bye in byes {
+        if let Some(record) = records.get_mut(&bye.agent_id) {
+            record.byes += 1;
+            record.last_round = record.last_round.max(bye.round);
+        }
+    }
+
+    let points: HashMap<Uuid, f64> = records.iter().map(|(id, r)| (*id, r.points())).collect();
+    let scores: HashMap<Uuid, f64> = scores.iter().map(|s| (s.agent_id, s.score)).collect();
+    let max_losses = max_losses(format);
+    let mut rows: Vec<(TournamentStanding, i32)> = entrants
+        .iter()
+        .map(|entrant| {
+            let record = &records[&entrant.agent_id];
+            let opponent_points = |(id, _): &&(Uuid, f64)| points.get(id).copied().unwrap_or(0.0);
+            let standing = TournamentStanding {
+                rank: 0,
+                agent_id: entrant.agent_id,
+                agent_name: entrant.agent_name.clone(),
+                seeding: entrant.seeding,
+                played: record.played,
+                won: record.won,
+                drawn: record.drawn,
+                lost: record.lost,
+                byes: record.byes,
+                points: record.points(),
+                buchholz: record
+                    .opponents
+                    .iter()
+                    .fold(0.0, |acc, o| acc + opponent_points(&o)),
+                sonneborn_berger: record
+                    .opponents
+                    .iter()
+                    .fold(0.0, |acc, o| acc + o.1 * opponent_points(&o)),
+                score: scores.get(&entrant.agent_id).copied().unwrap_or(0.0),
+                eliminated: max_losses.is_some_and(|max| record.lost >= max),
+            };
+            (standing, record.last_round)
+        })
+        .collect();
+
+    rows.sort_by(|(a, a_round), (b, b_round)| {
+        let order = match format {
+            TournamentFormat::RoundRobin => b
+                .points
+                .total_cmp(&a.points)
+                .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger)),
+            TournamentFormat::Swiss => b
+                .points
+                .total_cmp(&a.points)
+                .then(b.buchholz.total_cmp(&a.buchholz))
+                .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger)),
+            // 出局越晚名次越靠前
+            TournamentFormat::SingleElimination | TournamentFormat::DoubleElimination => a
+                .eliminated
+                .cmp(&b.eliminated)
+                .then(b_round.cmp(a_round))
+                .then(a.lost.cmp(&b.lost))
+                .then(b.points.total_cmp(&a.points)),
+        };
+        order
+            .then(b.score.total_cmp(&a.score))
+            .then(a.seeding.cmp(&b.seeding))
+    });
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, (mut standing, _))| {
+            standing.rank = i as i32 + 1;
+            standing
+        })
+        .collect()
+}
+
+/// 第 `round` 轮 (从 1 开始) 的配对, 返回 None 表示锦标赛已结束
+pub fn next_round(
+    format: TournamentFormat,
+    round: i32,
+    total_rounds: Option<i32>,
+    standings: &[TournamentStanding],
+    matches: &[TournamentMatchDTO],
+    byes: &[TournamentByeDTO],
+) -> Option<Vec<Pairing>> {
+    if total_rounds.is_some_and(|total| round > total) {
+        return None;
+    }
+    match format {
+        TournamentFormat::RoundRobin => {
+            let mut by_seeding: Vec<&TournamentStanding> = standings.iter().collect();
+            by_seeding.sort_by_key(|s| s.seeding);
+            let ids: Vec<Uuid> = by_seeding.iter().map(|s| s.agent_id).collect();
+            Some(round_robin(&ids, round))
+        }
+        TournamentFormat::Swiss => {
+            let played: HashSet<(Uuid, Uuid)> = matches
+                .iter()
+                .filter(|m| m.agent_ids.len() == 2)
+                .map(|m| pair_key(m.agent_ids[0], m.agent_ids[1]))
+                .collect();
+            let had_bye: HashSet<Uuid> = byes.iter().map(|b| b.agent_id).collect();
+            let ranked: Vec<Uuid> = standings.iter().map(|s| s.agent_id).collect();
+            Some(swiss(&ranked, &played, &had_bye))
+        }
+        TournamentFormat::SingleElimination | TournamentFormat::DoubleElimination => {
+            let mut alive: Vec<&TournamentStanding> =
+                standings.iter().filter(|s| !s.eliminated).collect();
+            if alive.len() < 2 {
+                return None;
+            }
+            alive.sort_by_key(|s| (s.lost, s.seeding));
+            Some(elimination(&alive))
+        }
+    }
+}
+
+/// 圆桌法: 第一个位置固定, 其余位置每轮旋转一格, 人数为奇数时补一个轮空位
+fn round_robin(ids: &[Uuid], round: i32) -> Vec<Pairing> {
+    let mut slots: Vec<Option<Uuid>> = ids.iter().copied().map(Some).collect();
+    if slots.len() % 2 == 1 {
+        slots.push(None);
+    }
+    let n = slots.len();
+    slots[1..].rotate_right((round as usize - 1) % (n - 1));
+    (0..n / 2)
+        .filter_map(|i| match (slots[i], slots[n - 1 - i]) {
+            (Some(a), Some(b)) => Some(Pairing::Match(a, b)),
+            (Some(a), None) | (None, Some(a)) => Some(Pairing::Bye(a)),
+            (None, None) => None,
+        })
+        .collect()
+}
+
+fn pair_key(a: Uuid, b: Uuid) -> (Uuid, Uuid) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// 按排名配对, 尽量避免重复相遇; 人数为奇数时排名最低且未轮空过的参赛者轮空
+fn swiss(ranked: &[Uuid], played: &HashSet<(Uuid, Uuid)>, had_bye: &HashSet<Uuid>) -> Vec<Pairing> {
+    let mut pool = ranked.to_vec();
+    let mut pairings = Vec::new();
+    if pool.len() % 2 == 1 {
+        let i = pool
+            .iter()
+            .rposition(|id| !had_bye.contains(id))
+            .unwrap_or(pool.len() - 1);
+        pairings.push(Pairing::Bye(pool.remove(i)));
+    }
+    let mut steps = 0;
+    let pairs = pair_without_rematch(&pool, played, &mut steps)
+        .unwrap_or_else(|| pool.chunks(2).map(|c| (c[0], c[1])).collect());
+    pairings.extend(pairs.into_iter().map(|(a, b)| Pairing::Match(a, b)));
+    pairings
+}
+
+/// 排名最高的参赛者依次尝试排名最近且未相遇过的对手, 失败时回溯
+fn pair_without_rematch(
+    pool: &[Uuid],
+    played: &HashSet<(Uuid, Uuid)>,
+    steps: &mut usize,
+) -> Option<Vec<(Uuid, Uuid)>> {
+    let Some((&first, rest)) = pool.split_first() else {
+        return Some(Vec::new());
+    };
+    for (i, &other) in rest.iter().enumerate() {
+        *steps += 1;
+        if *steps > SWISS_SEARCH_LIMIT {
+            return None;
+        }
+        if played.contains(&pair_key(first, other)) {
+            continue;
+        }
+        let mut remaining = rest.to_vec();
+        remaining.remove(i);
+        if let Some(mut pairs) = pair_without_rematch(&remaining, played, steps) {
+            pairs.insert(0, (first, other));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// 淘汰赛每轮重新按种子配对, 胜者组与败者组分开进行, 两组各剩一人时进行总决赛
+fn elimination(alive: &[&TournamentStanding]) -> Vec<Pairing> {
+    let (winners, losers): (Vec<&TournamentStanding>, Vec<&TournamentStanding>) =
+        alive.iter().partition(|s| s.lost == 0);
+    let winners: Vec<Uuid> = winners.iter().map(|s| s.agent_id).collect();
+    let losers: Vec<Uuid> = losers.iter().map(|s| s.agent_id).collect();
+    if winners.len() == 1 && losers.len() == 1 {
+        return vec![Pairing::Match(winners[0], losers[0])];
+    }
+    let mut pairings = bracket(&winners, winners.len().next_power_of_two() - winners.len());
+    pairings.extend(bracket(&losers, losers.len() % 2));
+    pairings
+}
+
+/// 种子靠前的 `byes` 人轮空, 其余首尾配对
+fn bracket(ids: &[Uuid], byes: usize) -> Vec<Pairing> {
+    let (bye, rest) = ids.split_at(byes.min(ids.len()));
+    let mut pairings: Vec<Pairing> = bye.iter().map(|&id| Pairing::Bye(id)).collect();
+    let n = rest.len();
+    pairings.extend((0..n / 2).map(|i| Pairing::Match(rest[i], rest[n - 1 - i])));
+    if n % 2 == 1 {
+        pairings.push(Pairing::Bye(rest[n / 2]));
+    }
+    pairings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    /// 种子号即 id 号
+    fn entrants(n: u128) -> Vec<TournamentEntrantResponse> {
+        (1..=n)
+            .map(|i| TournamentEntrantResponse {
+                agent_id: id(i),
+                agent_name: format!("agent-{}", i),
+                owner_name: "owner".to_string(),
+                seeding: i as i32,
+            })
+            .collect()
+    }
+
+    fn seeded(ids: &[u128]) -> Vec<Uuid> {
+        ids.iter().map(|&i| id(i)).collect()
+    }
+
+    /// 按 `winner` 决定每场胜者, 一直进行到锦标赛结束, 返回比赛, 轮空和最终排名
+    fn play(
+        format: TournamentFormat,
+        n: u128,
+        winner: impl Fn(Uuid, Uuid) -> Uuid,
+    ) -> (
+        Vec<TournamentMatchDTO>,
+        Vec<TournamentByeDTO>,
+        Vec<TournamentStanding>,
+    ) {
+        let entrants = entrants(n);
+        let total = total_rounds(format, entrants.len(), None);
+        let mut matches = Vec::new();
+        let mut byes = Vec::new();
+        for round in 1.. {
+            assert!(round < 50, "tournament did not finish");
+            let table = standings(format, &entrants, &matches, &byes, &[]);
+            let Some(pairings) = next_round(format, round, total, &table, &matches, &byes) else {
+                return (matches, byes, table);
+            };
+            for pairing in pairings {
+                match pairing {
+                    Pairing::Match(a, b) => matches.push(TournamentMatchDTO {
+                        match_id: Uuid::new_v4(),
+                        match_name: String::new(),
+                        round,
+                        status: MatchStatus::Completed,
+                        winner_id: Some(winner(a, b)),
+                        agent_ids: vec![a.min(b), a.max(b)],
+                        agent_names: Vec::new(),
+                    }),
+                    Pairing::Bye(agent_id) => byes.push(TournamentByeDTO { round, agent_id }),
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    fn higher_seed(a: Uuid, b: Uuid) -> Uuid {
+        a.min(b)
+    }
+
+    #[test]
+    fn round_robin_odd_entrants_meet_once_with_one_bye_each() {
+        let (matches, byes, _) = play(TournamentFormat::RoundRobin, 5, higher_seed);
+        assert_eq!(matches.len(), 10);
+        let pairs: HashSet<(Uuid, Uuid)> = matches
+            .iter()
+            .map(|m| pair_key(m.agent_ids[0], m.agent_ids[1]))
+            .collect();
+        assert_eq!(pairs.len(), 10);
+        let mut bye_ids: Vec<Uuid> = byes.iter().map(|b| b.agent_id).collect();
+        bye_ids.sort();
+        assert_eq!(bye_ids, seeded(&[1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn round_robin_even_entrants_have_no_byes() {
+        let (matches, byes, table) = play(TournamentFormat::RoundRobin, 4, higher_seed);
+        assert_eq!(matches.len(), 6);
+        assert!(byes.is_empty());
+        assert_eq!(table[0].agent_id, id(1));
+        assert_eq!(table[0].won, 3);
+    }
+
+    #[test]
+    fn swiss_bye_goes_to_lowest_ranked_without_a_previous_bye() {
+        let ranked = seeded(&[1, 2, 3, 4, 5]);
+        let pairings = swiss(&ranked, &HashSet::new(), &HashSet::new());
+        assert_eq!(pairings[0], Pairing::Bye(id(5)));
+
+        let had_bye = HashSet::from([id(5)]);
+        let pairings = swiss(&ranked, &HashSet::new(), &had_bye);
+        assert_eq!(pairings[0], Pairing::Bye(id(4)));
+        assert_eq!(pairings.len(), 3);
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let ranked = seeded(&[1, 2, 3, 4]);
+        let played = HashSet::from([pair_key(id(1), id(2))]);
+        let pairings = swiss(&ranked, &played, &HashSet::new());
+        assert_eq!(
+            pairings,
+            vec![Pairing::Match(id(1), id(3)), Pairing::Match(id(2), id(4))]
+        );
+    }
+
+    #[test]
+    fn swiss_falls_back_to_rank_order_when_rematch_is_unavoidable() {
+        let ranked = seeded(&[1, 2, 3, 4]);
+        let played: HashSet<(Uuid, Uuid)> = [(1, 2), (1, 3), (1, 4)]
+            .into_iter()
+            .map(|(a, b)| pair_key(id(a), id(b)))
+            .collect();
+        let pairings = swiss(&ranked, &played, &HashSet::new());
+        assert_eq!(
+            pairings,
+            vec![Pairing::Match(id(1), id(2)), Pairing::Match(id(3), id(4))]
+        );
+    }
+
+    #[test]
+    fn swiss_rounds_are_capped_by_round_robin() {
+        assert_eq!(total_rounds(TournamentFormat::Swiss, 5, None), Some(3));
+        assert_eq!(total_rounds(TournamentFormat::Swiss, 4, Some(10)), Some(3));
+    }
+
+    #[test]
+    fn elimination_gives_byes_to_top_seeds() {
+        let table = standings(
+            TournamentFormat::SingleElimination,
+            &entrants(5),
+            &[],
+            &[],
+            &[],
+        );
+        let pairings = next_round(
+            TournamentFormat::SingleElimination,
+            1,
+            None,
+            &table,
+            &[],
+            &[],
+        );
+        assert_eq!(
+            pairings,
+            Some(vec![
+                Pairing::Bye(id(1)),
+                Pairing::Bye(id(2)),
+                Pairing::Bye(id(3)),
+                Pairing::Match(id(4), id(5)),
+            ])
+        );
+    }
+
+    #[test]
+    fn single_elimination_ends_with_one_unbeaten() {
+        let (matches, _, table) = play(TournamentFormat::SingleElimination, 5, higher_seed);
+        assert_eq!(matches.len(), 4);
+        assert_eq!(table[0].agent_id, id(1));
+        assert!(table.iter().skip(1).all(|s| s.eliminated));
+    }
+
+    #[test]
+    fn double_elimination_grand_final_between_bracket_winners() {
+        let (matches, _, table) = play(TournamentFormat::DoubleElimination, 4, higher_seed);
+        let last = matches.last().unwrap();
+        assert_eq!(last.agent_ids, seeded(&[1, 2]));
+        assert_eq!(table[0].agent_id, id(1));
+        assert_eq!(table[0].lost, 0);
+        assert_eq!(table[1].agent_id, id(2));
+        assert_eq!(table[1].lost, 2);
+    }
+
+    #[test]
+    fn double_elimination_grand_final_is_replayed_when_unbeaten_loses() {
+        // 2 号在胜者组决赛输给 1 号, 总决赛及加赛都战胜 1 号
+        let met = std::cell::Cell::new(false);
+        let winner = |a: Uuid, b: Uuid| {
+            if pair_key(a, b) == (id(1), id(2)) && met.replace(true) {
+                id(2)
+            } else {
+                a.min(b)
+            }
+        };
+        let (matches, _, table) = play(TournamentFormat::DoubleElimination, 4, winner);
+        let finals: Vec<&TournamentMatchDTO> = matches
+            .iter()
+            .filter(|m| m.agent_ids == seeded(&[1, 2]))
+            .collect();
+        assert_eq!(finals.len(), 3);
+        assert_eq!(table[0].agent_id, id(2));
+        assert!(table[1].eliminated);
+    }
+}
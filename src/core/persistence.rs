@@ -0,0 +1,838 @@
+//! 写后持久化: 调用方把写操作放入有界队列后即返回, 由单个 worker 按批写入数据库
+//!
+//! 队列满时调用方等待 worker 消化, 形成背压; 临时性的数据库错误按指数退避重试.
+
+use serde_json::json;
+use std::{collections::HashMap, future::Future, iter::zip, sync::Arc, time::Duration};
+use tackle_box::{
+    contracts::payloads::{AgentStatus, MatchStatus},
+    metrics::SETTLEMENT_DURATION,
+};
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver, Sender, UnboundedSender},
+        oneshot,
+    },
+    time::{self, Instant},
+};
+use tracing::{debug, error, info_span, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    api::error::AppError,
+    core::{
+        core::{GameSettlement, TurnLog},
+        settlement::{settle, Outcome},
+    },
+    repo::{
+        agents::AgentRepo,
+        error::RepoError,
+        matches::{MatchRepo, ParticipantResultDTO},
+        stats::{StatsRepo, UpdateStatsDTO},
+        turns::{NewTurnDTO, TurnRepo},
+    },
+};
+
+/// 队列容量, 满时写入方等待
+const QUEUE_CAPACITY: usize = 1024;
+/// 每批最多取出的写操作数
+const BATCH_SIZE: usize = 256;
+/// 临时性错误的最大重试次数, 首次重试前等待 RETRY_BACKOFF, 之后每次加倍
+const MAX_RETRIES: u32 = 5;
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+pub enum WriteOp {
+    AgentStatus {
+        agent_id: Uuid,
+        status: AgentStatus,
+    },
+    /// 比赛开始: 标记为进行中并记下参赛版本, 先于该比赛的结算或取消写入
+    Start { match_id: Uuid },
+    /// 保存对局并结算比赛
+    Settle(GameSettlement),
+    /// 取消比赛, 已完成的对局仍然保存以便回放
+    Cancel {
+        match_id: Uuid,
+        agent_ids: Vec<Uuid>,
+        logs: Vec<TurnLog>,
+    },
+    Stats(UpdateStatsDTO),
+    /// 之前入队的写操作全部完成后回复
+    Flush(oneshot::Sender<()>),
+}
+
+/// 写操作队列的发送端, 可以克隆给多个服务
+#[derive(Clone)]
+pub struct Persistence {
+    tx: Sender<WriteOp>,
+}
+
+impl Persistence {
+    pub fn new(
+        match_repo: Arc<MatchRepo>,
+        agent_repo: Arc<AgentRepo>,
+        turn_repo: Arc<TurnRepo>,
+        stats_repo: Arc<StatsRepo>,
+        finished: UnboundedSender<Uuid>,
+    ) -> (Self, PersistenceWorker) {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let worker = PersistenceWorker {
+            rx,
+            repos: Repos {
+                match_repo,
+                agent_repo,
+                turn_repo,
+                stats_repo,
+            },
+            finished,
+        };
+        (Self { tx }, worker)
+    }
+
+    pub async fn set_agent_status(
+        &self,
+        agent_id: Uuid,
+        status: AgentStatus,
+    ) -> Result<(), AppError> {
+        self.send(WriteOp::AgentStatus { agent_id, status }).await
+    }
+
+    pub async fn start(&self, match_id: Uuid) -> Result<(), AppError> {
+        self.send(WriteOp::Start { match_id }).await
+    }
+
+    pub async fn settle(&self, settlement: GameSettlement) -> Result<(), AppError> {
+        self.send(WriteOp::Settle(settlement)).await
+    }
+
+    pub async fn cancel(
+        &self,
+        match_id: Uuid,
+        agent_ids: Vec<Uuid>,
+        logs: Vec<TurnLog>,
+    ) -> Result<(), AppError> {
+        self.send(WriteOp::Cancel {
+            match_id,
+            agent_ids,
+            logs,
+        })
+        .await
+    }
+
+    pub async fn update_stats(&self, data: UpdateStatsDTO) -> Result<(), AppError> {
+        self.send(WriteOp::Stats(data)).await
+    }
+
+    /// 等待已入队的写操作全部落库, 停机时调用
+    pub async fn flush(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(WriteOp::Flush(tx)).await.is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    /// 队列中等待写入的操作数
+    pub fn depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    async fn send(&self, op: WriteOp) -> Result<(), AppError> {
+        self.tx
+            .send(op)
+            .await
+            .map_err(|_| AppError::Internal("persistence worker stopped".to_string()))
+    }
+}
+
+struct Repos {
+    match_repo: Arc<MatchRepo>,
+    agent_repo: Arc<AgentRepo>,
+    turn_repo: Arc<TurnRepo>,
+    stats_repo: Arc<StatsRepo>,
+}
+
+pub struct PersistenceWorker {
+    rx: Receiver<WriteOp>,
+    repos: Repos,
+    /// 通知锦标赛比赛已结束, 比赛结果成功落库后才发送
+    finished: UnboundedSender<Uuid>,
+}
+
+impl PersistenceWorker {
+    /// 所有 Persistence 释放后写完剩余操作再退出
+    pub async fn run(mut self) {
+        let mut ops = Vec::with_capacity(BATCH_SIZE);
+        while self.rx.recv_many(&mut ops, BATCH_SIZE).await > 0 {
+            self.write_batch(ops.drain(..)).await;
+        }
+    }
+
+    async fn write_batch(&self, ops: impl Iterator<Item = WriteOp>) {
+        let steps = plan_batch(ops);
+        debug!(steps = steps.len(), "writing batch");
+        for step in steps {
+            match step {
+                Step::Statuses(status, agent_ids) => self.write_statuses(status, agent_ids).await,
+                Step::Start(match_id) => {
+                    if let Err(e) = retry("start match", || self.start(match_id)).await {
+                        error!(%match_id, error = ?e, "failed to start match");
+                    }
+                }
+                Step::Settle(settlement) => {
+                    let match_id = settlement.match_id;
+                    let finished = self
+                        .write_settlement(settlement)
+                        .instrument(info_span!("settle", %match_id))
+                        .await;
+                    self.notify_finished(match_id, finished);
+                }
+                Step::Cancel {
+                    match_id,
+                    agent_ids,
+                    logs,
+                } => {
+                    let turns = new_turns(match_id, &agent_ids, &logs);
+                    let result = retry("cancel match", || self.cancel(match_id, &turns)).await;
+                    if let Err(e) = &result {
+                        error!(%match_id, error = ?e, "failed to cancel match");
+                    }
+                    self.notify_finished(match_id, result.is_ok());
+                }
+                Step::Stats(data) => {
+                    if let Err(e) = retry("update stats", || self.update_stats(&data)).await {
+                        error!(error = ?e, "failed to update stats");
+                    }
+                }
+                Step::Flush(tx) => {
+                    let _ = tx.send(());
+                }
+            }
+        }
+    }
+
+    /// 比赛结果落库后才通知锦标赛, 写入失败的比赛不会被当作已结束
+    fn notify_finished(&self, match_id: Uuid, finished: bool) {
+        if finished {
+            let _ = self.finished.send(match_id);
+        }
+    }
+
+    /// 同一状态的 Agent 一条语句
+    async fn write_statuses(&self, status: AgentStatus, agent_ids: Vec<Uuid>) {
+        let agent_repo = &self.repos.agent_repo;
+        let result = retry("update agent status", || async {
+            Ok(agent_repo
+                .update_agent_statuses(&agent_ids, status.clone())
+                .await?)
+        })
+        .await;
+        if let Err(e) = result {
+            error!(?agent_ids, ?status, error = ?e, "failed to update agent status");
+        }
+    }
+
+    /// 返回比赛是否已结算或取消
+    async fn write_settlement(&self, settlement: GameSettlement) -> bool {
+        let start = Instant::now();
+        let GameSettlement {
+            match_id,
+            agent_ids,
+            logs,
+            forfeit,
+        } = settlement;
+        let turns = new_turns(match_id, &agent_ids, &logs);
+        let payoff_table: Vec<Vec<f32>> = logs.iter().map(|log| log.payoffs.clone()).collect();
+        let mut seat_history: HashMap<Uuid, Vec<i32>> = HashMap::new();
+        for log in &logs {
+            for (seat, agent_id) in log.seats.iter().enumerate() {
+                seat_history.entry(*agent_id).or_default().push(seat as i32);
+            }
+        }
+        let written = settle_or_cancel(
+            || {
+                self.settle(
+                    match_id,
+                    &agent_ids,
+                    &turns,
+                    &payoff_table,
+                    &seat_history,
+                    forfeit,
+                )
+            },
+            || self.cancel(match_id, &turns),
+        )
+        .await;
+        if written == Some(Written::Settled) {
+            SETTLEMENT_DURATION.observe(&[], start.elapsed());
+        }
+        written.is_some()
+    }
+
+    async fn start(&self, match_id: Uuid) -> Result<(), AppError> {
+        let match_repo = &self.repos.match_repo;
+        match_repo
+            .update_match_status(match_id, MatchStatus::Running)
+            .await?;
+        match_repo.record_versions(match_id).await?;
+        Ok(())
+    }
+
+    async fn settle(
+        &self,
+        match_id: Uuid,
+        agent_ids: &[Uuid],
+        turns: &[NewTurnDTO],
+        payoff_table: &[Vec<f32>],
+        seat_history: &HashMap<Uuid, Vec<i32>>,
+        forfeit: Option<Uuid>,
+    ) -> Result<(), AppError> {
+        let Repos {
+            agent_repo,
+            match_repo,
+            turn_repo,
+            ..
+        } = &self.repos;
+
+        let mut tx = match_repo.get_transaction().await?;
+        let rule = match_repo.get_settlement_rule(&mut tx, match_id).await?;
+        turn_repo.insert_turns(&mut tx, turns).await?;
+        let Outcome {
+            standings,
+            winner_id,
+            is_draw,
+        } = settle(
+            rule.settlement_rule,
+            rule.allow_draws,
+            agent_ids,
+            payoff_table,
+            forfeit,
+        );
+        for standing in standings {
+            match standing.placement {
+                1 if winner_id == Some(standing.agent_id) => {
+                    agent_repo.agent_won(&mut tx, standing.agent_id).await?
+                }
+                1 if is_draw => agent_repo.agent_drawn(&mut tx, standing.agent_id).await?,
+                _ => agent_repo.agent_failed(&mut tx, standing.agent_id).await?,
+            }
+            let result = ParticipantResultDTO {
+                agent_id: standing.agent_id,
+                placement: standing.placement,
+                final_score: standing.total_score,
+                games_won: standing.games_won,
+                seats: seat_history
+                    .get(&standing.agent_id)
+                    .cloned()
+                    .unwrap_or_default(),
+            };
+            match_repo.record_result(&mut tx, match_id, result).await?;
+        }
+
+        agent_repo
+            .record_version_results(&mut tx, match_id, winner_id, is_draw)
+            .await?;
+        match_repo
+            .update_match_final_status(&mut tx, match_id, winner_id, is_draw)
+            .await?;
+        tx.commit().await.map_err(RepoError::from)?;
+        Ok(())
+    }
+
+    async fn cancel(&self, match_id: Uuid, turns: &[NewTurnDTO]) -> Result<(), AppError> {
+        let match_repo = &self.repos.match_repo;
+        let mut tx = match_repo.get_transaction().await?;
+        self.repos.turn_repo.insert_turns(&mut tx, turns).await?;
+        match_repo.cancel_match(&mut tx, match_id).await?;
+        tx.commit().await.map_err(RepoError::from)?;
+        Ok(())
+    }
+
+    async fn update_stats(&self, data: &UpdateStatsDTO) -> Result<(), AppError> {
+        self.repos.stats_repo.update_stats(data).await?;
+        Ok(())
+    }
+}
+
+/// 每局的日志和按参赛者的得分
+fn new_turns(match_id: Uuid, agent_ids: &[Uuid], logs: &[TurnLog]) -> Vec<NewTurnDTO> {
+    logs.iter()
+        .enumerate()
+        .map(|(i_turn, log)| {
+            let score_deltas: HashMap<&Uuid, f32> =
+                HashMap::from_iter(zip(agent_ids, log.payoffs.iter().copied()));
+            NewTurnDTO {
+                match_id,
+                i_turn: i_turn as i32,
+                log: json!(log.logs),
+                score_deltas: json!(score_deltas),
+                start_time: log.start_time,
+                end_time: log.end_time,
+                seed: Some(log.seed),
+                seats: log.seats.clone(),
+            }
+        })
+        .collect()
+}
+
+/// 一批写操作的执行步骤
+enum Step {
+    Statuses(AgentStatus, Vec<Uuid>),
+    Start(Uuid),
+    Settle(GameSettlement),
+    Cancel {
+        match_id: Uuid,
+        agent_ids: Vec<Uuid>,
+        logs: Vec<TurnLog>,
+    },
+    Stats(UpdateStatsDTO),
+    Flush(oneshot::Sender<()>),
+}
+
+/// 排出一批写操作的执行顺序
+///
+/// 同一 Agent 的状态只保留最后一次, 按状态分组后最先写入, 比赛开始或结束时的状态不会晚于比赛本身;
+/// 比赛按入队顺序逐场写入; 排名只保留最新一次; 所有写入完成后才回复 Flush.
+fn plan_batch(ops: impl Iterator<Item = WriteOp>) -> Vec<Step> {
+    let mut statuses: Vec<(AgentStatus, Vec<Uuid>)> = Vec::new();
+    let mut latest: HashMap<Uuid, AgentStatus> = HashMap::new();
+    let mut matches = Vec::new();
+    let mut stats = None;
+    let mut flushes = Vec::new();
+    for op in ops {
+        match op {
+            WriteOp::AgentStatus { agent_id, status } => {
+                latest.insert(agent_id, status);
+            }
+            WriteOp::Start { match_id } => matches.push(Step::Start(match_id)),
+            WriteOp::Settle(settlement) => matches.push(Step::Settle(settlement)),
+            WriteOp::Cancel {
+                match_id,
+                agent_ids,
+                logs,
+            } => matches.push(Step::Cancel {
+                match_id,
+                agent_ids,
+                logs,
+            }),
+            WriteOp::Stats(data) => stats = Some(data),
+            WriteOp::Flush(tx) => flushes.push(Step::Flush(tx)),
+        }
+    }
+    for (agent_id, status) in latest {
+        match statuses.iter_mut().find(|(s, _)| *s == status) {
+            Some((_, agent_ids)) => agent_ids.push(agent_id),
+            None => statuses.push((status, vec![agent_id])),
+        }
+    }
+    let mut steps: Vec<Step> = statuses
+        .into_iter()
+        .map(|(status, agent_ids)| Step::Statuses(status, agent_ids))
+        .collect();
+    steps.extend(matches);
+    steps.extend(stats.map(Step::Stats));
+    steps.extend(flushes);
+    steps
+}
+
+/// 比赛最终以哪种方式落库
+#[derive(Debug, PartialEq)]
+enum Written {
+    Settled,
+    Cancelled,
+}
+
+/// 结算一直失败时改为取消比赛, 以免比赛停留在进行中; 两者都失败时返回 None
+async fn settle_or_cancel<S, SFut, C, CFut>(settle: S, cancel: C) -> Option<Written>
+where
+    S: FnMut() -> SFut,
+    SFut: Future<Output = Result<(), AppError>>,
+    C: FnMut() -> CFut,
+    CFut: Future<Output = Result<(), AppError>>,
+{
+    let Err(e) = retry("settle match", settle).await else {
+        return Some(Written::Settled);
+    };
+    error!(error = ?e, "failed to settle match, cancelling");
+    match retry("cancel match", cancel).await {
+        Ok(()) => Some(Written::Cancelled),
+        Err(e) => {
+            error!(error = ?e, "failed to cancel match");
+            None
+        }
+    }
+}
+
+/// 第 attempt 次重试前的等待时间, 从 RETRY_BACKOFF 开始每次加倍
+fn backoff(attempt: u32) -> Duration {
+    RETRY_BACKOFF * 2u32.pow(attempt.saturating_sub(1))
+}
+
+/// 事务整体重试, 失败的事务已回滚, 重做不会重复写入
+async fn retry<F, Fut>(what: &str, mut op: F) -> Result<(), AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(e) if attempt < MAX_RETRIES && is_transient(&e) => {
+                attempt += 1;
+                warn!(what, attempt, error = ?e, "transient database error, retrying");
+                time::sleep(backoff(attempt)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 连接断开, 连接池耗尽, 序列化冲突和死锁可以重试, 其余错误重试也不会成功
+fn is_transient(e: &AppError) -> bool {
+    let AppError::Database(RepoError::TechnicalError(e)) = e else {
+        return false;
+    };
+    match e {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+        // 08: 连接异常, 40001: 序列化失败, 40P01: 死锁, 57P01: 服务端终止连接
+        sqlx::Error::Database(db) => db.code().is_some_and(|code| {
+            code.starts_with("08") || matches!(code.as_ref(), "40001" | "40P01" | "57P01")
+        }),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use sqlx::{error::ErrorKind, postgres::PgPoolOptions};
+    use std::{borrow::Cow, cell::Cell, fmt};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn status(agent_id: Uuid, status: AgentStatus) -> WriteOp {
+        WriteOp::AgentStatus { agent_id, status }
+    }
+
+    fn settle_op(match_id: Uuid) -> WriteOp {
+        WriteOp::Settle(GameSettlement {
+            match_id,
+            agent_ids: vec![id(1), id(2)],
+            logs: vec![TurnLog {
+                logs: Vec::new(),
+                payoffs: vec![1.0, -1.0],
+                start_time: Utc::now(),
+                end_time: Utc::now(),
+                seed: 0,
+                seats: vec![id(1), id(2)],
+            }],
+            forfeit: None,
+        })
+    }
+
+    fn stats_op(rank: i32) -> WriteOp {
+        WriteOp::Stats(UpdateStatsDTO {
+            agent_ids: vec![id(1)],
+            game_type_ids: vec![id(9)],
+            new_ranks: vec![rank],
+        })
+    }
+
+    /// 步骤的简短描述, 状态组内的 Agent 按 id 排序
+    fn describe(steps: &[Step]) -> Vec<String> {
+        steps
+            .iter()
+            .map(|step| match step {
+                Step::Statuses(status, agent_ids) => {
+                    let mut ids: Vec<u128> = agent_ids.iter().map(|id| id.as_u128()).collect();
+                    ids.sort();
+                    format!("{:?} {:?}", status, ids)
+                }
+                Step::Start(match_id) => format!("start {}", match_id.as_u128()),
+                Step::Settle(s) => format!("settle {}", s.match_id.as_u128()),
+                Step::Cancel { match_id, .. } => format!("cancel {}", match_id.as_u128()),
+                Step::Stats(data) => format!("stats {:?}", data.new_ranks),
+                Step::Flush(_) => "flush".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn statuses_keep_the_last_one_per_agent() {
+        let ops = vec![
+            status(id(1), AgentStatus::Running),
+            status(id(2), AgentStatus::Running),
+            status(id(1), AgentStatus::Idle),
+            status(id(3), AgentStatus::Idle),
+        ];
+        let mut steps = describe(&plan_batch(ops.into_iter()));
+        steps.sort();
+        assert_eq!(steps, vec!["Idle [1, 3]", "Running [2]"]);
+    }
+
+    #[test]
+    fn statuses_are_written_before_matches_in_queue_order() {
+        let ops = vec![
+            WriteOp::Start { match_id: id(11) },
+            status(id(1), AgentStatus::Running),
+            settle_op(id(12)),
+            WriteOp::Cancel {
+                match_id: id(13),
+                agent_ids: Vec::new(),
+                logs: Vec::new(),
+            },
+            status(id(1), AgentStatus::Idle),
+        ];
+        assert_eq!(
+            describe(&plan_batch(ops.into_iter())),
+            vec!["Idle [1]", "start 11", "settle 12", "cancel 13"]
+        );
+    }
+
+    #[test]
+    fn only_the_latest_stats_are_written_and_flushes_come_last() {
+        let (first, _) = oneshot::channel();
+        let (second, _) = oneshot::channel();
+        let ops = vec![
+            WriteOp::Flush(first),
+            stats_op(1),
+            WriteOp::Start { match_id: id(11) },
+            stats_op(2),
+            WriteOp::Flush(second),
+        ];
+        assert_eq!(
+            describe(&plan_batch(ops.into_iter())),
+            vec!["start 11", "stats [2]", "flush", "flush"]
+        );
+    }
+
+    #[derive(Debug)]
+    struct FakeDbError(&'static str);
+
+    impl fmt::Display for FakeDbError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "database error {}", self.0)
+        }
+    }
+
+    impl std::error::Error for FakeDbError {}
+
+    impl sqlx::error::DatabaseError for FakeDbError {
+        fn message(&self) -> &str {
+            self.0
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn db_error(e: sqlx::Error) -> AppError {
+        AppError::Database(RepoError::TechnicalError(e))
+    }
+
+    fn sql_state(code: &'static str) -> AppError {
+        db_error(sqlx::Error::Database(Box::new(FakeDbError(code))))
+    }
+
+    fn transient() -> AppError {
+        db_error(sqlx::Error::PoolTimedOut)
+    }
+
+    fn permanent() -> AppError {
+        db_error(sqlx::Error::RowNotFound)
+    }
+
+    #[test]
+    fn connection_and_conflict_errors_are_transient() {
+        let io = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(is_transient(&db_error(sqlx::Error::Io(io))));
+        assert!(is_transient(&transient()));
+        assert!(is_transient(&db_error(sqlx::Error::WorkerCrashed)));
+        for code in ["08006", "40001", "40P01", "57P01"] {
+            assert!(is_transient(&sql_state(code)), "{}", code);
+        }
+    }
+
+    #[test]
+    fn other_errors_are_not_transient() {
+        assert!(!is_transient(&permanent()));
+        // 23505: 唯一约束冲突
+        assert!(!is_transient(&sql_state("23505")));
+        assert!(!is_transient(&AppError::Internal("stopped".to_string())));
+    }
+
+    #[test]
+    fn backoff_doubles_from_the_base() {
+        let waits: Vec<u128> = (1..=MAX_RETRIES).map(|n| backoff(n).as_millis()).collect();
+        assert_eq!(waits, vec![100, 200, 400, 800, 1600]);
+    }
+
+    /// 前 failures 次返回 error(), 之后成功, 记录调用次数
+    async fn flaky(
+        calls: &Cell<u32>,
+        failures: u32,
+        error: fn() -> AppError,
+    ) -> Result<(), AppError> {
+        calls.set(calls.get() + 1);
+        if calls.get() <= failures {
+            Err(error())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_errors_are_retried_with_backoff() {
+        let calls = Cell::new(0);
+        let start = Instant::now();
+        assert!(retry("test", || flaky(&calls, 2, transient)).await.is_ok());
+        assert_eq!(calls.get(), 3);
+        assert_eq!(start.elapsed(), backoff(1) + backoff(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_give_up_after_the_limit() {
+        let calls = Cell::new(0);
+        assert!(retry("test", || flaky(&calls, u32::MAX, transient))
+            .await
+            .is_err());
+        assert_eq!(calls.get(), MAX_RETRIES + 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanent_errors_are_not_retried() {
+        let calls = Cell::new(0);
+        assert!(retry("test", || flaky(&calls, 1, permanent)).await.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn settled_matches_are_not_cancelled() {
+        let (settles, cancels) = (Cell::new(0), Cell::new(0));
+        let written = settle_or_cancel(
+            || flaky(&settles, 1, transient),
+            || flaky(&cancels, 0, permanent),
+        )
+        .await;
+        assert_eq!(written, Some(Written::Settled));
+        assert_eq!(cancels.get(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_settlement_falls_back_to_cancel() {
+        let (settles, cancels) = (Cell::new(0), Cell::new(0));
+        let written = settle_or_cancel(
+            || flaky(&settles, u32::MAX, permanent),
+            || flaky(&cancels, 0, permanent),
+        )
+        .await;
+        assert_eq!(written, Some(Written::Cancelled));
+        assert_eq!((settles.get(), cancels.get()), (1, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nothing_is_written_when_cancel_fails_too() {
+        let (settles, cancels) = (Cell::new(0), Cell::new(0));
+        let written = settle_or_cancel(
+            || flaky(&settles, u32::MAX, permanent),
+            || flaky(&cancels, u32::MAX, permanent),
+        )
+        .await;
+        assert_eq!(written, None);
+    }
+
+    /// 连接不上数据库的 worker, 所有写入都会失败
+    fn offline_worker() -> (Persistence, PersistenceWorker, UnboundedReceiver<Uuid>) {
+        let pool = Arc::new(
+            PgPoolOptions::new()
+                .acquire_timeout(Duration::from_millis(10))
+                .connect_lazy("postgres://tacklebox@127.0.0.1:1/offline")
+                .expect("lazy pool"),
+        );
+        let (finished, finished_rx) = unbounded_channel();
+        let (persistence, worker) = Persistence::new(
+            Arc::new(MatchRepo { pool: pool.clone() }),
+            Arc::new(AgentRepo { pool: pool.clone() }),
+            Arc::new(TurnRepo { pool: pool.clone() }),
+            Arc::new(StatsRepo { pool }),
+            finished,
+        );
+        (persistence, worker, finished_rx)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_writes_do_not_notify_tournaments() {
+        let (_persistence, worker, mut finished_rx) = offline_worker();
+        let (flushed, flushed_rx) = oneshot::channel();
+        let ops = vec![
+            settle_op(id(12)),
+            WriteOp::Cancel {
+                match_id: id(13),
+                agent_ids: Vec::new(),
+                logs: Vec::new(),
+            },
+            WriteOp::Flush(flushed),
+        ];
+        worker.write_batch(ops.into_iter()).await;
+        assert!(finished_rx.try_recv().is_err());
+        // 写入失败后仍然回复 Flush, 停机不会卡住
+        assert!(flushed_rx.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn flush_waits_for_queued_writes() {
+        let (tx, mut rx) = mpsc::channel(QUEUE_CAPACITY);
+        let persistence = Persistence { tx };
+        let worker = tokio::spawn(async move {
+            let mut written = Vec::new();
+            while let Some(op) = rx.recv().await {
+                match op {
+                    WriteOp::AgentStatus { agent_id, .. } => written.push(agent_id),
+                    WriteOp::Flush(tx) => {
+                        let _ = tx.send(());
+                        return written;
+                    }
+                    _ => {}
+                }
+            }
+            written
+        });
+        persistence
+            .set_agent_status(id(1), AgentStatus::Idle)
+            .await
+            .unwrap();
+        persistence.flush().await;
+        assert_eq!(worker.await.unwrap(), vec![id(1)]);
+    }
+
+    #[tokio::test]
+    async fn flush_returns_when_the_worker_has_stopped() {
+        let (persistence, worker, _) = offline_worker();
+        drop(worker);
+        persistence.flush().await;
+        assert!(persistence
+            .set_agent_status(id(1), AgentStatus::Idle)
+            .await
+            .is_err());
+    }
+}
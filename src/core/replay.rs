@@ -0,0 +1,859 @@
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tackle_box::{
+    connection::{
+        game_control::ControlType, game_init_response::ResultType,
+        process_game_request::RequestType, process_game_response::ResponseType, GameControl,
+        GameEndStatus, GameInitRequest, GameStateUpdate, PlayerAction, ProcessGameRequest,
+    },
+    contracts::payloads::{GameReplay, GameVerification, ReplayStep, TurnLogResponse},
+};
+use tokio::sync::{
+    mpsc::{self, Sender},
+    oneshot,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Request;
+use uuid::Uuid;
+
+use crate::{
+    api::error::AppError,
+    core::core::{CoreMessage, GameStreamType},
+    repo::{
+        agents::AgentRepo, error::RepoError, game_type::GameTypeRepo, matches::MatchRepo,
+        turns::TurnRepo,
+    },
+};
+
+/// 重新模拟一局的最长时间
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Repos {
+    pub agent_repo: Arc<AgentRepo>,
+    pub turn_repo: Arc<TurnRepo>,
+    pub match_repo: Arc<MatchRepo>,
+    pub gametype_repo: Arc<GameTypeRepo>,
+}
+
+struct Senders {
+    pub core_tx: Sender<CoreMessage>,
+}
+
+/// 根据回合日志重建每局的逐步过程
+pub struct ReplayService {
+    repos: Repos,
+    senders: Senders,
+}
+
+/// 旧版本只记录了状态和动作字符串
+#[derive(Deserialize)]
+enum LegacyStreamType {
+    State(String),
+    Action(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredStreamType {
+    Current(GameStreamType),
+    Legacy(LegacyStreamType),
+}
+
+impl ReplayService {
+    pub fn new(
+        agent_repo: Arc<AgentRepo>,
+        turn_repo: Arc<TurnRepo>,
+        match_repo: Arc<MatchRepo>,
+        gametype_repo: Arc<GameTypeRepo>,
+        core_tx: Sender<CoreMessage>,
+    ) -> Self {
+        Self {
+            repos: Repos {
+                agent_repo,
+                turn_repo,
+                match_repo,
+                gametype_repo,
+            },
+            senders: Senders { core_tx },
+        }
+    }
+
+    pub async fn get_replay(
+        &self,
+        user_id: Uuid,
+        match_id: Uuid,
+        i_turn: Option<i32>,
+    ) -> Result<Vec<GameReplay>, AppError> {
+        let turns = match i_turn {
+            Some(i_turn) => vec![self.get_turn(match_id, i_turn).await?],
+            None => self.repos.turn_repo.get_turns(match_id).await?,
+        };
+        let my_agents: HashSet<Uuid> = self
+            .repos
+            .agent_repo
+            .get_my_agents(user_id)
+            .await?
+            .into_iter()
+            .map(|agent| agent.agent_id)
+            .collect();
+        turns
+            .into_iter()
+            .map(|turn| build_replay(turn, &my_agents))
+            .collect()
+    }
+
+    /// 用存储的种子在 sponsor 上重新进行一局, 按记录的动作逐步比对状态和得分
+    ///
+    /// 重新模拟会占用 sponsor, 只允许比赛创建者和参赛 Agent 的所有者发起
+    pub async fn verify_game(
+        &self,
+        user_id: Uuid,
+        match_id: Uuid,
+        i_turn: i32,
+    ) -> Result<GameVerification, AppError> {
+        let one_match = self.repos.match_repo.get_match(match_id).await?;
+        let turn = self.get_turn(match_id, i_turn).await?;
+        let deltas = HashMap::<Uuid, f32>::deserialize(&turn.score_deltas)?;
+        if one_match.creater_id != user_id {
+            let my_agents: Vec<Uuid> = self
+                .repos
+                .agent_repo
+                .get_my_agents(user_id)
+                .await?
+                .into_iter()
+                .map(|agent| agent.agent_id)
+                .collect();
+            ensure_participant_owner(match_id, &my_agents, &deltas)?;
+        }
+        let seed = turn.seed.ok_or_else(|| {
+            AppError::Validation(format!("game {} was played without a seed", i_turn))
+        })?;
+        let game_type = self
+            .repos
+            .gametype_repo
+            .get_game_type(one_match.game_type_id)
+            .await?;
+        let entries = Vec::<GameStreamType>::deserialize(&turn.log)?;
+        let stored_payoffs = StoredPayoffs::new(turn.seats.as_deref(), deltas);
+
+        let (tx, rx) = oneshot::channel();
+        self.senders
+            .core_tx
+            .send(CoreMessage::SponsorLookup {
+                sponsor: game_type.sponsor,
+                tx,
+            })
+            .await?;
+        let mut sponsor = rx
+            .await
+            .map_err(|_| AppError::Internal("core stopped".to_string()))?
+            .ok_or(AppError::Internal("Not Find Sponsor".to_string()))?;
+
+        let (sponsor_tx, sponsor_rx) = mpsc::channel(16);
+        let resp = sponsor
+            .process_game(Request::new(ReceiverStream::new(sponsor_rx)))
+            .await?;
+        sponsor_tx
+            .send(ProcessGameRequest {
+                request_type: Some(RequestType::Init(GameInitRequest {
+                    game_type: game_type.name,
+                    seed: Some(seed as u64),
+                })),
+            })
+            .await?;
+        let mut sponsor_rx = resp.into_inner();
+        // 丢弃一次应答, 因为Python后段依赖至少一次回复来生成流
+        let _ = sponsor_rx.next().await;
+
+        let mut checker = Checker::new(entries, stored_payoffs);
+        let simulated = tokio::time::timeout(VERIFY_TIMEOUT, async {
+            while let Some(resp) = sponsor_rx.next().await {
+                match checker.check(resp?.response_type)? {
+                    Step::Send(action) => {
+                        sponsor_tx
+                            .send(ProcessGameRequest {
+                                request_type: Some(RequestType::Action(PlayerAction { action })),
+                            })
+                            .await?
+                    }
+                    Step::Wait => {}
+                    Step::Done => return Ok::<(), AppError>(()),
+                }
+            }
+            checker.fail("sponsor closed the stream before the game ended".to_string());
+            Ok(())
+        })
+        .await;
+        match simulated {
+            Ok(result) => result?,
+            Err(_) => {
+                checker.fail("sponsor timed out".to_string());
+            }
+        }
+        let _ = sponsor_tx
+            .send(ProcessGameRequest {
+                request_type: Some(RequestType::Control(GameControl {
+                    r#type: ControlType::Pause.into(),
+                    seed: None,
+                })),
+            })
+            .await;
+
+        Ok(GameVerification {
+            match_id,
+            i_turn,
+            seed,
+            verified: checker.mismatch.is_none(),
+            checked_states: checker.checked_states,
+            mismatch: checker.mismatch,
+        })
+    }
+
+    async fn get_turn(&self, match_id: Uuid, i_turn: i32) -> Result<TurnLogResponse, AppError> {
+        self.repos
+            .turn_repo
+            .get_i_turn(match_id, i_turn)
+            .await
+            .map_err(|e| turn_error(i_turn, e))
+    }
+}
+
+/// 非创建者至少要拥有一个参赛 Agent 才能发起重新模拟
+fn ensure_participant_owner(
+    match_id: Uuid,
+    my_agents: &[Uuid],
+    deltas: &HashMap<Uuid, f32>,
+) -> Result<(), AppError> {
+    if my_agents
+        .iter()
+        .any(|agent_id| deltas.contains_key(agent_id))
+    {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "match {} has no agent owned by you",
+            match_id
+        )))
+    }
+}
+
+/// 指定的局不存在时报告为输入错误
+fn turn_error(i_turn: i32, e: RepoError) -> AppError {
+    match e {
+        RepoError::TechnicalError(sqlx::Error::RowNotFound) => {
+            AppError::Validation(format!("game {} not found", i_turn))
+        }
+        e => e.into(),
+    }
+}
+
+/// 比对一条 sponsor 输出后的下一步
+enum Step {
+    /// 把存储的动作发给 sponsor
+    Send(String),
+    /// 等待 sponsor 的下一条输出
+    Wait,
+    Done,
+}
+
+/// 逐条比对 sponsor 的输出和存储的日志
+struct Checker {
+    entries: std::vec::IntoIter<GameStreamType>,
+    stored_payoffs: StoredPayoffs,
+    /// 弃权的对局没有终局, 日志用完即结束
+    forfeited: bool,
+    checked_states: i32,
+    mismatch: Option<String>,
+}
+
+impl Checker {
+    fn new(entries: Vec<GameStreamType>, stored_payoffs: StoredPayoffs) -> Self {
+        let forfeited = entries
+            .iter()
+            .any(|entry| matches!(entry, GameStreamType::Resign { .. }));
+        // 只比对状态和动作
+        let entries: Vec<GameStreamType> = entries
+            .into_iter()
+            .filter(|entry| {
+                matches!(
+                    entry,
+                    GameStreamType::State { .. } | GameStreamType::Action { .. }
+                )
+            })
+            .collect();
+        Self {
+            entries: entries.into_iter(),
+            stored_payoffs,
+            forfeited,
+            checked_states: 0,
+            mismatch: None,
+        }
+    }
+
+    fn fail(&mut self, mismatch: String) -> Step {
+        self.mismatch.get_or_insert(mismatch);
+        Step::Done
+    }
+
+    fn check(&mut self, resp: Option<ResponseType>) -> Result<Step, AppError> {
+        let step = match resp {
+            Some(ResponseType::InitResponse(init)) => {
+                if init.r#type() == ResultType::Failed {
+                    return Err(AppError::Internal("sponsor failed to init".to_string()));
+                }
+                Step::Wait
+            }
+            Some(ResponseType::StateUpdate(GameStateUpdate {
+                state,
+                is_over,
+                i_player,
+            })) => self.check_state(state, is_over, i_player),
+            Some(ResponseType::EndStatus(GameEndStatus { payoffs })) => {
+                if self.entries.next().is_some() {
+                    self.fail("sponsor ended the game before the stored log".to_string())
+                } else if !self.forfeited && !self.stored_payoffs.matches(payoffs) {
+                    self.fail("payoffs differ from the stored result".to_string())
+                } else {
+                    Step::Done
+                }
+            }
+            None => Step::Wait,
+        };
+        Ok(step)
+    }
+
+    fn check_state(&mut self, state: String, is_over: bool, i_player: i32) -> Step {
+        let index = self.checked_states;
+        match self.entries.next() {
+            Some(GameStreamType::State {
+                seat,
+                state: stored,
+                is_over: stored_over,
+                ..
+            }) if seat == i_player && stored == state && stored_over == is_over => {}
+            Some(GameStreamType::State { .. }) => {
+                return self.fail(format!("state {} differs from the stored log", index))
+            }
+            None if self.forfeited => return Step::Done,
+            _ => return self.fail(format!("unexpected state {} from sponsor", index)),
+        }
+        self.checked_states += 1;
+        if is_over {
+            return Step::Wait;
+        }
+        match self.entries.next() {
+            Some(GameStreamType::Action { action, .. }) => Step::Send(action),
+            None if self.forfeited => Step::Done,
+            _ => self.fail(format!("no stored action after state {}", index)),
+        }
+    }
+}
+
+/// 存储的本局得分
+enum StoredPayoffs {
+    BySeat(Vec<f32>),
+    Unordered(Vec<f32>),
+}
+
+impl StoredPayoffs {
+    /// 旧数据没有记录座位, 只能比较排序后的得分
+    fn new(seats: Option<&[Uuid]>, deltas: HashMap<Uuid, f32>) -> Self {
+        match seats {
+            Some(seats) => StoredPayoffs::BySeat(
+                seats
+                    .iter()
+                    .map(|id| deltas.get(id).copied().unwrap_or_default())
+                    .collect(),
+            ),
+            None => StoredPayoffs::Unordered(deltas.into_values().collect()),
+        }
+    }
+
+    fn matches(&self, mut payoffs: Vec<f32>) -> bool {
+        let stored = match self {
+            StoredPayoffs::BySeat(stored) => stored.clone(),
+            StoredPayoffs::Unordered(stored) => {
+                let mut stored = stored.clone();
+                stored.sort_by(f32::total_cmp);
+                payoffs.sort_by(f32::total_cmp);
+                stored
+            }
+        };
+        payoffs.len() == stored.len()
+            && payoffs
+                .iter()
+                .zip(&stored)
+                .all(|(a, b)| (a - b).abs() < 1e-4)
+    }
+}
+
+fn build_replay(turn: TurnLogResponse, my_agents: &HashSet<Uuid>) -> Result<GameReplay, AppError> {
+    let entries = Vec::<StoredStreamType>::deserialize(&turn.log)?;
+    let mut steps: Vec<ReplayStep> = Vec::new();
+    for entry in entries {
+        match entry {
+            StoredStreamType::Current(GameStreamType::State {
+                seat,
+                state,
+                is_over,
+                at,
+            }) => push_state(&mut steps, Some(seat), state, is_over, Some(at)),
+            StoredStreamType::Legacy(LegacyStreamType::State(state)) => {
+                push_state(&mut steps, None, state, false, None)
+            }
+            StoredStreamType::Current(GameStreamType::Action { seat, action, at }) => {
+                push_action(&mut steps, Some(seat), action, Some(at))
+            }
+            StoredStreamType::Legacy(LegacyStreamType::Action(action)) => {
+                push_action(&mut steps, None, action, None)
+            }
+            StoredStreamType::Current(GameStreamType::Log { agent_id, message }) => {
+                if my_agents.contains(&agent_id) {
+                    if let Some(step) = steps.last_mut() {
+                        step.logs.push(message);
+                    }
+                }
+            }
+            StoredStreamType::Current(GameStreamType::Resign {
+                agent_id,
+                reason,
+                error,
+            }) => {
+                let kind = if error { "error" } else { "resign" };
+                if let Some(step) = steps.last_mut() {
+                    step.logs
+                        .push(format!("agent {} {}: {}", agent_id, kind, reason));
+                    step.is_over = true;
+                }
+            }
+        }
+    }
+    // 旧格式没有 is_over, 以最后一步为终局
+    if let Some(step) = steps.last_mut() {
+        step.is_over = true;
+    }
+    Ok(GameReplay {
+        match_id: turn.match_id,
+        i_turn: turn.i_turn,
+        start_time: turn.start_time,
+        end_time: turn.end_time,
+        seed: turn.seed,
+        steps,
+        payoffs: turn.score_deltas,
+    })
+}
+
+fn push_state(
+    steps: &mut Vec<ReplayStep>,
+    seat: Option<i32>,
+    state: String,
+    is_over: bool,
+    timestamp: Option<DateTime<Utc>>,
+) {
+    steps.push(ReplayStep {
+        index: steps.len() as i32,
+        seat,
+        state: Some(state),
+        action: None,
+        is_over,
+        timestamp,
+        logs: Vec::new(),
+    });
+}
+
+fn push_action(
+    steps: &mut Vec<ReplayStep>,
+    seat: Option<i32>,
+    action: String,
+    timestamp: Option<DateTime<Utc>>,
+) {
+    match steps.last_mut() {
+        Some(step) if step.action.is_none() && !step.is_over => {
+            step.action = Some(action);
+            step.seat = step.seat.or(seat);
+        }
+        // 没有对应状态的动作单独成一步
+        _ => steps.push(ReplayStep {
+            index: steps.len() as i32,
+            seat,
+            state: None,
+            action: Some(action),
+            is_over: false,
+            timestamp,
+            logs: Vec::new(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tackle_box::connection::GameInitResponse;
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn at(second: u32) -> DateTime<Utc> {
+        format!("2026-10-18T08:00:{:02}Z", second).parse().unwrap()
+    }
+
+    fn turn(log: serde_json::Value) -> TurnLogResponse {
+        TurnLogResponse {
+            turn_id: id(100),
+            match_id: id(10),
+            log,
+            i_turn: 3,
+            score_deltas: json!({ id(1).to_string(): 1.0, id(2).to_string(): -1.0 }),
+            start_time: at(0),
+            end_time: at(59),
+            seed: Some(42),
+            seats: Some(vec![id(1), id(2)]),
+        }
+    }
+
+    fn replay(log: serde_json::Value, my_agents: &[Uuid]) -> GameReplay {
+        build_replay(turn(log), &my_agents.iter().copied().collect()).unwrap()
+    }
+
+    #[test]
+    fn pairs_each_state_with_the_action_of_its_seat() {
+        let replay = replay(
+            json!([
+                { "State": { "seat": 0, "state": "s0", "is_over": false, "at": at(1) } },
+                { "Action": { "seat": 0, "action": "a0", "at": at(2) } },
+                { "State": { "seat": 1, "state": "s1", "is_over": false, "at": at(3) } },
+                { "Action": { "seat": 1, "action": "a1", "at": at(4) } },
+                { "State": { "seat": 0, "state": "s2", "is_over": true, "at": at(5) } },
+            ]),
+            &[],
+        );
+        let steps: Vec<_> = replay
+            .steps
+            .iter()
+            .map(|step| {
+                (
+                    step.index,
+                    step.seat,
+                    step.state.as_deref(),
+                    step.action.as_deref(),
+                    step.is_over,
+                )
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (0, Some(0), Some("s0"), Some("a0"), false),
+                (1, Some(1), Some("s1"), Some("a1"), false),
+                (2, Some(0), Some("s2"), None, true),
+            ]
+        );
+        // 时间戳取自状态下发的时刻
+        let timestamps: Vec<_> = replay.steps.iter().map(|step| step.timestamp).collect();
+        assert_eq!(timestamps, vec![Some(at(1)), Some(at(3)), Some(at(5))]);
+    }
+
+    #[test]
+    fn copies_the_turn_metadata_and_payoffs() {
+        let replay = replay(json!([]), &[]);
+        assert_eq!(replay.match_id, id(10));
+        assert_eq!(replay.i_turn, 3);
+        assert_eq!(replay.start_time, at(0));
+        assert_eq!(replay.end_time, at(59));
+        assert_eq!(replay.seed, Some(42));
+        assert_eq!(replay.payoffs[id(1).to_string()], json!(1.0));
+        assert_eq!(replay.payoffs[id(2).to_string()], json!(-1.0));
+        assert!(replay.steps.is_empty());
+    }
+
+    #[test]
+    fn reads_legacy_tuple_variants() {
+        let replay = replay(
+            json!([{ "State": "s0" }, { "Action": "a0" }, { "State": "s1" }]),
+            &[],
+        );
+        assert_eq!(replay.steps.len(), 2);
+        let first = &replay.steps[0];
+        assert_eq!(first.seat, None);
+        assert_eq!(first.state.as_deref(), Some("s0"));
+        assert_eq!(first.action.as_deref(), Some("a0"));
+        assert_eq!(first.timestamp, None);
+        assert!(!first.is_over);
+        // 旧格式没有 is_over, 最后一步视为终局
+        assert_eq!(replay.steps[1].state.as_deref(), Some("s1"));
+        assert!(replay.steps[1].is_over);
+    }
+
+    #[test]
+    fn action_without_state_is_its_own_step() {
+        let replay = replay(
+            json!([
+                { "Action": { "seat": 1, "action": "a0", "at": at(2) } },
+                { "Action": "a1" },
+            ]),
+            &[],
+        );
+        let actions: Vec<_> = replay
+            .steps
+            .iter()
+            .map(|step| (step.seat, step.state.as_deref(), step.action.as_deref()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![(Some(1), None, Some("a0")), (None, None, Some("a1"))]
+        );
+        assert_eq!(replay.steps[0].timestamp, Some(at(2)));
+    }
+
+    #[test]
+    fn shows_only_my_agent_logs_and_marks_resign() {
+        let replay = replay(
+            json!([
+                { "State": { "seat": 0, "state": "s0", "is_over": false, "at": at(1) } },
+                { "Log": { "agent_id": id(1), "message": "mine" } },
+                { "Log": { "agent_id": id(2), "message": "theirs" } },
+                { "Resign": { "agent_id": id(1), "reason": "timeout", "error": true } },
+            ]),
+            &[id(1)],
+        );
+        assert_eq!(replay.steps.len(), 1);
+        let step = &replay.steps[0];
+        assert_eq!(
+            step.logs,
+            vec![
+                "mine".to_string(),
+                format!("agent {} error: timeout", id(1))
+            ]
+        );
+        assert!(step.is_over);
+        assert_eq!(step.action, None);
+    }
+
+    #[test]
+    fn rejects_unknown_entries() {
+        let result = build_replay(turn(json!([{ "Move": "a0" }])), &HashSet::new());
+        assert!(result.is_err());
+    }
+
+    fn state(seat: i32, state: &str, is_over: bool) -> GameStreamType {
+        GameStreamType::State {
+            seat,
+            state: state.to_string(),
+            is_over,
+            at: at(0),
+        }
+    }
+
+    fn action(seat: i32, action: &str) -> GameStreamType {
+        GameStreamType::Action {
+            seat,
+            action: action.to_string(),
+            at: at(0),
+        }
+    }
+
+    fn update(i_player: i32, state: &str, is_over: bool) -> Option<ResponseType> {
+        Some(ResponseType::StateUpdate(GameStateUpdate {
+            state: state.to_string(),
+            is_over,
+            i_player,
+        }))
+    }
+
+    fn end(payoffs: &[f32]) -> Option<ResponseType> {
+        Some(ResponseType::EndStatus(GameEndStatus {
+            payoffs: payoffs.to_vec(),
+        }))
+    }
+
+    /// 两步后终局的对局记录, 座位 0 胜
+    fn recorded() -> Vec<GameStreamType> {
+        vec![
+            state(0, "s0", false),
+            action(0, "a0"),
+            state(1, "s1", false),
+            action(1, "a1"),
+            state(0, "s2", true),
+        ]
+    }
+
+    /// 依次喂给 Checker, 返回每条输出后的下一步
+    fn run(checker: &mut Checker, responses: Vec<Option<ResponseType>>) -> Vec<String> {
+        responses
+            .into_iter()
+            .map(|resp| match checker.check(resp).unwrap() {
+                Step::Send(action) => format!("send {}", action),
+                Step::Wait => "wait".to_string(),
+                Step::Done => "done".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matching_simulation_replays_the_stored_actions() {
+        let mut checker = Checker::new(recorded(), StoredPayoffs::BySeat(vec![1.0, -1.0]));
+        let steps = run(
+            &mut checker,
+            vec![
+                Some(ResponseType::InitResponse(GameInitResponse::default())),
+                update(0, "s0", false),
+                update(1, "s1", false),
+                update(0, "s2", true),
+                end(&[1.0, -1.0]),
+            ],
+        );
+        assert_eq!(steps, vec!["wait", "send a0", "send a1", "wait", "done"]);
+        assert_eq!(checker.mismatch, None);
+        assert_eq!(checker.checked_states, 3);
+    }
+
+    #[test]
+    fn diverging_state_stops_at_the_first_difference() {
+        let mut checker = Checker::new(recorded(), StoredPayoffs::BySeat(vec![1.0, -1.0]));
+        let steps = run(
+            &mut checker,
+            vec![update(0, "s0", false), update(1, "other", false)],
+        );
+        assert_eq!(steps, vec!["send a0", "done"]);
+        assert_eq!(
+            checker.mismatch.as_deref(),
+            Some("state 1 differs from the stored log")
+        );
+        assert_eq!(checker.checked_states, 1);
+    }
+
+    #[test]
+    fn seat_is_part_of_the_compared_state() {
+        let mut checker = Checker::new(recorded(), StoredPayoffs::BySeat(vec![1.0, -1.0]));
+        run(&mut checker, vec![update(1, "s0", false)]);
+        assert_eq!(
+            checker.mismatch.as_deref(),
+            Some("state 0 differs from the stored log")
+        );
+    }
+
+    #[test]
+    fn diverging_payoffs_fail_after_the_last_state() {
+        let mut checker = Checker::new(recorded(), StoredPayoffs::BySeat(vec![1.0, -1.0]));
+        let steps = run(
+            &mut checker,
+            vec![
+                update(0, "s0", false),
+                update(1, "s1", false),
+                update(0, "s2", true),
+                end(&[-1.0, 1.0]),
+            ],
+        );
+        assert_eq!(steps.last().map(String::as_str), Some("done"));
+        assert_eq!(
+            checker.mismatch.as_deref(),
+            Some("payoffs differ from the stored result")
+        );
+    }
+
+    #[test]
+    fn early_end_and_extra_states_are_mismatches() {
+        let mut checker = Checker::new(recorded(), StoredPayoffs::BySeat(vec![1.0, -1.0]));
+        run(
+            &mut checker,
+            vec![update(0, "s0", false), end(&[1.0, -1.0])],
+        );
+        assert_eq!(
+            checker.mismatch.as_deref(),
+            Some("sponsor ended the game before the stored log")
+        );
+
+        let mut checker =
+            Checker::new(vec![state(0, "s0", true)], StoredPayoffs::BySeat(vec![0.0]));
+        run(
+            &mut checker,
+            vec![update(0, "s0", true), update(0, "s1", false)],
+        );
+        assert_eq!(
+            checker.mismatch.as_deref(),
+            Some("unexpected state 1 from sponsor")
+        );
+    }
+
+    #[test]
+    fn failed_init_is_an_error() {
+        let mut checker = Checker::new(recorded(), StoredPayoffs::BySeat(vec![1.0, -1.0]));
+        let init = GameInitResponse {
+            r#type: ResultType::Failed.into(),
+        };
+        assert!(checker
+            .check(Some(ResponseType::InitResponse(init)))
+            .is_err());
+    }
+
+    #[test]
+    fn forfeited_log_ends_where_the_record_stops() {
+        let entries = vec![
+            state(0, "s0", false),
+            action(0, "a0"),
+            state(1, "s1", false),
+            GameStreamType::Resign {
+                agent_id: id(2),
+                reason: "timeout".to_string(),
+                error: true,
+            },
+        ];
+        let mut checker = Checker::new(entries, StoredPayoffs::BySeat(vec![1.0, -1.0]));
+        let steps = run(
+            &mut checker,
+            vec![update(0, "s0", false), update(1, "s1", false)],
+        );
+        assert_eq!(steps, vec!["send a0", "done"]);
+        assert_eq!(checker.mismatch, None);
+        assert_eq!(checker.checked_states, 2);
+    }
+
+    #[test]
+    fn payoffs_compare_by_seat_when_seats_are_stored() {
+        let deltas = HashMap::from([(id(1), 1.0), (id(2), -1.0)]);
+        let by_seat = StoredPayoffs::new(Some(&[id(2), id(1)]), deltas.clone());
+        assert!(by_seat.matches(vec![-1.0, 1.0]));
+        assert!(!by_seat.matches(vec![1.0, -1.0]));
+        // 旧数据只比较得分集合
+        let unordered = StoredPayoffs::new(None, deltas);
+        assert!(unordered.matches(vec![1.0, -1.0]));
+        assert!(unordered.matches(vec![-1.0, 1.0]));
+        assert!(!unordered.matches(vec![1.0, 1.0]));
+    }
+
+    #[test]
+    fn only_owners_of_a_participant_may_verify() {
+        let deltas = HashMap::from([(id(1), 1.0), (id(2), -1.0)]);
+        assert!(ensure_participant_owner(id(10), &[id(5), id(2)], &deltas).is_ok());
+        match ensure_participant_owner(id(10), &[id(5)], &deltas) {
+            Err(AppError::Validation(message)) => {
+                assert_eq!(
+                    message,
+                    format!("match {} has no agent owned by you", id(10))
+                )
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(ensure_participant_owner(id(10), &[], &deltas).is_err());
+    }
+
+    #[test]
+    fn missing_game_is_a_validation_error() {
+        match turn_error(7, RepoError::TechnicalError(sqlx::Error::RowNotFound)) {
+            AppError::Validation(message) => assert_eq!(message, "game 7 not found"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(matches!(
+            turn_error(7, RepoError::TechnicalError(sqlx::Error::PoolTimedOut)),
+            AppError::Database(_)
+        ));
+    }
+}
@@ -0,0 +1,426 @@
+use std::{cmp::Ordering, collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::{TimeDelta, Utc};
+use tackle_box::contracts::payloads::{
+    GetSeasonResponse, MatchStatus, NewSeasonPayload, SeasonStanding,
+};
+use tokio::time;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    api::error::AppError,
+    repo::{
+        game_type::GameTypeRepo,
+        seasons::{NewSeasonDTO, SeasonDTO, SeasonRatingDTO, SeasonRepo, UnratedMatchDTO},
+    },
+};
+
+/// 新 Agent 在赛季中的初始分数
+pub const BASE_RATING: f64 = 1500.0;
+/// 每场比赛的最大分数变化
+const K_FACTOR: f64 = 32.0;
+const SEASON_TICK: Duration = Duration::from_secs(60);
+
+struct Repos {
+    season_repo: Arc<SeasonRepo>,
+    gametype_repo: Arc<GameTypeRepo>,
+}
+
+pub struct SeasonService {
+    repos: Repos,
+}
+
+impl SeasonService {
+    pub fn new(season_repo: Arc<SeasonRepo>, gametype_repo: Arc<GameTypeRepo>) -> Self {
+        Self {
+            repos: Repos {
+                season_repo,
+                gametype_repo,
+            },
+        }
+    }
+
+    pub async fn new_season(
+        &self,
+        user_id: Uuid,
+        payload: NewSeasonPayload,
+    ) -> Result<Uuid, AppError> {
+        let NewSeasonPayload {
+            name,
+            game_type_id,
+            start_time,
+            days,
+            carry_over,
+            recurring,
+        } = payload;
+        if days <= 0 {
+            return Err(AppError::Validation("days must be positive".to_string()));
+        }
+        if !(0.0..=1.0).contains(&carry_over) {
+            return Err(AppError::Validation(
+                "carry_over must be between 0 and 1".to_string(),
+            ));
+        }
+        let game_type = self.repos.gametype_repo.get_game_type(game_type_id).await?;
+        let start_time = start_time.unwrap_or_else(Utc::now);
+        let end_time = start_time + TimeDelta::days(days as i64);
+        if self
+            .repos
+            .season_repo
+            .count_overlapping(game_type_id, start_time, end_time)
+            .await?
+            > 0
+        {
+            return Err(AppError::Validation(format!(
+                "another season of {} overlaps this period",
+                game_type.name
+            )));
+        }
+
+        let season = NewSeasonDTO {
+            name,
+            game_type_id,
+            creater_id: user_id,
+            start_time,
+            end_time,
+            carry_over,
+            recurring,
+        };
+        Ok(self.repos.season_repo.new_season(season).await?)
+    }
+
+    pub async fn get_season(&self, season_id: Uuid) -> Result<GetSeasonResponse, AppError> {
+        Ok(self.repos.season_repo.get_season(season_id).await?)
+    }
+
+    pub async fn get_seasons(&self) -> Result<Vec<GetSeasonResponse>, AppError> {
+        Ok(self.repos.season_repo.get_seasons().await?)
+    }
+
+    /// 进行中的赛季返回实时排名, 已结束的返回归档结果
+    pub async fn get_standings(&self, season_id: Uuid) -> Result<Vec<SeasonStanding>, AppError> {
+        let season = self.repos.season_repo.get_season(season_id).await?;
+        let standings = match season.status {
+            MatchStatus::Completed => {
+                self.repos
+                    .season_repo
+                    .get_archived_standings(season_id)
+                    .await?
+            }
+            _ => self.repos.season_repo.get_live_standings(season_id).await?,
+        };
+        Ok(standings)
+    }
+
+    /// 定时计分、结束到期赛季并开启到点的赛季
+    pub async fn run(&self) {
+        let mut interval = time::interval(SEASON_TICK);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.tick().await {
+                warn!("--- SEASON ERROR: Failed to update seasons: {:?} ---", e);
+            }
+        }
+    }
+
+    /// 单个赛季出错只记录日志, 不影响其他赛季
+    async fn tick(&self) -> Result<(), AppError> {
+        let now = Utc::now();
+        let Repos { season_repo, .. } = &self.repos;
+        // 先结束旧赛季, 循环赛季的下一季才能带入归档分数
+        for season in season_repo
+            .get_seasons_by_status(MatchStatus::Running)
+            .await?
+        {
+            let result = match self.rate_season(&season).await {
+                Ok(()) if season.end_time <= now => self.close_season(&season).await,
+                result => result,
+            };
+            if let Err(e) = result {
+                warn!("failed to update season {}: {:?}", season.season_id, e);
+            }
+        }
+        for season in season_repo
+            .get_seasons_by_status(MatchStatus::Pending)
+            .await?
+        {
+            if season.start_time <= now {
+                if let Err(e) = self.open_season(&season).await {
+                    warn!("failed to open season {}: {:?}", season.season_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn rate_season(&self, season: &SeasonDTO) -> Result<(), AppError> {
+        let Repos { season_repo, .. } = &self.repos;
+        let matches = season_repo.get_unrated_matches(season).await?;
+        if matches.is_empty() {
+            return Ok(());
+        }
+        let mut ratings: HashMap<Uuid, SeasonRatingDTO> = season_repo
+            .get_ratings(season.season_id)
+            .await?
+            .into_iter()
+            .map(|r| (r.agent_id, r))
+            .collect();
+        let count = matches.len();
+        for one_match in matches {
+            let (updated, changes) = rate_match(&mut ratings, &one_match).into_iter().unzip();
+            season_repo
+                .rate_match(season.season_id, one_match.match_id, updated, changes)
+                .await?;
+        }
+        info!("season {} rated {} matches", season.season_id, count);
+        Ok(())
+    }
+
+    async fn close_season(&self, season: &SeasonDTO) -> Result<(), AppError> {
+        let Repos { season_repo, .. } = &self.repos;
+        season_repo.close_season(season.season_id).await?;
+        info!("season {} closed", season.season_id);
+        if season.recurring {
+            let start_time = season.end_time;
+            let end_time = start_time + (season.end_time - season.start_time);
+            // 与手动创建赛季相同, 期间已有其他赛季时不再续期
+            if season_repo
+                .count_overlapping(season.game_type_id, start_time, end_time)
+                .await?
+                > 0
+            {
+                warn!(
+                    "season {} not renewed, another season overlaps the next period",
+                    season.season_id
+                );
+                return Ok(());
+            }
+            let next = NewSeasonDTO {
+                name: season.name.clone(),
+                game_type_id: season.game_type_id,
+                creater_id: season.creater_id,
+                start_time,
+                end_time,
+                carry_over: season.carry_over,
+                recurring: true,
+            };
+            let season_id = season_repo.new_season(next).await?;
+            info!("season {} scheduled after {}", season_id, season.season_id);
+        }
+        Ok(())
+    }
+
+    /// 开启赛季, 按上一季的 carry_over 把归档分数向初始分数衰减后带入
+    async fn open_season(&self, season: &SeasonDTO) -> Result<(), AppError> {
+        let Repos { season_repo, .. } = &self.repos;
+        let previous = season_repo
+            .get_previous_season(season.game_type_id, season.start_time)
+            .await?;
+        if let Some(previous) = previous.as_ref().filter(|p| p.carry_over > 0.0) {
+            let ratings = season_repo
+                .get_archived_standings(previous.season_id)
+                .await?
+                .into_iter()
+                .map(|s| SeasonRatingDTO {
+                    agent_id: s.agent_id,
+                    rating: carry_over(s.rating, previous.carry_over),
+                    played: 0,
+                    won: 0,
+                    drawn: 0,
+                    lost: 0,
+                })
+                .collect();
+            season_repo.seed_ratings(season.season_id, ratings).await?;
+        }
+        season_repo
+            .open_season(season.season_id, previous.map(|p| p.season_id))
+            .await?;
+        info!("season {} opened", season.season_id);
+        Ok(())
+    }
+}
+
+/// 上一季的分数带入新赛季: 与初始分数的差距按 carry_over 缩小
+fn carry_over(rating: f64, carry_over: f64) -> f64 {
+    BASE_RATING + (rating - BASE_RATING) * carry_over
+}
+
+/// 按比赛结果更新参赛者的 Elo 分数, 返回更新后的记录及本场的分数变化
+///
+/// 每对参赛者按名次比较: 名次靠前的记胜, 名次相同记平.
+/// 胜平负场数按比赛结果记录, 只有平局比赛中并列第一的参赛者记平.
+/// 多人比赛中每对参赛者的变化按 K / (n - 1) 缩放.
+fn rate_match(
+    ratings: &mut HashMap<Uuid, SeasonRatingDTO>,
+    one_match: &UnratedMatchDTO,
+) -> Vec<(SeasonRatingDTO, f64)> {
+    let agent_ids = &one_match.agent_ids;
+    let placements = &one_match.placements;
+    let current: Vec<f64> = agent_ids
+        .iter()
+        .map(|id| ratings.get(id).map_or(BASE_RATING, |r| r.rating))
+        .collect();
+    let k = K_FACTOR / (agent_ids.len().max(2) - 1) as f64;
+    let mut updated = Vec::with_capacity(agent_ids.len());
+    for (i, &agent_id) in agent_ids.iter().enumerate() {
+        let mut delta = 0.0;
+        for j in 0..agent_ids.len() {
+            if i == j {
+                continue;
+            }
+            let actual = match placements[i].cmp(&placements[j]) {
+                Ordering::Less => 1.0,
+                Ordering::Greater => 0.0,
+                Ordering::Equal => 0.5,
+            };
+            let expected = 1.0 / (1.0 + 10f64.powf((current[j] - current[i]) / 400.0));
+            delta += k * (actual - expected);
+        }
+        let record = ratings.entry(agent_id).or_insert(SeasonRatingDTO {
+            agent_id,
+            rating: BASE_RATING,
+            played: 0,
+            won: 0,
+            drawn: 0,
+            lost: 0,
+        });
+        record.rating = current[i] + delta;
+        record.played += 1;
+        match one_match.winner_id {
+            Some(winner) if winner == agent_id => record.won += 1,
+            None if one_match.is_draw && placements[i] == 1 => record.drawn += 1,
+            _ => record.lost += 1,
+        }
+        updated.push((record.clone(), delta));
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn one_match(placements: &[i32], winner: Option<u128>, is_draw: bool) -> UnratedMatchDTO {
+        UnratedMatchDTO {
+            match_id: Uuid::nil(),
+            winner_id: winner.map(id),
+            is_draw,
+            agent_ids: (1..=placements.len() as u128).map(id).collect(),
+            placements: placements.to_vec(),
+        }
+    }
+
+    fn rating(agent_id: Uuid, rating: f64) -> (Uuid, SeasonRatingDTO) {
+        let record = SeasonRatingDTO {
+            agent_id,
+            rating,
+            played: 3,
+            won: 1,
+            drawn: 1,
+            lost: 1,
+        };
+        (agent_id, record)
+    }
+
+    fn deltas(updated: &[(SeasonRatingDTO, f64)]) -> Vec<f64> {
+        updated.iter().map(|(_, delta)| *delta).collect()
+    }
+
+    /// (胜, 平, 负)
+    fn results(updated: &[(SeasonRatingDTO, f64)]) -> Vec<(i32, i32, i32)> {
+        updated
+            .iter()
+            .map(|(r, _)| (r.won, r.drawn, r.lost))
+            .collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn unrated_agents_start_at_base_rating() {
+        let mut ratings = HashMap::new();
+        let updated = rate_match(&mut ratings, &one_match(&[1, 2], Some(1), false));
+        assert_eq!(deltas(&updated), vec![K_FACTOR / 2.0, -K_FACTOR / 2.0]);
+        assert_close(ratings[&id(1)].rating, BASE_RATING + 16.0);
+        assert_close(ratings[&id(2)].rating, BASE_RATING - 16.0);
+        assert_eq!(results(&updated), vec![(1, 0, 0), (0, 0, 1)]);
+        assert!(updated.iter().all(|(r, _)| r.played == 1));
+    }
+
+    #[test]
+    fn two_player_deltas_are_symmetric() {
+        // 低分的 2 号爆冷获胜, 得到的比 K / 2 多
+        let mut ratings = HashMap::from([rating(id(1), 1600.0), rating(id(2), 1500.0)]);
+        let updated = rate_match(&mut ratings, &one_match(&[2, 1], Some(2), false));
+        let deltas = deltas(&updated);
+        let expected = 1.0 / (1.0 + 10f64.powf(100.0 / 400.0));
+        assert_close(deltas[1], K_FACTOR * (1.0 - expected));
+        assert_close(deltas[0] + deltas[1], 0.0);
+        assert!(deltas[1] > K_FACTOR / 2.0);
+        assert_close(ratings[&id(1)].rating, 1600.0 + deltas[0]);
+        assert_eq!(results(&updated), vec![(1, 1, 2), (2, 1, 1)]);
+        assert_eq!(ratings[&id(1)].played, 4);
+    }
+
+    #[test]
+    fn draw_between_equals_changes_nothing() {
+        let mut ratings = HashMap::new();
+        let updated = rate_match(&mut ratings, &one_match(&[1, 1], None, true));
+        assert_eq!(deltas(&updated), vec![0.0, 0.0]);
+        assert_eq!(results(&updated), vec![(0, 1, 0), (0, 1, 0)]);
+    }
+
+    #[test]
+    fn draw_pulls_ratings_together() {
+        let mut ratings = HashMap::from([rating(id(1), 1700.0), rating(id(2), 1500.0)]);
+        let updated = rate_match(&mut ratings, &one_match(&[1, 1], None, true));
+        let deltas = deltas(&updated);
+        assert!(deltas[0] < 0.0);
+        assert_close(deltas[0] + deltas[1], 0.0);
+    }
+
+    #[test]
+    fn three_players_are_rated_pairwise_by_placement() {
+        // 三人时 K 按 n - 1 减半: 第一名两胜, 第二名一胜一负, 第三名两负
+        let mut ratings = HashMap::new();
+        let updated = rate_match(&mut ratings, &one_match(&[2, 1, 3], Some(2), false));
+        assert_eq!(deltas(&updated), vec![0.0, 16.0, -16.0]);
+        assert_eq!(results(&updated), vec![(0, 0, 1), (1, 0, 0), (0, 0, 1)]);
+    }
+
+    #[test]
+    fn only_tied_first_places_are_drawn() {
+        let mut ratings = HashMap::new();
+        let updated = rate_match(&mut ratings, &one_match(&[1, 1, 3], None, true));
+        assert_eq!(deltas(&updated), vec![8.0, 8.0, -16.0]);
+        assert_eq!(results(&updated), vec![(0, 1, 0), (0, 1, 0), (0, 0, 1)]);
+    }
+
+    #[test]
+    fn shared_first_without_a_draw_counts_as_a_loss() {
+        // 不允许平局时并列第一仍有唯一胜者
+        let mut ratings = HashMap::new();
+        let updated = rate_match(&mut ratings, &one_match(&[1, 1], Some(1), false));
+        assert_eq!(results(&updated), vec![(1, 0, 0), (0, 0, 1)]);
+    }
+
+    #[test]
+    fn carry_over_decays_towards_base_rating() {
+        assert_close(carry_over(1700.0, 0.5), 1600.0);
+        assert_close(carry_over(1300.0, 0.25), 1450.0);
+        assert_close(carry_over(1800.0, 0.0), BASE_RATING);
+        assert_close(carry_over(1800.0, 1.0), 1800.0);
+    }
+}
@@ -0,0 +1,237 @@
+//! 比赛结算: 按游戏类型的规则根据每局得分排出名次, 不访问数据库
+
+use std::cmp::Ordering;
+
+use tackle_box::contracts::payloads::SettlementRule;
+use uuid::Uuid;
+
+/// 单个参赛者的结算结果
+#[derive(Debug, Clone)]
+pub struct Standing {
+    pub agent_id: Uuid,
+    pub total_score: f32,
+    /// 单局得分唯一最高的局数
+    pub games_won: i32,
+    /// 名次从 1 开始, 并列时相同
+    pub placement: i32,
+}
+
+#[derive(Debug)]
+pub struct Outcome {
+    /// 按名次排列
+    pub standings: Vec<Standing>,
+    pub winner_id: Option<Uuid>,
+    pub is_draw: bool,
+}
+
+/// 计算比赛结果, agent_ids 按加入比赛的顺序排列, payoffs 为每局按 agent_ids 顺序的得分
+///
+/// 认输的 Agent 排在最后且不能获胜. 并列第一时允许平局则记平局,
+/// 否则按加入比赛的顺序决出胜者.
+pub fn settle(
+    rule: SettlementRule,
+    allow_draws: bool,
+    agent_ids: &[Uuid],
+    payoffs: &[Vec<f32>],
+    forfeit: Option<Uuid>,
+) -> Outcome {
+    let mut standings: Vec<Standing> = agent_ids
+        .iter()
+        .map(|&agent_id| Standing {
+            agent_id,
+            total_score: 0.0,
+            games_won: 0,
+            placement: 0,
+        })
+        .collect();
+    for game in payoffs {
+        for (standing, score) in standings.iter_mut().zip(game) {
+            standing.total_score += score;
+        }
+        if let Some(i) = game_winner(game) {
+            standings[i].games_won += 1;
+        }
+    }
+
+    let compare = |a: &Standing, b: &Standing| {
+        let forfeited = |s: &Standing| Some(s.agent_id) == forfeit;
+        let by_score = || {
+            b.total_score
+                .partial_cmp(&a.total_score)
+                .unwrap_or(Ordering::Equal)
+        };
+        let by_games = || b.games_won.cmp(&a.games_won);
+        forfeited(a).cmp(&forfeited(b)).then_with(|| match rule {
+            SettlementRule::HighestScore => by_score().then_with(by_games),
+            SettlementRule::MostGamesWon => by_games().then_with(by_score),
+        })
+    };
+    // 稳定排序, 完全并列时保持加入顺序
+    standings.sort_by(compare);
+    for i in 0..standings.len() {
+        standings[i].placement = match i {
+            0 => 1,
+            _ if compare(&standings[i - 1], &standings[i]) == Ordering::Equal => {
+                standings[i - 1].placement
+            }
+            _ => i as i32 + 1,
+        };
+    }
+    if !allow_draws {
+        for standing in standings.iter_mut().skip(1) {
+            if standing.placement == 1 {
+                standing.placement = 2;
+            }
+        }
+    }
+
+    let leaders: Vec<&Standing> = standings
+        .iter()
+        .filter(|s| s.placement == 1 && Some(s.agent_id) != forfeit)
+        .collect();
+    let winner_id = match leaders.as_slice() {
+        [leader] => Some(leader.agent_id),
+        _ => None,
+    };
+    let is_draw = leaders.len() > 1;
+    Outcome {
+        standings,
+        winner_id,
+        is_draw,
+    }
+}
+
+/// 单局得分唯一最高的参赛者, 并列最高时没有人赢下这一局
+fn game_winner(game: &[f32]) -> Option<usize> {
+    let (best, &max) = game
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
+    let unique = game.iter().filter(|&&score| score == max).count() == 1;
+    unique.then_some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn ids(n: u128) -> Vec<Uuid> {
+        (1..=n).map(id).collect()
+    }
+
+    /// 按名次排列的 (id 号, 名次)
+    fn placements(outcome: &Outcome) -> Vec<(u128, i32)> {
+        outcome
+            .standings
+            .iter()
+            .map(|s| (s.agent_id.as_u128(), s.placement))
+            .collect()
+    }
+
+    #[test]
+    fn highest_score_ranks_by_total() {
+        // 1 号赢两局但总分低, 2 号一局大胜
+        let payoffs = vec![vec![1.0, -1.0], vec![1.0, -1.0], vec![-5.0, 5.0]];
+        let outcome = settle(SettlementRule::HighestScore, true, &ids(2), &payoffs, None);
+        assert_eq!(placements(&outcome), vec![(2, 1), (1, 2)]);
+        assert_eq!(outcome.winner_id, Some(id(2)));
+        assert!(!outcome.is_draw);
+        assert_eq!(outcome.standings[0].total_score, 3.0);
+        assert_eq!(outcome.standings[1].games_won, 2);
+    }
+
+    #[test]
+    fn most_games_won_ranks_by_games() {
+        let payoffs = vec![vec![1.0, -1.0], vec![1.0, -1.0], vec![-5.0, 5.0]];
+        let outcome = settle(SettlementRule::MostGamesWon, true, &ids(2), &payoffs, None);
+        assert_eq!(placements(&outcome), vec![(1, 1), (2, 2)]);
+        assert_eq!(outcome.winner_id, Some(id(1)));
+    }
+
+    #[test]
+    fn tied_games_are_won_by_nobody() {
+        let payoffs = vec![vec![0.0, 0.0], vec![2.0, 1.0]];
+        let outcome = settle(SettlementRule::MostGamesWon, true, &ids(2), &payoffs, None);
+        let games_won: Vec<i32> = outcome.standings.iter().map(|s| s.games_won).collect();
+        assert_eq!(games_won, vec![1, 0]);
+    }
+
+    #[test]
+    fn exact_tie_is_a_draw_when_allowed() {
+        let payoffs = vec![vec![1.0, -1.0], vec![-1.0, 1.0]];
+        let outcome = settle(SettlementRule::HighestScore, true, &ids(2), &payoffs, None);
+        assert_eq!(placements(&outcome), vec![(1, 1), (2, 1)]);
+        assert_eq!(outcome.winner_id, None);
+        assert!(outcome.is_draw);
+    }
+
+    #[test]
+    fn exact_tie_goes_to_the_earlier_entrant_without_draws() {
+        let payoffs = vec![vec![1.0, -1.0], vec![-1.0, 1.0]];
+        for agent_ids in [ids(2), vec![id(2), id(1)]] {
+            let outcome = settle(
+                SettlementRule::HighestScore,
+                false,
+                &agent_ids,
+                &payoffs,
+                None,
+            );
+            assert_eq!(outcome.winner_id, Some(agent_ids[0]));
+            assert!(!outcome.is_draw);
+            assert_eq!(outcome.standings[1].placement, 2);
+        }
+    }
+
+    #[test]
+    fn forfeit_overrides_payoffs() {
+        let payoffs = vec![vec![10.0, -10.0], vec![10.0, -10.0]];
+        for rule in [SettlementRule::HighestScore, SettlementRule::MostGamesWon] {
+            let outcome = settle(rule, true, &ids(2), &payoffs, Some(id(1)));
+            assert_eq!(placements(&outcome), vec![(2, 1), (1, 2)]);
+            assert_eq!(outcome.winner_id, Some(id(2)));
+            assert!(!outcome.is_draw);
+        }
+    }
+
+    #[test]
+    fn forfeit_with_no_games_played() {
+        let outcome = settle(
+            SettlementRule::HighestScore,
+            true,
+            &ids(3),
+            &[],
+            Some(id(2)),
+        );
+        // 其余两人完全并列, 记平局
+        assert_eq!(placements(&outcome), vec![(1, 1), (3, 1), (2, 3)]);
+        assert_eq!(outcome.winner_id, None);
+        assert!(outcome.is_draw);
+    }
+
+    #[test]
+    fn multiplayer_placements_share_places() {
+        // 总分: 1 号 3, 2 号 5, 3 号 3, 4 号 -1
+        let payoffs = vec![vec![2.0, 3.0, 1.0, 0.0], vec![1.0, 2.0, 2.0, -1.0]];
+        let outcome = settle(SettlementRule::HighestScore, true, &ids(4), &payoffs, None);
+        assert_eq!(placements(&outcome), vec![(2, 1), (1, 2), (3, 2), (4, 4)]);
+        assert_eq!(outcome.winner_id, Some(id(2)));
+    }
+
+    #[test]
+    fn multiplayer_tie_for_first() {
+        // 1 号和 3 号总分与赢局数都相同
+        let payoffs = vec![vec![3.0, 0.0, 1.0], vec![1.0, 0.0, 3.0]];
+        let outcome = settle(SettlementRule::HighestScore, true, &ids(3), &payoffs, None);
+        assert_eq!(placements(&outcome), vec![(1, 1), (3, 1), (2, 3)]);
+        assert!(outcome.is_draw);
+
+        let outcome = settle(SettlementRule::HighestScore, false, &ids(3), &payoffs, None);
+        assert_eq!(placements(&outcome), vec![(1, 1), (3, 2), (2, 3)]);
+        assert_eq!(outcome.winner_id, Some(id(1)));
+        assert!(!outcome.is_draw);
+    }
+}
@@ -1,15 +1,20 @@
 use crate::{
     api::error::AppError,
+    core::persistence::Persistence,
     repo::{
         agents::{AgentRepo, GetRankableAgentDTO},
-        stats::{StatsRepo, UpdateStatsDTO},
+        stats::{HeadToHeadDTO, StatsRepo, UpdateStatsDTO},
     },
 };
 use std::{collections::HashMap, sync::Arc, time::Duration};
+use tackle_box::contracts::payloads::HeadToHeadStats;
 use tokio::time;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+/// 95% 置信区间的正态分位数
+const CI_Z: f64 = 1.96;
+
 struct Repos {
     agent_repo: Arc<AgentRepo>,
     stats_repo: Arc<StatsRepo>,
@@ -17,15 +22,21 @@ struct Repos {
 
 pub struct StatsService {
     repos: Repos,
+    persistence: Persistence,
 }
 
 impl StatsService {
-    pub fn new(agent_repo: Arc<AgentRepo>, stats_repo: Arc<StatsRepo>) -> Self {
+    pub fn new(
+        agent_repo: Arc<AgentRepo>,
+        stats_repo: Arc<StatsRepo>,
+        persistence: Persistence,
+    ) -> Self {
         Self {
             repos: Repos {
                 agent_repo,
                 stats_repo,
             },
+            persistence,
         }
     }
     pub async fn update_stats(&self) -> Result<(), AppError> {
@@ -70,13 +81,34 @@ impl StatsService {
             game_type_ids,
             new_ranks,
         };
-        self.repos.stats_repo.update_stats(data).await?;
+        // 排名计算完成即返回, 写入由持久化队列完成
+        self.persistence.update_stats(data).await?;
 
-        info!("Stats update completed successfully.");
+        info!("Stats update queued.");
         Ok(())
     }
 
-    pub async fn run(&self) -> Result<(), AppError> {
+    /// 按对手列出逐局胜负和平均得分, 平均得分最低 (最被针对) 的对手排在前面
+    pub async fn head_to_head(
+        &self,
+        agent_id: Uuid,
+        opponent_id: Option<Uuid>,
+    ) -> Result<Vec<HeadToHeadStats>, AppError> {
+        let rows = self
+            .repos
+            .stats_repo
+            .head_to_head(agent_id, opponent_id)
+            .await?;
+        let mut stats: Vec<HeadToHeadStats> = rows
+            .chunk_by(|a, b| a.opponent_id == b.opponent_id)
+            .map(head_to_head_stats)
+            .collect();
+        stats.sort_by(|a, b| a.mean_payoff.total_cmp(&b.mean_payoff));
+        Ok(stats)
+    }
+
+    /// 每小时重算一次排名
+    pub async fn run(&self) {
         let mut interval = time::interval(Duration::from_secs(60 * 60));
         interval.tick().await;
         loop {
@@ -90,3 +122,167 @@ impl StatsService {
         }
     }
 }
+
+/// 同一对手的各局, 至少有一局
+fn head_to_head_stats(games: &[HeadToHeadDTO]) -> HeadToHeadStats {
+    let first = &games[0];
+    let payoffs: Vec<(f64, f64)> = games.iter().map(|game| (game.mine, game.theirs)).collect();
+    let summary = summarize_payoffs(&payoffs);
+    HeadToHeadStats {
+        opponent_id: first.opponent_id,
+        opponent_name: first.opponent_name.clone(),
+        owner_name: first.owner_name.clone(),
+        games: summary.games,
+        won: summary.won,
+        lost: summary.lost,
+        drawn: summary.drawn,
+        win_rate: summary.win_rate,
+        mean_payoff: summary.mean_payoff,
+        ci_low: summary.ci.map(|(low, _)| low),
+        ci_high: summary.ci.map(|(_, high)| high),
+        opponent_mean_payoff: summary.opponent_mean_payoff,
+    }
+}
+
+/// 一组 (自己, 对手) 得分的汇总
+#[derive(Debug, PartialEq)]
+struct PayoffSummary {
+    games: i64,
+    won: i64,
+    lost: i64,
+    drawn: i64,
+    win_rate: f64,
+    mean_payoff: f64,
+    /// 自己均值的 95% 置信区间, 少于两局时无法估计方差
+    ci: Option<(f64, f64)>,
+    opponent_mean_payoff: f64,
+}
+
+/// 得分更高者记胜, 置信区间用样本标准差的正态近似 mean ± 1.96·sd/√n
+fn summarize_payoffs(payoffs: &[(f64, f64)]) -> PayoffSummary {
+    let n = payoffs.len();
+    let count = |f: fn(&f64, &f64) -> bool| {
+        payoffs
+            .iter()
+            .filter(|(mine, theirs)| f(mine, theirs))
+            .count() as i64
+    };
+    let won = count(|mine, theirs| mine > theirs);
+    let lost = count(|mine, theirs| mine < theirs);
+    let drawn = count(|mine, theirs| mine == theirs);
+    if n == 0 {
+        return PayoffSummary {
+            games: 0,
+            won,
+            lost,
+            drawn,
+            win_rate: 0.0,
+            mean_payoff: 0.0,
+            ci: None,
+            opponent_mean_payoff: 0.0,
+        };
+    }
+    let mean_payoff = payoffs.iter().map(|(mine, _)| mine).sum::<f64>() / n as f64;
+    let opponent_mean_payoff = payoffs.iter().map(|(_, theirs)| theirs).sum::<f64>() / n as f64;
+    let ci = (n >= 2).then(|| {
+        let variance = payoffs
+            .iter()
+            .map(|(mine, _)| (mine - mean_payoff).powi(2))
+            .sum::<f64>()
+            / (n - 1) as f64;
+        let margin = CI_Z * variance.sqrt() / (n as f64).sqrt();
+        (mean_payoff - margin, mean_payoff + margin)
+    });
+    PayoffSummary {
+        games: n as i64,
+        won,
+        lost,
+        drawn,
+        win_rate: won as f64 / n as f64,
+        mean_payoff,
+        ci,
+        opponent_mean_payoff,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn no_games_has_no_rates_or_interval() {
+        assert_eq!(
+            summarize_payoffs(&[]),
+            PayoffSummary {
+                games: 0,
+                won: 0,
+                lost: 0,
+                drawn: 0,
+                win_rate: 0.0,
+                mean_payoff: 0.0,
+                ci: None,
+                opponent_mean_payoff: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn single_game_has_no_interval() {
+        assert_eq!(
+            summarize_payoffs(&[(1.0, -1.0)]),
+            PayoffSummary {
+                games: 1,
+                won: 1,
+                lost: 0,
+                drawn: 0,
+                win_rate: 1.0,
+                mean_payoff: 1.0,
+                ci: None,
+                opponent_mean_payoff: -1.0,
+            }
+        );
+    }
+
+    #[test]
+    fn known_sample() {
+        // 自己的得分 2, 4, 4, 4, 5, 5, 7, 9: 均值 5, 样本方差 32/7
+        let payoffs = [
+            (2.0, 3.0),
+            (4.0, 4.0),
+            (4.0, 1.0),
+            (4.0, 6.0),
+            (5.0, 5.0),
+            (5.0, 0.0),
+            (7.0, 8.0),
+            (9.0, 5.0),
+        ];
+        let summary = summarize_payoffs(&payoffs);
+        assert_eq!(
+            (summary.games, summary.won, summary.lost, summary.drawn),
+            (8, 3, 3, 2)
+        );
+        assert_close(summary.win_rate, 0.375);
+        assert_close(summary.mean_payoff, 5.0);
+        assert_close(summary.opponent_mean_payoff, 4.0);
+        let margin = 1.96 * (32.0_f64 / 7.0).sqrt() / 8.0_f64.sqrt();
+        let (low, high) = summary.ci.unwrap();
+        assert_close(low, 5.0 - margin);
+        assert_close(high, 5.0 + margin);
+    }
+
+    #[test]
+    fn identical_payoffs_have_a_zero_width_interval() {
+        let summary = summarize_payoffs(&[(0.5, 0.5), (0.5, 0.5)]);
+        assert_eq!(summary.drawn, 2);
+        assert_eq!(summary.ci, Some((0.5, 0.5)));
+    }
+}
@@ -0,0 +1,494 @@
+use std::{collections::HashSet, sync::Arc};
+
+use tackle_box::contracts::{
+    payloads::{
+        GetTournamentResponse, MatchStatus, NewTournamentPayload, TournamentEntrantResponse,
+        TournamentMatchResponse, TournamentStanding,
+    },
+    seed::game_seed,
+};
+use tokio::sync::{
+    mpsc::{Sender, UnboundedReceiver},
+    Mutex,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    api::error::AppError,
+    core::{
+        core::{CoreMessage, MatchSettings},
+        matches::random_seed,
+        pairing::{self, Pairing},
+    },
+    repo::{
+        agents::AgentRepo,
+        error::RepoError,
+        game_type::GameTypeRepo,
+        matches::{MatchRepo, NewMatchDTO},
+        participation::ParticipationRepo,
+        tournaments::{NewTournamentDTO, TournamentRepo},
+    },
+};
+
+struct Repos {
+    tournament_repo: Arc<TournamentRepo>,
+    match_repo: Arc<MatchRepo>,
+    participation_repo: Arc<ParticipationRepo>,
+    agent_repo: Arc<AgentRepo>,
+    gametype_repo: Arc<GameTypeRepo>,
+}
+
+struct Senders {
+    core_tx: Sender<CoreMessage>,
+}
+
+pub struct TournamentService {
+    repos: Repos,
+    senders: Senders,
+    /// 推进轮次时持有, 避免同一轮被重复安排
+    advancing: Mutex<()>,
+}
+
+impl TournamentService {
+    pub fn new(
+        tournament_repo: Arc<TournamentRepo>,
+        match_repo: Arc<MatchRepo>,
+        participation_repo: Arc<ParticipationRepo>,
+        agent_repo: Arc<AgentRepo>,
+        gametype_repo: Arc<GameTypeRepo>,
+        core_tx: Sender<CoreMessage>,
+    ) -> Self {
+        Self {
+            repos: Repos {
+                tournament_repo,
+                match_repo,
+                participation_repo,
+                agent_repo,
+                gametype_repo,
+            },
+            senders: Senders { core_tx },
+            advancing: Mutex::new(()),
+        }
+    }
+
+    pub async fn new_tournament(
+        &self,
+        user_id: Uuid,
+        payload: NewTournamentPayload,
+    ) -> Result<Uuid, AppError> {
+        let NewTournamentPayload {
+            name,
+            game_type_id,
+            format,
+            games_per_match,
+            mode,
+            rounds,
+            max_entrants,
+            password,
+            seed,
+            with_agent_ids,
+        } = payload;
+        if games_per_match <= 0 {
+            return Err(AppError::Validation(
+                "games_per_match must be positive".to_string(),
+            ));
+        }
+        if rounds.is_some_and(|r| r <= 0) {
+            return Err(AppError::Validation("rounds must be positive".to_string()));
+        }
+        if max_entrants.is_some_and(|m| m < 2) {
+            return Err(AppError::Validation(
+                "a tournament needs at least two entrants".to_string(),
+            ));
+        }
+        // 每场比赛由两名参赛者对阵
+        let game_type = self.repos.gametype_repo.get_game_type(game_type_id).await?;
+        if !(game_type.min_slots..=game_type.max_slots).contains(&2) {
+            return Err(AppError::Validation(format!(
+                "game type {} does not support two-player matches",
+                game_type.name
+            )));
+        }
+
+        let tournament = NewTournamentDTO {
+            name,
+            game_type_id,
+            creater_id: user_id,
+            format,
+            games_per_match,
+            mode,
+            total_rounds: rounds,
+            max_entrants,
+            password: password.clone(),
+            seed: seed.unwrap_or_else(random_seed),
+        };
+        let tournament_id = self
+            .repos
+            .tournament_repo
+            .new_tournament(tournament)
+            .await?;
+        self.join_tournament(user_id, tournament_id, with_agent_ids, password)
+            .await?;
+        Ok(tournament_id)
+    }
+
+    pub async fn join_tournament(
+        &self,
+        user_id: Uuid,
+        tournament_id: Uuid,
+        mut agent_ids: Vec<Uuid>,
+        password: Option<String>,
+    ) -> Result<(), AppError> {
+        let Repos {
+            tournament_repo,
+            agent_repo,
+            ..
+        } = &self.repos;
+        let tournament = tournament_repo.get_tournament(tournament_id).await?;
+        if password != tournament_repo.get_password(tournament_id).await? {
+            return Err(AppError::Validation("error password".to_string()));
+        }
+        let mut seen = HashSet::new();
+        agent_ids.retain(|agent_id| seen.insert(*agent_id));
+        for agent_id in &agent_ids {
+            let agent = agent_repo.get_agent(*agent_id).await?;
+            if agent.owner_id != user_id {
+                return Err(AppError::Validation(format!(
+                    "agent {} is not owned by you",
+                    agent.name
+                )));
+            }
+            if agent.game_type_id != tournament.game_type_id {
+                return Err(AppError::Validation(format!(
+                    "agent {} does not play {}",
+                    agent.name, tournament.game_type_name
+                )));
+            }
+        }
+
+        // 人数检查和写入在同一事务中, 锦标赛行被锁住, 并发报名不会超员
+        let mut tx = tournament_repo.get_transaction().await?;
+        let (status, entered) = tournament_repo
+            .lock_entrants(&mut tx, tournament_id)
+            .await?;
+        if status != MatchStatus::Pending {
+            return Err(AppError::Validation(
+                "tournament is not pending, cannot join".to_string(),
+            ));
+        }
+        if agent_ids.iter().any(|agent_id| entered.contains(agent_id)) {
+            return Err(AppError::Validation(
+                "agent has already joined this tournament".to_string(),
+            ));
+        }
+        let entrants = (entered.len() + agent_ids.len()) as i64;
+        if tournament
+            .max_entrants
+            .is_some_and(|max| entrants > max as i64)
+        {
+            return Err(AppError::Validation(
+                "exceeding max entrants for this tournament".to_string(),
+            ));
+        }
+        tournament_repo
+            .insert_entrants(&mut tx, tournament_id, &agent_ids)
+            .await?;
+        tx.commit().await.map_err(RepoError::from)?;
+
+        // 报满后自动开始, 报满之后的报名都会失败, 只有报满的那次会开始
+        if !agent_ids.is_empty()
+            && tournament
+                .max_entrants
+                .is_some_and(|max| entrants >= max as i64)
+        {
+            self.start(tournament_id).await?;
+        }
+        Ok(())
+    }
+
+    /// 由创建者手动开始, 至少需要两名参赛者
+    pub async fn start_tournament(
+        &self,
+        user_id: Uuid,
+        tournament_id: Uuid,
+    ) -> Result<(), AppError> {
+        let tournament = self
+            .repos
+            .tournament_repo
+            .get_tournament(tournament_id)
+            .await?;
+        if tournament.creater_id != user_id {
+            return Err(AppError::Validation(
+                "only the creator can start the tournament".to_string(),
+            ));
+        }
+        if tournament.status != MatchStatus::Pending {
+            return Err(AppError::Validation(
+                "tournament is not pending, cannot start".to_string(),
+            ));
+        }
+        if tournament.entrants < 2 {
+            return Err(AppError::Validation(
+                "a tournament needs at least two entrants".to_string(),
+            ));
+        }
+        self.start(tournament_id).await
+    }
+
+    async fn start(&self, tournament_id: Uuid) -> Result<(), AppError> {
+        let tournament = self
+            .repos
+            .tournament_repo
+            .get_tournament(tournament_id)
+            .await?;
+        let total_rounds = pairing::total_rounds(
+            tournament.format,
+            tournament.entrants as usize,
+            tournament.total_rounds,
+        );
+        self.repos
+            .tournament_repo
+            .start_tournament(tournament_id, total_rounds)
+            .await?;
+        info!("tournament {} started", tournament_id);
+        self.advance(tournament_id).await
+    }
+
+    pub async fn get_tournament(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<GetTournamentResponse, AppError> {
+        Ok(self
+            .repos
+            .tournament_repo
+            .get_tournament(tournament_id)
+            .await?)
+    }
+
+    pub async fn get_tournaments(&self) -> Result<Vec<GetTournamentResponse>, AppError> {
+        Ok(self.repos.tournament_repo.get_tournaments().await?)
+    }
+
+    pub async fn get_entrants(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<Vec<TournamentEntrantResponse>, AppError> {
+        Ok(self
+            .repos
+            .tournament_repo
+            .get_entrants(tournament_id)
+            .await?)
+    }
+
+    pub async fn get_standings(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<Vec<TournamentStanding>, AppError> {
+        let tournament = self
+            .repos
+            .tournament_repo
+            .get_tournament(tournament_id)
+            .await?;
+        let Repos {
+            tournament_repo, ..
+        } = &self.repos;
+        let entrants = tournament_repo.get_entrants(tournament_id).await?;
+        let matches = tournament_repo.get_matches(tournament_id).await?;
+        let byes = tournament_repo.get_byes(tournament_id).await?;
+        let scores = tournament_repo.get_scores(tournament_id).await?;
+        Ok(pairing::standings(
+            tournament.format,
+            &entrants,
+            &matches,
+            &byes,
+            &scores,
+        ))
+    }
+
+    /// 按轮次列出比赛和轮空
+    pub async fn get_matches(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<Vec<TournamentMatchResponse>, AppError> {
+        let Repos {
+            tournament_repo, ..
+        } = &self.repos;
+        let entrants = tournament_repo.get_entrants(tournament_id).await?;
+        let mut rounds: Vec<TournamentMatchResponse> = tournament_repo
+            .get_matches(tournament_id)
+            .await?
+            .into_iter()
+            .map(|m| TournamentMatchResponse {
+                round: m.round,
+                match_id: Some(m.match_id),
+                match_name: Some(m.match_name),
+                status: Some(m.status),
+                agent_ids: m.agent_ids,
+                agent_names: m.agent_names,
+                winner_id: m.winner_id,
+            })
+            .collect();
+        for bye in tournament_repo.get_byes(tournament_id).await? {
+            let agent_name = entrants
+                .iter()
+                .find(|e| e.agent_id == bye.agent_id)
+                .map(|e| e.agent_name.clone())
+                .unwrap_or_default();
+            rounds.push(TournamentMatchResponse {
+                round: bye.round,
+                match_id: None,
+                match_name: None,
+                status: None,
+                agent_ids: vec![bye.agent_id],
+                agent_names: vec![agent_name],
+                winner_id: Some(bye.agent_id),
+            });
+        }
+        // 稳定排序, 同一轮中比赛在前, 轮空在后
+        rounds.sort_by_key(|r| r.round);
+        Ok(rounds)
+    }
+
+    /// 接收 Core 通知的已结束比赛, 推进其所属的锦标赛
+    pub async fn run(&self, mut finished_rx: UnboundedReceiver<Uuid>) {
+        while let Some(match_id) = finished_rx.recv().await {
+            let result = match self.repos.tournament_repo.get_match_round(match_id).await {
+                Ok(Some((tournament_id, _))) => self.advance(tournament_id).await,
+                Ok(None) => Ok(()),
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                warn!(
+                    "failed to advance tournament of match {}: {:?}",
+                    match_id, e
+                );
+            }
+        }
+    }
+
+    /// 当前轮全部结束后安排下一轮, 没有下一轮时结束锦标赛
+    async fn advance(&self, tournament_id: Uuid) -> Result<(), AppError> {
+        let _guard = self.advancing.lock().await;
+        let Repos {
+            tournament_repo, ..
+        } = &self.repos;
+        // 只有轮空的一轮不会有比赛结束的通知, 直接继续安排下一轮
+        loop {
+            let tournament = tournament_repo.get_tournament(tournament_id).await?;
+            if tournament.status != MatchStatus::Running {
+                return Ok(());
+            }
+            let matches = tournament_repo.get_matches(tournament_id).await?;
+            if matches
+                .iter()
+                .any(|m| m.round == tournament.current_round && !pairing::is_finished(&m.status))
+            {
+                return Ok(());
+            }
+            let entrants = tournament_repo.get_entrants(tournament_id).await?;
+            let byes = tournament_repo.get_byes(tournament_id).await?;
+            let scores = tournament_repo.get_scores(tournament_id).await?;
+            let standings =
+                pairing::standings(tournament.format, &entrants, &matches, &byes, &scores);
+            let round = tournament.current_round + 1;
+            match pairing::next_round(
+                tournament.format,
+                round,
+                tournament.total_rounds,
+                &standings,
+                &matches,
+                &byes,
+            ) {
+                Some(pairings) => self.schedule_round(&tournament, round, pairings).await?,
+                None => {
+                    let winner_id = standings.first().map(|s| s.agent_id);
+                    tournament_repo
+                        .finish_tournament(tournament_id, winner_id)
+                        .await?;
+                    info!("tournament {} completed", tournament_id);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// 创建一轮的比赛并交给 Core 开始, 每场比赛的种子由锦标赛种子派生
+    async fn schedule_round(
+        &self,
+        tournament: &GetTournamentResponse,
+        round: i32,
+        pairings: Vec<Pairing>,
+    ) -> Result<(), AppError> {
+        let Repos {
+            tournament_repo,
+            match_repo,
+            participation_repo,
+            gametype_repo,
+            ..
+        } = &self.repos;
+        let game_type = gametype_repo.get_game_type(tournament.game_type_id).await?;
+        let round_seed = game_seed(tournament.seed, round);
+        let mut starts = Vec::new();
+        // 整轮的比赛、轮空和轮次一起写入, 中途失败不会留下半轮; 提交后才开始比赛
+        let mut tx = tournament_repo.get_transaction().await?;
+        for (table, pairing) in pairings.into_iter().enumerate() {
+            match pairing {
+                Pairing::Bye(agent_id) => {
+                    tournament_repo
+                        .insert_bye(&mut tx, tournament.tournament_id, round, agent_id)
+                        .await?;
+                }
+                Pairing::Match(a, b) => {
+                    let seed = game_seed(round_seed, table as i32);
+                    let one_match = NewMatchDTO {
+                        name: format!("{} R{}-{}", tournament.name, round, table + 1),
+                        game_type_id: tournament.game_type_id,
+                        total_games: tournament.games_per_match,
+                        creater_id: tournament.creater_id,
+                        password: None,
+                        seed,
+                        mode: tournament.mode,
+                        tournament_id: Some(tournament.tournament_id),
+                        round: Some(round),
+                    };
+                    let match_id = match_repo.new_match(&mut tx, one_match).await?;
+                    for agent_id in [a, b] {
+                        participation_repo
+                            .insert_participant(&mut tx, match_id, agent_id)
+                            .await?;
+                    }
+                    starts.push((match_id, vec![a, b], seed));
+                }
+            }
+        }
+        tournament_repo
+            .update_current_round(&mut tx, tournament.tournament_id, round)
+            .await?;
+        tx.commit().await.map_err(RepoError::from)?;
+        info!(
+            "tournament {} round {} scheduled with {} matches",
+            tournament.tournament_id,
+            round,
+            starts.len()
+        );
+
+        for (match_id, agent_ids, seed) in starts {
+            self.senders
+                .core_tx
+                .send(CoreMessage::MatchStart {
+                    match_id,
+                    agent_ids,
+                    sponsor: game_type.sponsor.clone(),
+                    game_type: game_type.name.clone(),
+                    settings: MatchSettings {
+                        total_games: tournament.games_per_match,
+                        seed,
+                        mode: tournament.mode,
+                    },
+                })
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
 pub mod contracts;
+pub mod metrics;
+pub mod routing;
+pub mod runner;
 pub mod connection {
     tonic::include_proto!("client");
     tonic::include_proto!("sponsor");
@@ -0,0 +1,396 @@
+//! Prometheus 指标, 由各模块直接更新, 服务端在 /metrics 以文本格式输出
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// Agent 思考时间可能较长
+const ACTION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+pub static HTTP_REQUESTS: Counter = Counter::new(
+    "tacklebox_http_requests_total",
+    "HTTP requests by route and status",
+    &["method", "route", "status"],
+);
+pub static HTTP_DURATION: Histogram = Histogram::new(
+    "tacklebox_http_request_duration_seconds",
+    "HTTP request latency by route",
+    &["method", "route"],
+    LATENCY_BUCKETS,
+);
+pub static GRPC_STREAMS: Gauge = Gauge::new(
+    "tacklebox_grpc_active_streams",
+    "Open client gRPC streams",
+    &["kind"],
+);
+pub static ACTIVE_MATCHES: Gauge = Gauge::new(
+    "tacklebox_active_matches",
+    "Running matches by game type",
+    &["game_type"],
+);
+pub static ACTION_LATENCY: Histogram = Histogram::new(
+    "tacklebox_agent_action_latency_seconds",
+    "Time from sending a state to an agent until its action arrives",
+    &["game_type"],
+    ACTION_BUCKETS,
+);
+pub static SPONSOR_RTT: Histogram = Histogram::new(
+    "tacklebox_sponsor_round_trip_seconds",
+    "Time from a request to the sponsor until its next response",
+    &["sponsor"],
+    LATENCY_BUCKETS,
+);
+pub static SETTLEMENT_DURATION: Histogram = Histogram::new(
+    "tacklebox_settlement_duration_seconds",
+    "Time spent settling a finished match",
+    &[],
+    LATENCY_BUCKETS,
+);
+pub static CORE_QUEUE_DEPTH: Gauge = Gauge::new(
+    "tacklebox_core_queue_depth",
+    "Messages waiting in the core channel",
+    &[],
+);
+pub static PERSISTENCE_QUEUE_DEPTH: Gauge = Gauge::new(
+    "tacklebox_persistence_queue_depth",
+    "Writes waiting in the persistence queue",
+    &[],
+);
+pub static DB_POOL_CONNECTIONS: Gauge = Gauge::new(
+    "tacklebox_db_pool_connections",
+    "Database pool connections by state",
+    &["state"],
+);
+pub static DB_POOL_MAX: Gauge = Gauge::new(
+    "tacklebox_db_pool_max_connections",
+    "Database pool size limit",
+    &[],
+);
+
+type Labels = Vec<String>;
+
+/// 持锁时只读写 BTreeMap, 锁中毒时数据仍然完整, 直接取回, 以免指标拖垮调用方
+fn lock<V>(mutex: &Mutex<V>) -> MutexGuard<'_, V> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn labels(values: &[&str]) -> Labels {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_labels(out: &mut String, names: &[&str], values: &[String], le: Option<&str>) {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if !pairs.is_empty() {
+        let _ = write!(out, "{{{}}}", pairs.join(","));
+    }
+}
+
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    values: Mutex<BTreeMap<Labels, u64>>,
+}
+
+impl Counter {
+    const fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn inc(&self, values: &[&str]) {
+        *lock(&self.values).entry(labels(values)).or_default() += 1;
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "counter");
+        for (values, count) in lock(&self.values).iter() {
+            out.push_str(self.name);
+            write_labels(out, self.label_names, values, None);
+            let _ = writeln!(out, " {}", count);
+        }
+    }
+}
+
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    values: Mutex<BTreeMap<Labels, i64>>,
+}
+
+/// 创建时加一, 释放时减一, 用于统计存活的流和任务
+pub struct GaugeGuard {
+    gauge: &'static Gauge,
+    labels: Labels,
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.gauge.add(self.labels.clone(), -1);
+    }
+}
+
+impl Gauge {
+    const fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn set(&self, values: &[&str], value: i64) {
+        lock(&self.values).insert(labels(values), value);
+    }
+
+    pub fn track(&'static self, values: &[&str]) -> GaugeGuard {
+        let labels = labels(values);
+        self.add(labels.clone(), 1);
+        GaugeGuard {
+            gauge: self,
+            labels,
+        }
+    }
+
+    fn add(&self, labels: Labels, delta: i64) {
+        *lock(&self.values).entry(labels).or_default() += delta;
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "gauge");
+        for (values, value) in lock(&self.values).iter() {
+            out.push_str(self.name);
+            write_labels(out, self.label_names, values, None);
+            let _ = writeln!(out, " {}", value);
+        }
+    }
+}
+
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    buckets: &'static [f64],
+    values: Mutex<BTreeMap<Labels, HistogramData>>,
+}
+
+struct HistogramData {
+    /// 每个桶内的观测数, 输出时再累加
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    const fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+        buckets: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            buckets,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn observe(&self, values: &[&str], elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let mut all = lock(&self.values);
+        let data = all.entry(labels(values)).or_insert_with(|| HistogramData {
+            counts: vec![0; self.buckets.len()],
+            sum: 0.0,
+            count: 0,
+        });
+        if let Some(i) = self.buckets.iter().position(|&bound| secs <= bound) {
+            data.counts[i] += 1;
+        }
+        data.sum += secs;
+        data.count += 1;
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "histogram");
+        for (values, data) in lock(&self.values).iter() {
+            let mut cumulative = 0;
+            for (bound, count) in self.buckets.iter().zip(&data.counts) {
+                cumulative += count;
+                let _ = write!(out, "{}_bucket", self.name);
+                write_labels(out, self.label_names, values, Some(&bound.to_string()));
+                let _ = writeln!(out, " {}", cumulative);
+            }
+            let _ = write!(out, "{}_bucket", self.name);
+            write_labels(out, self.label_names, values, Some("+Inf"));
+            let _ = writeln!(out, " {}", data.count);
+            let _ = write!(out, "{}_sum", self.name);
+            write_labels(out, self.label_names, values, None);
+            let _ = writeln!(out, " {}", data.sum);
+            let _ = write!(out, "{}_count", self.name);
+            write_labels(out, self.label_names, values, None);
+            let _ = writeln!(out, " {}", data.count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    fn render(metric: impl FnOnce(&mut String)) -> String {
+        let mut out = String::new();
+        metric(&mut out);
+        out
+    }
+
+    #[test]
+    fn counter_renders_each_label_set() {
+        let counter = Counter::new("requests_total", "Requests", &["method", "route"]);
+        counter.inc(&["GET", "/a"]);
+        counter.inc(&["GET", "/a"]);
+        counter.inc(&["POST", "/b"]);
+        assert_eq!(
+            render(|out| counter.render(out)),
+            "# HELP requests_total Requests\n\
+             # TYPE requests_total counter\n\
+             requests_total{method=\"GET\",route=\"/a\"} 2\n\
+             requests_total{method=\"POST\",route=\"/b\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let counter = Counter::new("errors_total", "Errors", &["reason"]);
+        counter.inc(&["say \"hi\"\nC:\\tmp"]);
+        assert_eq!(
+            render(|out| counter.render(out)),
+            "# HELP errors_total Errors\n\
+             # TYPE errors_total counter\n\
+             errors_total{reason=\"say \\\"hi\\\"\\nC:\\\\tmp\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn unlabelled_gauge_has_no_braces() {
+        let gauge = Gauge::new("queue_depth", "Queued", &[]);
+        gauge.set(&[], 5);
+        gauge.set(&[], 3);
+        assert_eq!(
+            render(|out| gauge.render(out)),
+            "# HELP queue_depth Queued\n# TYPE queue_depth gauge\nqueue_depth 3\n"
+        );
+    }
+
+    #[test]
+    fn gauge_guards_count_live_values() {
+        static STREAMS: Gauge = Gauge::new("streams", "Open streams", &["kind"]);
+        let first = STREAMS.track(&["player"]);
+        let second = STREAMS.track(&["player"]);
+        let monitor = STREAMS.track(&["monitor"]);
+        assert_eq!(
+            render(|out| STREAMS.render(out)),
+            "# HELP streams Open streams\n\
+             # TYPE streams gauge\n\
+             streams{kind=\"monitor\"} 1\n\
+             streams{kind=\"player\"} 2\n"
+        );
+        drop((first, second, monitor));
+        assert_eq!(
+            render(|out| STREAMS.render(out)),
+            "# HELP streams Open streams\n\
+             # TYPE streams gauge\n\
+             streams{kind=\"monitor\"} 0\n\
+             streams{kind=\"player\"} 0\n"
+        );
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let histogram = Histogram::new("latency_seconds", "Latency", &["game"], &[0.1, 1.0]);
+        // 恰好落在上界的观测计入该桶, 超出所有上界的只计入 +Inf
+        histogram.observe(&["go"], Duration::from_micros(62_500));
+        histogram.observe(&["go"], Duration::from_secs(1));
+        histogram.observe(&["go"], Duration::from_secs(2));
+        assert_eq!(
+            render(|out| histogram.render(out)),
+            "# HELP latency_seconds Latency\n\
+             # TYPE latency_seconds histogram\n\
+             latency_seconds_bucket{game=\"go\",le=\"0.1\"} 1\n\
+             latency_seconds_bucket{game=\"go\",le=\"1\"} 2\n\
+             latency_seconds_bucket{game=\"go\",le=\"+Inf\"} 3\n\
+             latency_seconds_sum{game=\"go\"} 3.0625\n\
+             latency_seconds_count{game=\"go\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn unlabelled_histogram_keeps_only_le() {
+        let histogram = Histogram::new("settle_seconds", "Settle", &[], &[0.5]);
+        histogram.observe(&[], Duration::from_millis(250));
+        assert_eq!(
+            render(|out| histogram.render(out)),
+            "# HELP settle_seconds Settle\n\
+             # TYPE settle_seconds histogram\n\
+             settle_seconds_bucket{le=\"0.5\"} 1\n\
+             settle_seconds_bucket{le=\"+Inf\"} 1\n\
+             settle_seconds_sum 0.25\n\
+             settle_seconds_count 1\n"
+        );
+    }
+
+    #[test]
+    fn poisoned_lock_is_recovered() {
+        let counter = Counter::new("poisoned_total", "Poisoned", &[]);
+        counter.inc(&[]);
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _guard = counter.values.lock().unwrap();
+            panic!("poison the lock");
+        }));
+        assert!(counter.values.is_poisoned());
+        counter.inc(&[]);
+        assert!(render(|out| counter.render(out)).ends_with("poisoned_total 2\n"));
+    }
+}
@@ -2,7 +2,10 @@ pub mod agents;
 pub mod error;
 pub mod game_type;
 pub mod matches;
+pub mod migrations;
 pub mod participation;
+pub mod seasons;
 pub mod stats;
+pub mod tournaments;
 pub mod turns;
 pub mod users;
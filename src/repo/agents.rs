@@ -1,7 +1,9 @@
 use crate::repo::error::RepoError;
 use sqlx::{query, query_as, PgPool, Postgres, Transaction};
 use std::sync::Arc;
-use tackle_box::contracts::payloads::{AgentPolicy, AgentStatus, GetAgentResponse};
+use tackle_box::contracts::payloads::{
+    AgentPolicy, AgentStatus, AgentVersionResponse, GetAgentResponse, VersionOpponentStats,
+};
 use uuid::Uuid;
 
 // #[derive(Serialize, Deserialize)]
@@ -85,8 +87,9 @@ impl AgentRepo {
                 A.version,
                 A.description,
                 A.created_at,            
-                A.played_games,
-                A.won_games,
+                V.played_games,
+                V.won_games,
+                V.drawn_games,
                 A.updated_at,
                 A.status AS "status!:AgentStatus",
                 A.policy AS "policy!:AgentPolicy"
@@ -96,6 +99,8 @@ impl AgentRepo {
                 GAMETYPES AS G ON A.game_type_id = G.game_type_id
             INNER JOIN
                 USERS AS U ON A.owner_id = U.user_id
+            INNER JOIN
+                AGENT_VERSIONS AS V ON V.agent_id = A.agent_id AND V.version = A.version
             WHERE A.owner_id = $1 AND A.status != 'Decommissioned'
             "#,
             user_id,
@@ -120,8 +125,9 @@ impl AgentRepo {
                 A.version,
                 A.description,
                 A.created_at,            
-                A.played_games,
-                A.won_games,
+                V.played_games,
+                V.won_games,
+                V.drawn_games,
                 A.updated_at,
                 A.policy AS "policy!:AgentPolicy",
                 A.status AS "status!:AgentStatus"
@@ -131,6 +137,8 @@ impl AgentRepo {
                 GAMETYPES AS G ON A.game_type_id = G.game_type_id
             INNER JOIN
                 USERS AS U ON A.owner_id = U.user_id
+            INNER JOIN
+                AGENT_VERSIONS AS V ON V.agent_id = A.agent_id AND V.version = A.version
             WHERE A.agent_id = $1 AND A.status != 'Decommissioned'
             "#,
             agent_id,
@@ -144,9 +152,13 @@ impl AgentRepo {
         let mut conn = self.pool.acquire().await?;
         let _ = query!(
             r#"
-            insert into 
-            agents (owner_id, name, game_type_id, version, description, policy) 
-            values ($1, $2, $3, $4, $5, $6)
+            with A as (
+                insert into 
+                agents (owner_id, name, game_type_id, version, description, policy) 
+                values ($1, $2, $3, $4, $5, $6)
+                returning agent_id, version
+            )
+            insert into agent_versions (agent_id, version) select agent_id, version from A
             "#,
             agent.user_id,
             agent.name,
@@ -164,9 +176,14 @@ impl AgentRepo {
         let mut conn = self.pool.acquire().await?;
         let _ = query!(
             r#"
-            update agents 
-            set (name, game_type_id, version, description, policy) = ($1, $2, $3, $4, $5) 
-            where agent_id = $6 and owner_id = $7
+            with A as (
+                update agents 
+                set (name, game_type_id, version, description, policy) = ($1, $2, $3, $4, $5) 
+                where agent_id = $6 and owner_id = $7
+                returning agent_id, version
+            )
+            insert into agent_versions (agent_id, version) select agent_id, version from A
+            on conflict (agent_id, version) do nothing
             "#,
             agent.name,
             agent.game_type_id,
@@ -201,6 +218,27 @@ impl AgentRepo {
         Ok(())
     }
 
+    /// 把一批 Agent 置为同一状态
+    pub async fn update_agent_statuses(
+        &self,
+        agent_ids: &[Uuid],
+        status: AgentStatus,
+    ) -> Result<(), RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let _ = query!(
+            r#"
+            update agents
+            set status = $1
+            where agent_id = any($2)
+            "#,
+            status as AgentStatus,
+            agent_ids
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
     pub async fn delete_agent(&self, agent_id: Uuid, owner_id: Uuid) -> Result<(), RepoError> {
         self.update_agent_status(agent_id, AgentStatus::Decommissioned)
             .await?;
@@ -236,6 +274,7 @@ impl AgentRepo {
     //     Ok(agents)
     // }
 
+    /// agents 上的战绩是所有版本的累计, 只用于展示, 排名使用当前版本的记录
     pub async fn agent_won(
         &self,
         tx: &mut Transaction<'_, Postgres>,
@@ -270,6 +309,128 @@ impl AgentRepo {
         Ok(())
     }
 
+    pub async fn agent_drawn(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        agent_id: Uuid,
+    ) -> Result<(), RepoError> {
+        let _ = query!(
+            r#"
+            update agents set (drawn_games, played_games) =
+            (drawn_games+1, played_games+1) where agent_id = $1
+            "#,
+            agent_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
+    /// 按比赛开始时记录的版本累计战绩, 平局时名次第一的参赛者记平
+    pub async fn record_version_results(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        match_id: Uuid,
+        winner_id: Option<Uuid>,
+        is_draw: bool,
+    ) -> Result<(), RepoError> {
+        let _ = query!(
+            r#"
+            update agent_versions as V set (won_games, drawn_games, played_games) =
+            (
+                V.won_games + (P.agent_id IS NOT DISTINCT FROM $2)::int,
+                V.drawn_games + ($3 AND P.placement IS NOT DISTINCT FROM 1)::int,
+                V.played_games + 1
+            )
+            from participants as P
+            where P.match_id = $1 and P.version_id = V.version_id
+            "#,
+            match_id,
+            winner_id,
+            is_draw,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_versions(
+        &self,
+        agent_id: Uuid,
+    ) -> Result<Vec<AgentVersionResponse>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let versions = query_as!(
+            AgentVersionResponse,
+            r#"
+            SELECT
+                V.version_id,
+                V.version,
+                V.played_games,
+                V.won_games,
+                V.drawn_games,
+                V.created_at,
+                V.version = A.version AS "current!"
+            FROM
+                AGENT_VERSIONS AS V
+            INNER JOIN
+                AGENTS AS A ON V.agent_id = A.agent_id
+            WHERE V.agent_id = $1
+            ORDER BY V.created_at
+            "#,
+            agent_id,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(versions)
+    }
+
+    /// 两个版本分别对每个对手的战绩, 只统计已完成的比赛
+    pub async fn compare_versions(
+        &self,
+        agent_id: Uuid,
+        base: &str,
+        target: &str,
+    ) -> Result<Vec<VersionOpponentStats>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let stats = query_as!(
+            VersionOpponentStats,
+            r#"
+            SELECT
+                O.agent_id AS opponent_id,
+                OA.name AS opponent_name,
+                COUNT(*) FILTER (WHERE V.version = $2) AS "base_played!",
+                COUNT(*) FILTER (WHERE V.version = $2 AND M.winner_id = P.agent_id) AS "base_won!",
+                COUNT(*) FILTER (WHERE V.version = $3) AS "target_played!",
+                COUNT(*) FILTER (WHERE V.version = $3 AND M.winner_id = P.agent_id) AS "target_won!"
+            FROM
+                PARTICIPANTS AS P
+            INNER JOIN
+                AGENT_VERSIONS AS V ON P.version_id = V.version_id
+            INNER JOIN
+                MATCHES AS M ON P.match_id = M.match_id
+            INNER JOIN
+                PARTICIPANTS AS O ON O.match_id = P.match_id AND O.agent_id != P.agent_id
+            INNER JOIN
+                AGENTS AS OA ON O.agent_id = OA.agent_id
+            WHERE
+                P.agent_id = $1
+                AND V.version IN ($2, $3)
+                AND M.status = 'Completed'
+            GROUP BY
+                O.agent_id, OA.name
+            ORDER BY
+                OA.name
+            "#,
+            agent_id,
+            base,
+            target,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(stats)
+    }
+
+    /// 排名只看当前版本的战绩, 更换版本后从该版本的记录重新计算
     pub async fn rankable_agents(&self) -> Result<Vec<GetRankableAgentDTO>, RepoError> {
         let mut conn = self.pool.acquire().await?;
         let agents = query_as!(
@@ -279,9 +440,11 @@ impl AgentRepo {
                 A.agent_id,
                 A.game_type_id,
                 A.owner_id,
-                A.won_games,
-                A.played_games
+                V.won_games,
+                V.played_games
             FROM AGENTS AS A
+            INNER JOIN AGENT_VERSIONS AS V
+                ON V.agent_id = A.agent_id AND V.version = A.version
             WHERE A.status != 'Decommissioned'
             "#
         )
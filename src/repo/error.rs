@@ -8,4 +8,6 @@ pub enum RepoError {
     AlreadyExists,
     #[error("Database query failed: {0}")]
     TechnicalError(#[from] sqlx::Error),
+    #[error("Migration failed: {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
 }
@@ -1,6 +1,6 @@
 use sqlx::{query_as, PgPool};
 use std::sync::Arc;
-use tackle_box::contracts::payloads::GetGameTypeResponse;
+use tackle_box::contracts::payloads::{GetGameTypeResponse, SettlementRule};
 use uuid::Uuid;
 
 use crate::repo::error::RepoError;
@@ -33,7 +33,9 @@ impl GameTypeRepo {
             sponsor,
             description,
             min_slots,
-            max_slots
+            max_slots,
+            settlement_rule AS "settlement_rule!: SettlementRule",
+            allow_draws
             FROM gametypes
             "#
         )
@@ -56,7 +58,9 @@ impl GameTypeRepo {
             sponsor,
             description,
             min_slots,
-            max_slots
+            max_slots,
+            settlement_rule AS "settlement_rule!: SettlementRule",
+            allow_draws
             FROM gametypes
             WHERE game_type_id = $1
             "#,
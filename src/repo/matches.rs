@@ -1,15 +1,38 @@
 use crate::repo::error::RepoError;
 use sqlx::{query, query_as, query_scalar, PgPool, Postgres, Transaction};
 use std::sync::Arc;
-use tackle_box::contracts::payloads::{GetMatchResponse, GetOnlineMatchResponse, MatchStatus};
+use tackle_box::contracts::payloads::{
+    GetMatchResponse, GetOnlineMatchResponse, MatchMode, MatchStatus, SettlementRule,
+};
 use uuid::Uuid;
 
+/// 比赛所属游戏类型的结算规则
+pub struct SettlementRuleDTO {
+    pub settlement_rule: SettlementRule,
+    pub allow_draws: bool,
+}
+
+/// 结算时写入参赛记录的结果, 分数变化只在赛季计分时由 `SeasonRepo::rate_match` 写入
+pub struct ParticipantResultDTO {
+    pub agent_id: Uuid,
+    pub placement: i32,
+    pub final_score: f32,
+    pub games_won: i32,
+    /// 每局所在的座位
+    pub seats: Vec<i32>,
+}
+
 pub struct NewMatchDTO {
     pub name: String,
     pub game_type_id: Uuid,
     pub total_games: i32,
     pub creater_id: Uuid,
     pub password: Option<String>,
+    pub seed: i64,
+    pub mode: MatchMode,
+    /// 锦标赛中的比赛记录所属的锦标赛和轮次
+    pub tournament_id: Option<Uuid>,
+    pub round: Option<i32>,
 }
 
 // #[derive(FromRow, Serialize)]
@@ -54,12 +77,15 @@ impl MatchRepo {
         Ok(self.pool.begin().await?)
     }
 
-    pub async fn new_match(&self, one_match: NewMatchDTO) -> Result<Uuid, RepoError> {
-        let mut conn = self.pool.acquire().await?;
+    pub async fn new_match(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        one_match: NewMatchDTO,
+    ) -> Result<Uuid, RepoError> {
         let match_id = query_scalar!(
             r#"
-            insert into matches (name, game_type_id, total_games, creater_id, status, password)
-            values ($1, $2, $3, $4, $5::match_status, $6) returning match_id;
+            insert into matches (name, game_type_id, total_games, creater_id, status, password, seed, mode, tournament_id, round)
+            values ($1, $2, $3, $4, $5::match_status, $6, $7, $8::match_mode, $9, $10) returning match_id;
             "#,
             one_match.name,
             one_match.game_type_id,
@@ -67,8 +93,12 @@ impl MatchRepo {
             one_match.creater_id,
             MatchStatus::Pending as MatchStatus,
             one_match.password,
+            one_match.seed,
+            one_match.mode as MatchMode,
+            one_match.tournament_id,
+            one_match.round,
         )
-        .fetch_one(&mut *conn)
+        .fetch_one(tx.as_mut())
         .await?;
         Ok(match_id)
     }
@@ -104,9 +134,12 @@ impl MatchRepo {
                 M.total_games,
                 M.winner_id,
                 WA.name AS "winner_agent_name: _",
+                M.is_draw,
                 M.start_time,
                 M.end_time,
                 M.status as "status!:MatchStatus",
+                M.seed,
+                M.mode as "mode!: MatchMode",
                 M.password,
                 G.min_slots,
                 G.max_slots
@@ -171,9 +204,12 @@ impl MatchRepo {
                 M.total_games,
                 M.winner_id,
                 WA.name AS "winner_agent_name: _",
+                M.is_draw,
                 M.start_time,
                 M.end_time,
                 M.status as "status!:MatchStatus",
+                M.seed,
+                M.mode as "mode!: MatchMode",
                 M.password,
                 G.min_slots,
                 G.max_slots
@@ -214,9 +250,12 @@ impl MatchRepo {
                 M.total_games,
                 M.winner_id,
                 WA.name AS "winner_agent_name: _", 
+                M.is_draw,
                 M.start_time,
                 M.end_time,
                 M.status AS "status!:MatchStatus", 
+                M.seed,
+                M.mode as "mode!: MatchMode",
                 M.password,
                 G.min_slots,
                 G.max_slots
@@ -320,6 +359,68 @@ impl MatchRepo {
         Ok(())
     }
 
+    /// 比赛开始时记下每个参赛者当前的版本
+    pub async fn record_versions(&self, match_id: Uuid) -> Result<(), RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let _ = query!(
+            r#"
+            update participants as P set version_id = V.version_id
+            from agents as A, agent_versions as V
+            where P.match_id = $1 and A.agent_id = P.agent_id
+                and V.agent_id = A.agent_id and V.version = A.version
+            "#,
+            match_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_settlement_rule(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        match_id: Uuid,
+    ) -> Result<SettlementRuleDTO, RepoError> {
+        let rule = query_as!(
+            SettlementRuleDTO,
+            r#"
+            SELECT
+                G.settlement_rule AS "settlement_rule!: SettlementRule",
+                G.allow_draws
+            FROM matches AS M
+            INNER JOIN gametypes AS G ON M.game_type_id = G.game_type_id
+            WHERE M.match_id = $1
+            "#,
+            match_id,
+        )
+        .fetch_one(tx.as_mut())
+        .await?;
+        Ok(rule)
+    }
+
+    pub async fn record_result(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        match_id: Uuid,
+        result: ParticipantResultDTO,
+    ) -> Result<(), RepoError> {
+        let _ = query!(
+            r#"
+            update participants set (placement, final_score, games_won, seats) = ($3, $4, $5, $6)
+            where match_id = $1 and agent_id = $2
+            "#,
+            match_id,
+            result.agent_id,
+            result.placement,
+            result.final_score,
+            result.games_won,
+            &result.seats,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
     pub async fn delete_match(&self, match_id: Uuid, creater_id: Uuid) -> Result<(), RepoError> {
         let mut conn = self.pool.acquire().await?;
         let _ = query!(
@@ -334,18 +435,39 @@ impl MatchRepo {
         Ok(())
     }
 
+    pub async fn cancel_match(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        match_id: Uuid,
+    ) -> Result<(), RepoError> {
+        let _ = query!(
+            r#"
+            update matches set status = $1, end_time = now()
+            where match_id = $2
+            "#,
+            MatchStatus::Cancelled as MatchStatus,
+            match_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
     pub async fn update_match_final_status(
         &self,
         tx: &mut Transaction<'_, Postgres>,
         match_id: Uuid,
         winner_id: Option<Uuid>,
+        is_draw: bool,
     ) -> Result<(), RepoError> {
         let _ = query!(
             r#"
-            update matches set status = $1, winner_id = $2 where match_id = $3
+            update matches set status = $1, winner_id = $2, is_draw = $3, end_time = now()
+            where match_id = $4
             "#,
             MatchStatus::Completed as MatchStatus,
             winner_id,
+            is_draw,
             match_id
         )
         .execute(tx.as_mut())
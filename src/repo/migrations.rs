@@ -0,0 +1,77 @@
+//! 编译时内嵌的数据库迁移, 迁移文件位于仓库根目录的 migrations/
+
+use std::sync::Arc;
+
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    PgPool,
+};
+
+use crate::repo::error::RepoError;
+
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+    /// 已应用的迁移与内嵌文件内容不一致
+    pub modified: bool,
+}
+
+pub struct MigrationRepo {
+    pub pool: Arc<PgPool>,
+}
+
+impl MigrationRepo {
+    /// 执行所有未应用的迁移
+    pub async fn up(&self) -> Result<(), RepoError> {
+        MIGRATOR.run(&*self.pool).await?;
+        Ok(())
+    }
+
+    /// 回滚版本大于 target 的迁移, 未指定时只回滚最近应用的一个, 返回回滚后的版本
+    pub async fn down(&self, target: Option<i64>) -> Result<i64, RepoError> {
+        let target = match target {
+            Some(target) => target,
+            None => {
+                let mut applied: Vec<i64> = self
+                    .status()
+                    .await?
+                    .into_iter()
+                    .filter(|m| m.applied)
+                    .map(|m| m.version)
+                    .collect();
+                applied.pop();
+                applied.pop().unwrap_or(0)
+            }
+        };
+        MIGRATOR.undo(&*self.pool, target).await?;
+        Ok(target)
+    }
+
+    pub async fn status(&self) -> Result<Vec<MigrationStatus>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        conn.ensure_migrations_table().await?;
+        let applied = conn.list_applied_migrations().await?;
+        let status = MIGRATOR
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .map(|m| {
+                let record = applied.iter().find(|a| a.version == m.version);
+                MigrationStatus {
+                    version: m.version,
+                    description: m.description.to_string(),
+                    applied: record.is_some(),
+                    modified: record.is_some_and(|a| a.checksum != m.checksum),
+                }
+            })
+            .collect();
+        Ok(status)
+    }
+
+    /// 尚未应用的迁移数量
+    pub async fn pending(&self) -> Result<usize, RepoError> {
+        Ok(self.status().await?.iter().filter(|m| !m.applied).count())
+    }
+}
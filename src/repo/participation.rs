@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use sqlx::{query, query_as, query_scalar, PgPool};
+use sqlx::{query, query_as, query_scalar, PgPool, Postgres, Transaction};
 use tackle_box::contracts::payloads::GetParticipantsResponse;
 use uuid::Uuid;
 
@@ -20,18 +20,18 @@ pub struct ParticipationRepo {
 impl ParticipationRepo {
     pub async fn insert_participant(
         &self,
+        tx: &mut Transaction<'_, Postgres>,
         match_id: Uuid,
         agent_id: Uuid,
     ) -> Result<(), RepoError> {
-        let mut conn = self.pool.acquire().await?;
         let _ = query!(
             r#"
-            insert into participants (match_id, agent_id) values ($1, $2)
+            insert into participants (match_id, agent_id, joined_at) values ($1, $2, clock_timestamp())
             "#,
             match_id,
             agent_id,
         )
-        .execute(&mut *conn)
+        .execute(tx.as_mut())
         .await?;
         Ok(())
     }
@@ -43,17 +43,25 @@ impl ParticipationRepo {
         let mut conn = self.pool.acquire().await?;
         let participants = query_as!(
             GetParticipantsResponse,
-            r"
+            r#"
             SELECT 
                 P.match_id,
                 M.name as match_name,
                 P.agent_id,
-                A.name as agent_name
+                A.name as agent_name,
+                V.version as "version?",
+                P.placement,
+                P.final_score,
+                P.games_won,
+                P.seats,
+                P.rating_change
             FROM participants P
             JOIN matches M ON P.match_id = M.match_id
             JOIN agents A ON P.agent_id = A.agent_id
+            LEFT JOIN agent_versions V ON P.version_id = V.version_id
             WHERE P.match_id = $1
-            ",
+            ORDER BY P.placement NULLS LAST, P.joined_at
+            "#,
             match_id
         )
         .fetch_all(&mut *conn)
@@ -61,6 +69,20 @@ impl ParticipationRepo {
         Ok(participants)
     }
 
+    /// 按加入顺序返回参赛的 Agent, 即开始比赛时的座位顺序
+    pub async fn get_agent_ids(&self, match_id: Uuid) -> Result<Vec<Uuid>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let agent_ids = query_scalar!(
+            r#"
+            select agent_id from participants where match_id = $1 order by joined_at
+            "#,
+            match_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(agent_ids)
+    }
+
     pub async fn count_participants(&self, match_id: Uuid) -> Result<i32, RepoError> {
         let mut conn = self.pool.acquire().await?;
         let num = query_scalar!(
@@ -0,0 +1,479 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, PgPool, Postgres, Transaction};
+use tackle_box::contracts::payloads::{GetSeasonResponse, MatchStatus, SeasonStanding};
+use uuid::Uuid;
+
+use crate::repo::error::RepoError;
+
+pub struct NewSeasonDTO {
+    pub name: String,
+    pub game_type_id: Uuid,
+    pub creater_id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub carry_over: f64,
+    pub recurring: bool,
+}
+
+pub struct SeasonDTO {
+    pub season_id: Uuid,
+    pub name: String,
+    pub game_type_id: Uuid,
+    pub creater_id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub carry_over: f64,
+    pub recurring: bool,
+}
+
+/// 待计分的比赛, 参赛者按 agent_id 排序
+pub struct UnratedMatchDTO {
+    pub match_id: Uuid,
+    pub winner_id: Option<Uuid>,
+    pub is_draw: bool,
+    pub agent_ids: Vec<Uuid>,
+    /// 与 agent_ids 对应的名次, 没有记录名次的旧比赛由胜者推出
+    pub placements: Vec<i32>,
+}
+
+#[derive(Clone)]
+pub struct SeasonRatingDTO {
+    pub agent_id: Uuid,
+    pub rating: f64,
+    pub played: i32,
+    pub won: i32,
+    pub drawn: i32,
+    pub lost: i32,
+}
+
+pub struct SeasonRepo {
+    pub pool: Arc<PgPool>,
+}
+
+impl SeasonRepo {
+    pub async fn new_season(&self, season: NewSeasonDTO) -> Result<Uuid, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let season_id = query_scalar!(
+            r#"
+            insert into seasons (name, game_type_id, creater_id, start_time, end_time, carry_over, recurring)
+            values ($1, $2, $3, $4, $5, $6, $7) returning season_id;
+            "#,
+            season.name,
+            season.game_type_id,
+            season.creater_id,
+            season.start_time,
+            season.end_time,
+            season.carry_over,
+            season.recurring,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(season_id)
+    }
+
+    pub async fn get_season(&self, season_id: Uuid) -> Result<GetSeasonResponse, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let season = query_as!(
+            GetSeasonResponse,
+            r#"
+            SELECT
+                S.season_id,
+                S.name,
+                S.game_type_id,
+                G.name AS game_type_name,
+                S.creater_id,
+                U.username AS creater_name,
+                S.start_time,
+                S.end_time,
+                S.status AS "status!: MatchStatus",
+                S.carry_over,
+                S.recurring,
+                S.previous_id,
+                (SELECT COUNT(*) FROM season_matches SM WHERE SM.season_id = S.season_id) AS "matches!"
+            FROM
+                seasons AS S
+            INNER JOIN
+                gametypes AS G ON S.game_type_id = G.game_type_id
+            INNER JOIN
+                users AS U ON S.creater_id = U.user_id
+            WHERE
+                S.season_id = $1
+            "#,
+            season_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(season)
+    }
+
+    pub async fn get_seasons(&self) -> Result<Vec<GetSeasonResponse>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let seasons = query_as!(
+            GetSeasonResponse,
+            r#"
+            SELECT
+                S.season_id,
+                S.name,
+                S.game_type_id,
+                G.name AS game_type_name,
+                S.creater_id,
+                U.username AS creater_name,
+                S.start_time,
+                S.end_time,
+                S.status AS "status!: MatchStatus",
+                S.carry_over,
+                S.recurring,
+                S.previous_id,
+                (SELECT COUNT(*) FROM season_matches SM WHERE SM.season_id = S.season_id) AS "matches!"
+            FROM
+                seasons AS S
+            INNER JOIN
+                gametypes AS G ON S.game_type_id = G.game_type_id
+            INNER JOIN
+                users AS U ON S.creater_id = U.user_id
+            ORDER BY
+                S.start_time DESC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(seasons)
+    }
+
+    /// 同一游戏类型下与给定时间段重叠且未归档的赛季数
+    pub async fn count_overlapping(
+        &self,
+        game_type_id: Uuid,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<i64, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let count = query_scalar!(
+            r#"
+            select count(*) as "count!" from seasons
+            where game_type_id = $1 and status != $2 and start_time < $4 and end_time > $3
+            "#,
+            game_type_id,
+            MatchStatus::Completed as MatchStatus,
+            start_time,
+            end_time,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(count)
+    }
+
+    pub async fn get_seasons_by_status(
+        &self,
+        status: MatchStatus,
+    ) -> Result<Vec<SeasonDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let seasons = query_as!(
+            SeasonDTO,
+            r#"
+            select season_id, name, game_type_id, creater_id, start_time, end_time, carry_over, recurring
+            from seasons where status = $1 order by start_time
+            "#,
+            status as MatchStatus,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(seasons)
+    }
+
+    /// 同一游戏类型下在给定时间之前结束的最近一个已归档赛季
+    pub async fn get_previous_season(
+        &self,
+        game_type_id: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<Option<SeasonDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let season = query_as!(
+            SeasonDTO,
+            r#"
+            select season_id, name, game_type_id, creater_id, start_time, end_time, carry_over, recurring
+            from seasons where game_type_id = $1 and status = $2 and end_time <= $3
+            order by end_time desc limit 1
+            "#,
+            game_type_id,
+            MatchStatus::Completed as MatchStatus,
+            before,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(season)
+    }
+
+    pub async fn open_season(
+        &self,
+        season_id: Uuid,
+        previous_id: Option<Uuid>,
+    ) -> Result<(), RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let _ = query!(
+            "update seasons set status = $1, previous_id = $2 where season_id = $3",
+            MatchStatus::Running as MatchStatus,
+            previous_id,
+            season_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    /// 赛季时间段内结束且尚未计分的比赛, 按结束时间排序
+    pub async fn get_unrated_matches(
+        &self,
+        season: &SeasonDTO,
+    ) -> Result<Vec<UnratedMatchDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let matches = query_as!(
+            UnratedMatchDTO,
+            r#"
+            SELECT
+                M.match_id,
+                M.winner_id,
+                M.is_draw,
+                ARRAY(
+                    SELECT P.agent_id FROM participants P
+                    WHERE P.match_id = M.match_id ORDER BY P.agent_id
+                ) AS "agent_ids!",
+                ARRAY(
+                    SELECT COALESCE(
+                        P.placement,
+                        CASE WHEN M.winner_id IS NULL OR M.winner_id = P.agent_id THEN 1 ELSE 2 END
+                    )
+                    FROM participants P
+                    WHERE P.match_id = M.match_id ORDER BY P.agent_id
+                ) AS "placements!"
+            FROM
+                matches AS M
+            WHERE
+                M.game_type_id = $1
+                AND M.status = $2
+                AND M.end_time >= $3
+                AND M.end_time < $4
+                AND NOT EXISTS (
+                    SELECT 1 FROM season_matches SM
+                    WHERE SM.season_id = $5 AND SM.match_id = M.match_id
+                )
+            ORDER BY
+                M.end_time
+            "#,
+            season.game_type_id,
+            MatchStatus::Completed as MatchStatus,
+            season.start_time,
+            season.end_time,
+            season.season_id,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(matches)
+    }
+
+    pub async fn get_ratings(&self, season_id: Uuid) -> Result<Vec<SeasonRatingDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let ratings = query_as!(
+            SeasonRatingDTO,
+            r#"
+            select agent_id, rating, played, won, drawn, lost
+            from season_ratings where season_id = $1
+            "#,
+            season_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(ratings)
+    }
+
+    /// 写入一场比赛后的分数和参赛者的分数变化并记为已计分, 在同一事务中
+    pub async fn rate_match(
+        &self,
+        season_id: Uuid,
+        match_id: Uuid,
+        ratings: Vec<SeasonRatingDTO>,
+        changes: Vec<f64>,
+    ) -> Result<(), RepoError> {
+        let mut tx = self.pool.begin().await?;
+        let _ = query!(
+            "insert into season_matches (season_id, match_id) values ($1, $2)",
+            season_id,
+            match_id,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        let agent_ids: Vec<Uuid> = ratings.iter().map(|r| r.agent_id).collect();
+        let _ = query!(
+            r#"
+            update participants as P set rating_change = R.change
+            from unnest($2::uuid[], $3::float8[]) as R(agent_id, change)
+            where P.match_id = $1 and P.agent_id = R.agent_id
+            "#,
+            match_id,
+            &agent_ids,
+            &changes,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        self.upsert_ratings(&mut tx, season_id, ratings).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 用上赛季的分数初始化新赛季
+    pub async fn seed_ratings(
+        &self,
+        season_id: Uuid,
+        ratings: Vec<SeasonRatingDTO>,
+    ) -> Result<(), RepoError> {
+        let mut tx = self.pool.begin().await?;
+        self.upsert_ratings(&mut tx, season_id, ratings).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn upsert_ratings(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        season_id: Uuid,
+        ratings: Vec<SeasonRatingDTO>,
+    ) -> Result<(), RepoError> {
+        let mut agent_ids = Vec::with_capacity(ratings.len());
+        let mut values = Vec::with_capacity(ratings.len());
+        let mut played = Vec::with_capacity(ratings.len());
+        let mut won = Vec::with_capacity(ratings.len());
+        let mut drawn = Vec::with_capacity(ratings.len());
+        let mut lost = Vec::with_capacity(ratings.len());
+        for r in ratings {
+            agent_ids.push(r.agent_id);
+            values.push(r.rating);
+            played.push(r.played);
+            won.push(r.won);
+            drawn.push(r.drawn);
+            lost.push(r.lost);
+        }
+        let _ = query!(
+            r#"
+            INSERT INTO season_ratings (season_id, agent_id, rating, played, won, drawn, lost, updated_time)
+            SELECT $1, R.agent_id, R.rating, R.played, R.won, R.drawn, R.lost, now()
+            FROM unnest($2::uuid[], $3::float8[], $4::int[], $5::int[], $6::int[], $7::int[])
+                AS R(agent_id, rating, played, won, drawn, lost)
+            ON CONFLICT (season_id, agent_id) DO UPDATE SET
+                rating = EXCLUDED.rating,
+                played = EXCLUDED.played,
+                won = EXCLUDED.won,
+                drawn = EXCLUDED.drawn,
+                lost = EXCLUDED.lost,
+                updated_time = EXCLUDED.updated_time
+            "#,
+            season_id,
+            &agent_ids,
+            &values,
+            &played,
+            &won,
+            &drawn,
+            &lost,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
+    /// 按当前分数实时排名, 只包含赛季内下过场的 Agent
+    pub async fn get_live_standings(
+        &self,
+        season_id: Uuid,
+    ) -> Result<Vec<SeasonStanding>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let standings = query_as!(
+            SeasonStanding,
+            r#"
+            SELECT
+                ROW_NUMBER() OVER (ORDER BY R.rating DESC, R.played DESC)::int AS "rank!",
+                R.agent_id,
+                A.name AS agent_name,
+                U.username AS owner_name,
+                R.rating,
+                R.played,
+                R.won,
+                R.drawn,
+                R.lost
+            FROM season_ratings R
+            JOIN agents A ON R.agent_id = A.agent_id
+            JOIN users U ON A.owner_id = U.user_id
+            WHERE R.season_id = $1 AND R.played > 0
+            ORDER BY R.rating DESC, R.played DESC
+            "#,
+            season_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(standings)
+    }
+
+    pub async fn get_archived_standings(
+        &self,
+        season_id: Uuid,
+    ) -> Result<Vec<SeasonStanding>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let standings = query_as!(
+            SeasonStanding,
+            r#"
+            SELECT
+                S.rank,
+                S.agent_id,
+                A.name AS agent_name,
+                U.username AS owner_name,
+                S.rating,
+                S.played,
+                S.won,
+                S.drawn,
+                S.lost
+            FROM season_standings S
+            JOIN agents A ON S.agent_id = A.agent_id
+            JOIN users U ON A.owner_id = U.user_id
+            WHERE S.season_id = $1
+            ORDER BY S.rank
+            "#,
+            season_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(standings)
+    }
+
+    /// 归档最终排名并结束赛季
+    pub async fn close_season(&self, season_id: Uuid) -> Result<(), RepoError> {
+        let mut tx = self.pool.begin().await?;
+        let _ = query!(
+            r#"
+            INSERT INTO season_standings (season_id, agent_id, rank, rating, played, won, drawn, lost)
+            SELECT
+                season_id,
+                agent_id,
+                ROW_NUMBER() OVER (ORDER BY rating DESC, played DESC),
+                rating,
+                played,
+                won,
+                drawn,
+                lost
+            FROM season_ratings
+            WHERE season_id = $1 AND played > 0
+            "#,
+            season_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+        let _ = query!(
+            "update seasons set status = $1 where season_id = $2",
+            MatchStatus::Completed as MatchStatus,
+            season_id,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
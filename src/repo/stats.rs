@@ -13,6 +13,17 @@ pub struct UpdateStatsDTO {
     pub new_ranks: Vec<i32>,
 }
 
+/// 与一个对手逐局比较的原始汇总
+pub struct HeadToHeadDTO {
+    pub opponent_id: Uuid,
+    pub opponent_name: String,
+    pub owner_name: String,
+    /// 本局自己的得分
+    pub mine: f64,
+    /// 本局对手的得分
+    pub theirs: f64,
+}
+
 pub struct StatsRepo {
     pub pool: Arc<PgPool>,
 }
@@ -48,7 +59,7 @@ impl StatsRepo {
         Ok(stats)
     }
 
-    pub async fn update_stats(&self, data: UpdateStatsDTO) -> Result<(), RepoError> {
+    pub async fn update_stats(&self, data: &UpdateStatsDTO) -> Result<(), RepoError> {
         let mut conn = self.pool.acquire().await?;
         let UpdateStatsDTO {
             agent_ids,
@@ -89,13 +100,54 @@ impl StatsRepo {
             WHERE 
                 CR.agent_id NOT IN (SELECT agent_id FROM UpdateExisting);
             "#,
-            &agent_ids,
-            &game_type_ids,
-            &new_ranks,
+            agent_ids,
+            game_type_ids,
+            new_ranks,
             &updated_times
         )
         .execute(&mut *conn)
         .await?;
         Ok(())
     }
+
+    /// 列出与各对手同桌的每一局中双方的得分, 同一对手的局相邻
+    pub async fn head_to_head(
+        &self,
+        agent_id: Uuid,
+        opponent_id: Option<Uuid>,
+    ) -> Result<Vec<HeadToHeadDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let games = query_as!(
+            HeadToHeadDTO,
+            r#"
+            SELECT
+                O.agent_id AS "opponent_id!",
+                A.name AS opponent_name,
+                U.username AS owner_name,
+                (T.score_deltas->>(P.agent_id::text))::float8 AS "mine!",
+                (T.score_deltas->>(O.agent_id::text))::float8 AS "theirs!"
+            FROM
+                TURNS AS T
+            INNER JOIN
+                PARTICIPANTS AS P ON P.match_id = T.match_id AND P.agent_id = $1
+            INNER JOIN
+                PARTICIPANTS AS O ON O.match_id = T.match_id AND O.agent_id != P.agent_id
+            INNER JOIN
+                AGENTS AS A ON A.agent_id = O.agent_id
+            INNER JOIN
+                USERS AS U ON A.owner_id = U.user_id
+            WHERE
+                ($2::uuid IS NULL OR O.agent_id = $2)
+                AND T.score_deltas ? (P.agent_id::text)
+                AND T.score_deltas ? (O.agent_id::text)
+            ORDER BY
+                O.agent_id, T.match_id, T.i_turn
+            "#,
+            agent_id,
+            opponent_id,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(games)
+    }
 }
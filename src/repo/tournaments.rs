@@ -0,0 +1,413 @@
+use std::sync::Arc;
+
+use sqlx::{query, query_as, query_scalar, PgPool, Postgres, Transaction};
+use tackle_box::contracts::payloads::{
+    GetTournamentResponse, MatchMode, MatchStatus, TournamentEntrantResponse, TournamentFormat,
+};
+use uuid::Uuid;
+
+use crate::repo::error::RepoError;
+
+pub struct NewTournamentDTO {
+    pub name: String,
+    pub game_type_id: Uuid,
+    pub creater_id: Uuid,
+    pub format: TournamentFormat,
+    pub games_per_match: i32,
+    pub mode: MatchMode,
+    pub total_rounds: Option<i32>,
+    pub max_entrants: Option<i32>,
+    pub password: Option<String>,
+    pub seed: i64,
+}
+
+/// 锦标赛中的一场比赛及其参赛者, 参赛者按 agent_id 排序
+pub struct TournamentMatchDTO {
+    pub match_id: Uuid,
+    pub match_name: String,
+    pub round: i32,
+    pub status: MatchStatus,
+    pub winner_id: Option<Uuid>,
+    pub agent_ids: Vec<Uuid>,
+    pub agent_names: Vec<String>,
+}
+
+pub struct TournamentByeDTO {
+    pub round: i32,
+    pub agent_id: Uuid,
+}
+
+pub struct TournamentScoreDTO {
+    pub agent_id: Uuid,
+    pub score: f64,
+}
+
+pub struct TournamentRepo {
+    pub pool: Arc<PgPool>,
+}
+
+impl TournamentRepo {
+    pub async fn new_tournament(&self, tournament: NewTournamentDTO) -> Result<Uuid, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let tournament_id = query_scalar!(
+            r#"
+            insert into tournaments (name, game_type_id, creater_id, format, games_per_match, mode, total_rounds, max_entrants, password, seed)
+            values ($1, $2, $3, $4::tournament_format, $5, $6::match_mode, $7, $8, $9, $10) returning tournament_id;
+            "#,
+            tournament.name,
+            tournament.game_type_id,
+            tournament.creater_id,
+            tournament.format as TournamentFormat,
+            tournament.games_per_match,
+            tournament.mode as MatchMode,
+            tournament.total_rounds,
+            tournament.max_entrants,
+            tournament.password,
+            tournament.seed,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(tournament_id)
+    }
+
+    pub async fn get_tournament(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<GetTournamentResponse, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let tournament = query_as!(
+            GetTournamentResponse,
+            r#"
+            SELECT
+                T.tournament_id,
+                T.name,
+                T.game_type_id,
+                G.name AS game_type_name,
+                T.creater_id,
+                U.username AS creater_name,
+                T.format AS "format!: TournamentFormat",
+                T.games_per_match,
+                T.mode AS "mode!: MatchMode",
+                T.total_rounds,
+                T.current_round,
+                T.max_entrants,
+                (SELECT COUNT(*) FROM entrants E WHERE E.tournament_id = T.tournament_id) AS "entrants!",
+                T.password IS NOT NULL AS "with_password!",
+                T.seed,
+                T.status AS "status!: MatchStatus",
+                T.winner_id,
+                WA.name AS "winner_agent_name: _",
+                T.created_at,
+                T.end_time
+            FROM
+                tournaments AS T
+            INNER JOIN
+                gametypes AS G ON T.game_type_id = G.game_type_id
+            INNER JOIN
+                users AS U ON T.creater_id = U.user_id
+            LEFT JOIN
+                agents AS WA ON T.winner_id = WA.agent_id
+            WHERE
+                T.tournament_id = $1
+            "#,
+            tournament_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(tournament)
+    }
+
+    pub async fn get_tournaments(&self) -> Result<Vec<GetTournamentResponse>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let tournaments = query_as!(
+            GetTournamentResponse,
+            r#"
+            SELECT
+                T.tournament_id,
+                T.name,
+                T.game_type_id,
+                G.name AS game_type_name,
+                T.creater_id,
+                U.username AS creater_name,
+                T.format AS "format!: TournamentFormat",
+                T.games_per_match,
+                T.mode AS "mode!: MatchMode",
+                T.total_rounds,
+                T.current_round,
+                T.max_entrants,
+                (SELECT COUNT(*) FROM entrants E WHERE E.tournament_id = T.tournament_id) AS "entrants!",
+                T.password IS NOT NULL AS "with_password!",
+                T.seed,
+                T.status AS "status!: MatchStatus",
+                T.winner_id,
+                WA.name AS "winner_agent_name: _",
+                T.created_at,
+                T.end_time
+            FROM
+                tournaments AS T
+            INNER JOIN
+                gametypes AS G ON T.game_type_id = G.game_type_id
+            INNER JOIN
+                users AS U ON T.creater_id = U.user_id
+            LEFT JOIN
+                agents AS WA ON T.winner_id = WA.agent_id
+            ORDER BY
+                T.created_at DESC
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(tournaments)
+    }
+
+    pub async fn get_password(&self, tournament_id: Uuid) -> Result<Option<String>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let password = query_scalar!(
+            "select password from tournaments where tournament_id = $1",
+            tournament_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(password)
+    }
+
+    /// 按报名顺序分配种子序号
+    pub async fn get_transaction(&self) -> Result<Transaction<'_, Postgres>, RepoError> {
+        Ok(self.pool.begin().await?)
+    }
+
+    /// 锁住锦标赛行直到事务结束, 返回状态和已报名的 Agent, 并发报名依次进行
+    pub async fn lock_entrants(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        tournament_id: Uuid,
+    ) -> Result<(MatchStatus, Vec<Uuid>), RepoError> {
+        let status = query_scalar!(
+            r#"
+            select status as "status!: MatchStatus" from tournaments
+            where tournament_id = $1
+            for update
+            "#,
+            tournament_id
+        )
+        .fetch_one(tx.as_mut())
+        .await?;
+        let entered = query_scalar!(
+            r#"
+            select agent_id from entrants where tournament_id = $1
+            "#,
+            tournament_id
+        )
+        .fetch_all(tx.as_mut())
+        .await?;
+        Ok((status, entered))
+    }
+
+    /// 按顺序接在已有报名之后编号
+    pub async fn insert_entrants(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        tournament_id: Uuid,
+        agent_ids: &[Uuid],
+    ) -> Result<(), RepoError> {
+        let _ = query!(
+            r#"
+            insert into entrants (tournament_id, agent_id, seeding)
+            select $1, A.agent_id,
+                (select coalesce(max(seeding), 0) from entrants where tournament_id = $1) + A.ord::int
+            from unnest($2::uuid[]) with ordinality as A(agent_id, ord)
+            "#,
+            tournament_id,
+            agent_ids,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_entrants(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<Vec<TournamentEntrantResponse>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let entrants = query_as!(
+            TournamentEntrantResponse,
+            r#"
+            SELECT
+                E.agent_id,
+                A.name AS agent_name,
+                U.username AS owner_name,
+                E.seeding
+            FROM entrants E
+            JOIN agents A ON E.agent_id = A.agent_id
+            JOIN users U ON A.owner_id = U.user_id
+            WHERE E.tournament_id = $1
+            ORDER BY E.seeding
+            "#,
+            tournament_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(entrants)
+    }
+
+    pub async fn start_tournament(
+        &self,
+        tournament_id: Uuid,
+        total_rounds: Option<i32>,
+    ) -> Result<(), RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let _ = query!(
+            r#"
+            update tournaments set status = $1, total_rounds = $2 where tournament_id = $3
+            "#,
+            MatchStatus::Running as MatchStatus,
+            total_rounds,
+            tournament_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn update_current_round(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        tournament_id: Uuid,
+        round: i32,
+    ) -> Result<(), RepoError> {
+        let _ = query!(
+            "update tournaments set current_round = $1 where tournament_id = $2",
+            round,
+            tournament_id,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn finish_tournament(
+        &self,
+        tournament_id: Uuid,
+        winner_id: Option<Uuid>,
+    ) -> Result<(), RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let _ = query!(
+            r#"
+            update tournaments set status = $1, winner_id = $2, end_time = now() where tournament_id = $3
+            "#,
+            MatchStatus::Completed as MatchStatus,
+            winner_id,
+            tournament_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn insert_bye(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        tournament_id: Uuid,
+        round: i32,
+        agent_id: Uuid,
+    ) -> Result<(), RepoError> {
+        let _ = query!(
+            "insert into tournament_byes (tournament_id, round, agent_id) values ($1, $2, $3)",
+            tournament_id,
+            round,
+            agent_id,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_byes(&self, tournament_id: Uuid) -> Result<Vec<TournamentByeDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let byes = query_as!(
+            TournamentByeDTO,
+            "select round, agent_id from tournament_byes where tournament_id = $1 order by round",
+            tournament_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(byes)
+    }
+
+    pub async fn get_matches(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<Vec<TournamentMatchDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let matches = query_as!(
+            TournamentMatchDTO,
+            r#"
+            SELECT
+                M.match_id,
+                M.name AS match_name,
+                M.round AS "round!",
+                M.status AS "status!: MatchStatus",
+                M.winner_id,
+                ARRAY(
+                    SELECT P.agent_id FROM participants P
+                    WHERE P.match_id = M.match_id ORDER BY P.agent_id
+                ) AS "agent_ids!",
+                ARRAY(
+                    SELECT A.name FROM participants P JOIN agents A ON P.agent_id = A.agent_id
+                    WHERE P.match_id = M.match_id ORDER BY P.agent_id
+                ) AS "agent_names!"
+            FROM
+                matches AS M
+            WHERE
+                M.tournament_id = $1
+            ORDER BY
+                M.round, M.name
+            "#,
+            tournament_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(matches)
+    }
+
+    /// 各参赛者在锦标赛所有对局中的累计得分
+    pub async fn get_scores(
+        &self,
+        tournament_id: Uuid,
+    ) -> Result<Vec<TournamentScoreDTO>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let scores = query_as!(
+            TournamentScoreDTO,
+            r#"
+            SELECT
+                P.agent_id,
+                SUM(P.final_score)::float8 AS "score!"
+            FROM
+                participants AS P
+            INNER JOIN
+                matches AS M ON P.match_id = M.match_id
+            WHERE
+                M.tournament_id = $1 AND P.final_score IS NOT NULL
+            GROUP BY
+                P.agent_id
+            "#,
+            tournament_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(scores)
+    }
+
+    /// 比赛所属的锦标赛和轮次, 普通比赛返回 None
+    pub async fn get_match_round(&self, match_id: Uuid) -> Result<Option<(Uuid, i32)>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let row = query!(
+            "select tournament_id, round from matches where match_id = $1",
+            match_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(row.tournament_id.zip(row.round))
+    }
+}
@@ -1,13 +1,16 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sqlx::{query, query_as, PgPool, Postgres, Transaction};
+use sqlx::{query_as, PgPool, Postgres, QueryBuilder, Transaction};
 use std::sync::Arc;
-use tackle_box::contracts::payloads::TurnLogResponse;
+use tackle_box::contracts::payloads::{ExportCursor, ExportedTurn, TurnLogResponse};
 use uuid::Uuid;
 
 use crate::repo::error::RepoError;
 
+/// 每局 8 个参数, 单条语句不超过 65535 个参数
+const TURN_INSERT_CHUNK: usize = 1000;
+
 pub struct NewTurnDTO {
     pub match_id: Uuid,
     pub i_turn: i32,         // 回合数/局数
@@ -15,6 +18,8 @@ pub struct NewTurnDTO {
     pub log: Value,          // JSONB 格式的详细日志
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
+    pub seed: Option<i64>,
+    pub seats: Vec<Uuid>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -41,31 +46,43 @@ pub struct GetSettleTurnDTO {
 //     pub log: Value,
 // }
 
+/// 导出回合时的过滤条件, 为空的条件不生效
+pub struct TurnFilterDTO {
+    pub game_type_id: Option<Uuid>,
+    pub agent_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// 只包含该用户的 Agent 参与的比赛
+    pub owner_id: Option<Uuid>,
+}
+
 pub struct TurnRepo {
     pub pool: Arc<PgPool>,
 }
 
 impl TurnRepo {
-    pub async fn insert_turn(
+    /// 一条语句插入多局, 每条语句的参数个数受 Postgres 协议限制, 按 TURN_INSERT_CHUNK 分批
+    pub async fn insert_turns(
         &self,
         tx: &mut Transaction<'_, Postgres>,
-        turn: NewTurnDTO,
+        turns: &[NewTurnDTO],
     ) -> Result<(), RepoError> {
-        // let mut conn = self.pool.acquire().await?;
-        let _ = query!(
-            r#"
-            insert into turns (match_id, i_turn, score_deltas, log, start_time, end_time)
-            values ($1, $2, $3, $4, $5, $6)
-            "#,
-            turn.match_id,
-            turn.i_turn,
-            turn.score_deltas,
-            turn.log,
-            turn.start_time,
-            turn.end_time,
-        )
-        .execute(tx.as_mut())
-        .await?;
+        for chunk in turns.chunks(TURN_INSERT_CHUNK) {
+            let mut builder = QueryBuilder::<Postgres>::new(
+                "insert into turns (match_id, i_turn, score_deltas, log, start_time, end_time, seed, seats) ",
+            );
+            builder.push_values(chunk, |mut row, turn| {
+                row.push_bind(turn.match_id)
+                    .push_bind(turn.i_turn)
+                    .push_bind(&turn.score_deltas)
+                    .push_bind(&turn.log)
+                    .push_bind(turn.start_time)
+                    .push_bind(turn.end_time)
+                    .push_bind(turn.seed)
+                    .push_bind(&turn.seats);
+            });
+            builder.build().execute(tx.as_mut()).await?;
+        }
         Ok(())
     }
 
@@ -101,7 +118,11 @@ impl TurnRepo {
                 match_id,
                 i_turn,
                 score_deltas,
-                log
+                log,
+                start_time,
+                end_time,
+                seed,
+                seats
             FROM turns
             WHERE match_id = $1 AND i_turn = $2
             "#,
@@ -123,9 +144,14 @@ impl TurnRepo {
                 match_id,
                 i_turn,
                 score_deltas,
-                log
+                log,
+                start_time,
+                end_time,
+                seed,
+                seats
             FROM turns
             WHERE match_id = $1
+            ORDER BY i_turn
             "#,
             match_id,
         )
@@ -133,4 +159,58 @@ impl TurnRepo {
         .await?;
         Ok(turn)
     }
+
+    /// 按 (end_time, turn_id) 分页读取一批回合, 游标之后的最多 limit 条
+    pub async fn export_turns(
+        &self,
+        filter: &TurnFilterDTO,
+        after: Option<&ExportCursor>,
+        limit: i64,
+    ) -> Result<Vec<ExportedTurn>, RepoError> {
+        let mut conn = self.pool.acquire().await?;
+        let turns = query_as!(
+            ExportedTurn,
+            r#"
+            SELECT
+                T.turn_id,
+                T.match_id,
+                M.name AS match_name,
+                G.name AS game_type_name,
+                T.i_turn,
+                T.seed,
+                T.seats,
+                T.score_deltas,
+                T.log,
+                T.start_time,
+                T.end_time
+            FROM turns AS T
+            INNER JOIN matches AS M ON T.match_id = M.match_id
+            INNER JOIN gametypes AS G ON M.game_type_id = G.game_type_id
+            WHERE ($1::uuid IS NULL OR M.game_type_id = $1)
+              AND ($2::uuid IS NULL OR EXISTS (
+                  SELECT 1 FROM participants AS P
+                  WHERE P.match_id = T.match_id AND P.agent_id = $2))
+              AND ($3::timestamptz IS NULL OR T.end_time >= $3)
+              AND ($4::timestamptz IS NULL OR T.end_time < $4)
+              AND ($5::uuid IS NULL OR EXISTS (
+                  SELECT 1 FROM participants AS P
+                  INNER JOIN agents AS A ON P.agent_id = A.agent_id
+                  WHERE P.match_id = T.match_id AND A.owner_id = $5))
+              AND ($6::timestamptz IS NULL OR (T.end_time, T.turn_id) > ($6, $7::uuid))
+            ORDER BY T.end_time, T.turn_id
+            LIMIT $8
+            "#,
+            filter.game_type_id,
+            filter.agent_id,
+            filter.from,
+            filter.to,
+            filter.owner_id,
+            after.map(|c| c.end_time),
+            after.map(|c| c.turn_id),
+            limit,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(turns)
+    }
 }
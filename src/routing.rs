@@ -0,0 +1,107 @@
+//! 按 id 分片的路由表, 客户端与比赛之间直接投递消息时使用
+
+use std::{
+    collections::HashMap,
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+use uuid::Uuid;
+
+/// 默认分片数, 需为 2 的幂
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// 每个分片一把读写锁, 不同 id 的注册与查找互不阻塞
+///
+/// 锁只在读写 HashMap 时持有, 取出的值 (通常是 `Sender`) 克隆后再使用.
+pub struct Registry<V> {
+    shards: Box<[RwLock<HashMap<Uuid, V>>]>,
+    mask: usize,
+}
+
+impl<V: Clone> Registry<V> {
+    pub fn new(shards: usize) -> Self {
+        let shards = shards.max(1).next_power_of_two();
+        Self {
+            shards: (0..shards).map(|_| RwLock::new(HashMap::new())).collect(),
+            mask: shards - 1,
+        }
+    }
+
+    pub fn insert(&self, id: Uuid, value: V) -> Option<V> {
+        self.write(&id).insert(id, value)
+    }
+
+    pub fn remove(&self, id: &Uuid) -> Option<V> {
+        self.write(id).remove(id)
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<V> {
+        self.read(id).get(id).cloned()
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.read(id).contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| read(shard).is_empty())
+    }
+
+    /// 逐个分片过滤, 返回被移除的条目
+    pub fn retain(&self, mut keep: impl FnMut(&Uuid, &V) -> bool) -> Vec<(Uuid, V)> {
+        let mut removed = Vec::new();
+        for shard in self.shards.iter() {
+            let mut shard = write(shard);
+            let ids: Vec<Uuid> = shard
+                .iter()
+                .filter(|(id, value)| !keep(id, value))
+                .map(|(id, _)| *id)
+                .collect();
+            for id in ids {
+                if let Some(value) = shard.remove(&id) {
+                    removed.push((id, value));
+                }
+            }
+        }
+        removed
+    }
+
+    pub fn drain(&self) -> Vec<(Uuid, V)> {
+        self.shards
+            .iter()
+            .flat_map(|shard| write(shard).drain().collect::<Vec<_>>())
+            .collect()
+    }
+
+    fn shard(&self, id: &Uuid) -> &RwLock<HashMap<Uuid, V>> {
+        // v4 uuid 的低位是随机的, 直接取模即可均匀分布
+        &self.shards[id.as_u128() as usize & self.mask]
+    }
+
+    fn read(&self, id: &Uuid) -> RwLockReadGuard<'_, HashMap<Uuid, V>> {
+        read(self.shard(id))
+    }
+
+    fn write(&self, id: &Uuid) -> RwLockWriteGuard<'_, HashMap<Uuid, V>> {
+        write(self.shard(id))
+    }
+}
+
+impl<V: Clone> Default for Registry<V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS)
+    }
+}
+
+/// 只有 retain 的过滤函数 panic 时锁才会中毒, 此时 HashMap 仍然完整, 直接取回
+fn read<V>(lock: &RwLock<V>) -> RwLockReadGuard<'_, V> {
+    lock.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn write<V>(lock: &RwLock<V>) -> RwLockWriteGuard<'_, V> {
+    lock.write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
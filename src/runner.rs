@@ -0,0 +1,695 @@
+//! 比赛任务 MatchRunner, 以及它与 Core, 客户端之间传递的消息
+//!
+//! MatchRunner 驱动 sponsor 进行每一局, 把状态直接发给 Agent 的客户端,
+//! 比赛结束后把对局交给 Core 结算.
+
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{iter::zip, sync::Arc};
+use thiserror::Error;
+use tokio::{
+    sync::{
+        mpsc::{self, error::SendError, Receiver, Sender},
+        oneshot, watch,
+    },
+    time::Instant,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Channel, Request, Status, Streaming};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    connection::{
+        game_control::ControlType, game_init_response::ResultType,
+        match_monitor_response::EventType, process_game_request::RequestType,
+        process_game_response::ResponseType, sponsor_service_client::SponsorServiceClient,
+        GameControl, GameEndStatus, GameInitRequest, GameStateUpdate, MatchMonitorResponse,
+        MatchUpdate, PlayerAction, ProcessGameRequest, ProcessGameResponse, ScoreChange,
+    },
+    contracts::{payloads::MatchMode, seed::game_seed},
+    metrics::{ACTION_LATENCY, SPONSOR_RTT},
+    routing::Registry,
+};
+
+/// 比赛无法继续的原因, 服务端转换为 AppError
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    #[error("{0}")]
+    Aborted(String),
+    #[error("{0}")]
+    Internal(String),
+    #[error("sponsor error: {0}")]
+    Communication(#[from] Status),
+    #[error("core or client channel closed")]
+    SendCore(#[from] SendError<CoreMessage>),
+    #[error("sponsor stream closed")]
+    SendSponsor(#[from] SendError<ProcessGameRequest>),
+}
+
+/// 比赛开始时的对局设置
+#[derive(Debug, Clone, Copy)]
+pub struct MatchSettings {
+    pub total_games: i32,
+    pub seed: i64,
+    pub mode: MatchMode,
+}
+
+pub enum CoreMessage {
+    ClientRegiser {
+        user_id: Uuid,
+        agent_id: Uuid,
+        tx: Sender<CoreMessage>,
+    },
+    ClientUnregiser {
+        user_id: Uuid,
+        agent_id: Uuid,
+    },
+    MatchStart {
+        match_id: Uuid,
+        agent_ids: Vec<Uuid>,
+        sponsor: String,
+        game_type: String,
+        settings: MatchSettings,
+    },
+    AgentAction {
+        agent_id: Uuid,
+        match_id: Uuid,
+        action: String,
+    },
+    AgentLog {
+        agent_id: Uuid,
+        match_id: Uuid,
+        message: String,
+    },
+    AgentResign {
+        agent_id: Uuid,
+        match_id: Uuid,
+        reason: String,
+        error: bool,
+    },
+    GameState {
+        agent_id: Uuid,
+        match_id: Uuid,
+        seat: i32,
+        i_turn: i32,
+        state: String,
+    },
+    /// 比赛中止, logs 为已完成的对局
+    MatchPause {
+        match_id: Uuid,
+        agent_ids: Vec<Uuid>,
+        logs: Vec<TurnLog>,
+    },
+    MatchSettle {
+        match_id: Uuid,
+        settler: GameSettlement,
+    },
+    MonitorRegister {
+        match_id: Uuid,
+        tx: Sender<Result<MatchMonitorResponse, Status>>,
+    },
+    MatchEvent {
+        match_id: Uuid,
+        event: EventType,
+    },
+    /// 借用 sponsor 连接, 用于比赛之外的重新模拟
+    SponsorLookup {
+        sponsor: String,
+        tx: oneshot::Sender<Option<SponsorServiceClient<Channel>>>,
+    },
+    /// 健康检查, 能收到回复说明 Core 仍在处理消息
+    Health {
+        tx: oneshot::Sender<CoreStatus>,
+    },
+    /// 发给客户端, 其消息被丢弃
+    Rejected {
+        match_id: Uuid,
+        reason: String,
+        /// 比赛已不在运行, 之后的输出不必再发送
+        closed: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CoreStatus {
+    pub draining: bool,
+    pub clients: usize,
+    pub matches: usize,
+}
+
+/// Core, 客户端和 MatchRunner 共享的路由表
+///
+/// 动作和状态由客户端与 MatchRunner 查表后直接投递, 不经过 Core;
+/// Core 只负责注册, 比赛的开始与结算等低频消息.
+#[derive(Default)]
+pub struct Routes {
+    pub clients: Registry<Sender<CoreMessage>>,
+    pub matches: Registry<Sender<CoreMessage>>,
+}
+
+/// 建立与 sponsor 的双向流并发送初始化请求
+pub async fn connect_sponsor(
+    mut client: SponsorServiceClient<Channel>,
+    game_type: &str,
+    seed: i64,
+) -> Result<(Sender<ProcessGameRequest>, Streaming<ProcessGameResponse>), RunnerError> {
+    let (sponsor_tx, sponsor_rx) = mpsc::channel(16);
+    let init_req = ProcessGameRequest {
+        request_type: Some(RequestType::Init(GameInitRequest {
+            game_type: game_type.to_string(),
+            seed: Some(game_seed(seed, 0) as u64),
+        })),
+    };
+    let request_stream = ReceiverStream::new(sponsor_rx);
+    let resp = client.process_game(Request::new(request_stream)).await?;
+    sponsor_tx.send(init_req).await?;
+    let mut sponsor_instream = resp.into_inner();
+    // 丢弃一次应答, 因为Python后段依赖至少一次回复来生成流
+    let _ = sponsor_instream.next().await;
+    Ok((sponsor_tx, sponsor_instream))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TurnLog {
+    pub logs: Vec<GameStreamType>,
+    /// 按 `GameSettlement::agent_ids` 顺序的得分, 与座位无关
+    pub payoffs: Vec<f32>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// 本局发给 sponsor 的种子
+    pub seed: i64,
+    /// 本局各座位上的 Agent
+    pub seats: Vec<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GameSettlement {
+    pub match_id: Uuid,
+    pub agent_ids: Vec<Uuid>,
+    pub logs: Vec<TurnLog>,
+    /// 认输或出错的 Agent, 该 Agent 判负且不参与胜者计算
+    pub forfeit: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum GameStreamType {
+    /// sponsor 下发的状态, seat 为需要行动的座位
+    State {
+        seat: i32,
+        state: String,
+        is_over: bool,
+        at: DateTime<Utc>,
+    },
+    Action {
+        seat: i32,
+        action: String,
+        at: DateTime<Utc>,
+    },
+    /// Agent 的调试输出, 只对 Agent 的所有者可见
+    Log { agent_id: Uuid, message: String },
+    Resign {
+        agent_id: Uuid,
+        reason: String,
+        error: bool,
+    },
+}
+
+/// 单条 Agent 日志的最大长度
+const MAX_AGENT_LOG_LEN: usize = 2048;
+/// 每局最多记录的 Agent 日志条数, 超出部分丢弃
+const MAX_AGENT_LOGS_PER_TURN: usize = 256;
+
+/// 比赛任务的收发端
+pub struct MatchChannels {
+    /// Agent 的动作和 Core 转来的消息
+    pub match_rx: Receiver<CoreMessage>,
+    pub core_tx: Sender<CoreMessage>,
+    pub routes: Arc<Routes>,
+    pub sponsor_tx: Sender<ProcessGameRequest>,
+    pub sponsor_rx: Streaming<ProcessGameResponse>,
+    pub abort: watch::Receiver<bool>,
+}
+
+pub struct MatchRunner {
+    match_id: Uuid,
+    agent_ids: Vec<Uuid>,
+    sponsor: String,
+    game_type: String,
+    total_games: i32,
+    /// 比赛种子, 第 i 副牌使用 `game_seed(seed, i)`
+    seed: i64,
+    mode: MatchMode,
+
+    match_rx: Receiver<CoreMessage>,
+    core_tx: Sender<CoreMessage>,
+    /// 状态直接发给 Agent 的客户端
+    routes: Arc<Routes>,
+    sponsor_tx: Sender<ProcessGameRequest>,
+    sponsor_rx: Streaming<ProcessGameResponse>,
+    /// 停机超时后收到中止通知
+    abort: watch::Receiver<bool>,
+
+    i_turn: i32,
+    game_logs: Option<Vec<TurnLog>>,
+    turn_log: Option<Vec<GameStreamType>>,
+    turn_log_count: usize,
+    turn_start_time: DateTime<Utc>,
+    /// 最近一次状态中需要行动的座位
+    current_seat: i32,
+    /// 等待其行动的 Agent, 收到它的动作或本局结束后清空
+    awaiting: Option<Uuid>,
+    forfeit: Option<(Uuid, String)>,
+    /// 最近一次把状态发给 Agent 的时间, 收到动作时统计延迟
+    state_sent_at: Option<Instant>,
+    /// 最近一次向 sponsor 发出请求的时间, 收到应答时统计往返时间
+    sponsor_sent_at: Option<Instant>,
+}
+
+impl MatchRunner {
+    pub fn new(
+        match_id: Uuid,
+        agent_ids: Vec<Uuid>,
+        sponsor: String,
+        game_type: String,
+        settings: MatchSettings,
+        channels: MatchChannels,
+    ) -> Self {
+        let MatchSettings {
+            total_games,
+            seed,
+            mode,
+        } = settings;
+        // 复式赛中 total_games 为牌副数, 每副牌由每个参赛者轮流坐各个座位
+        let total_games = match mode {
+            MatchMode::Standard => total_games,
+            MatchMode::Duplicate => total_games * agent_ids.len() as i32,
+        };
+        let MatchChannels {
+            match_rx,
+            core_tx,
+            routes,
+            sponsor_tx,
+            sponsor_rx,
+            abort,
+        } = channels;
+        Self {
+            match_id,
+            agent_ids,
+            sponsor,
+            game_type,
+            total_games,
+            seed,
+            mode,
+            match_rx,
+            core_tx,
+            routes,
+            sponsor_tx,
+            sponsor_rx,
+            abort,
+            i_turn: 0,
+            turn_log: Some(Vec::new()),
+            game_logs: Some(Vec::new()),
+            turn_log_count: 0,
+            turn_start_time: Utc::now(),
+            current_seat: 0,
+            awaiting: None,
+            forfeit: None,
+            state_sent_at: None,
+            sponsor_sent_at: None,
+        }
+    }
+
+    /// 每副牌进行的局数, 复式赛中每个参赛者轮流坐过每个座位
+    fn games_per_deal(&self) -> i32 {
+        match self.mode {
+            MatchMode::Standard => 1,
+            MatchMode::Duplicate => self.agent_ids.len().max(1) as i32,
+        }
+    }
+
+    /// 当前局使用的种子, 同一副牌的各局相同
+    fn game_seed(&self) -> i64 {
+        game_seed(self.seed, self.i_turn / self.games_per_deal())
+    }
+
+    /// 当前局各座位上的 Agent, 每局相对上一局轮换一个座位
+    fn seating(&self) -> Vec<Uuid> {
+        let n = self.agent_ids.len();
+        let rotation = (self.i_turn % self.games_per_deal()) as usize;
+        (0..n)
+            .map(|seat| self.agent_ids[(seat + rotation) % n])
+            .collect()
+    }
+
+    pub async fn run(&mut self) -> Result<(), RunnerError> {
+        self.notify(EventType::MatchUpdate(MatchUpdate {
+            current_status: "Running".to_string(),
+            message: format!(
+                "{} started with {} agents, {} games",
+                self.game_type,
+                self.agent_ids.len(),
+                self.total_games
+            ),
+        }))
+        .await?;
+        let loop_result: Result<(), RunnerError> = async {
+            while self.i_turn < self.total_games && self.forfeit.is_none() {
+                let r = tokio::select! {
+                    Some(msg) = self.match_rx.recv() => {
+                        self.process_core_message(msg).await
+                    },
+                    Some(Ok(resp)) = self.sponsor_rx.next() => {
+                        self.process_sponsor_message(resp).await
+                    },
+                    _ = self.abort.changed() => {
+                        return Err(RunnerError::Aborted("server is shutting down".to_string()));
+                    },
+                    else => {
+                        return Err(RunnerError::Aborted("Input stream/channel closed unexpectedly.".to_string()));
+                    }
+                };
+                r?;
+            }
+            // 比赛正常完成
+            Ok(())
+        }.await;
+        if let Err(e) = loop_result {
+            // if e.is_connection_error() || e.is_match_aborted() { // 假设 RunnerError 有这些辅助方法
+
+            //     // 🚀 核心：更新比赛状态为 CANCELLED
+            //     self.core_tx.send(CoreMessage::MatchStatusUpdate {
+            //         match_id: self.match_id,
+            //         status: MatchStatus::Cancelled,
+            //     }).await?;
+
+            //     // 返回错误，但已经执行了清理/取消操作
+            //     return Err(e);
+            // } else {
+            //     // 如果是其他不应该导致取消的内部逻辑错误
+            //     return Err(e);
+            // }
+            self.notify(EventType::MatchUpdate(MatchUpdate {
+                current_status: "Cancelled".to_string(),
+                message: e.to_string(),
+            }))
+            .await?;
+            self.core_tx
+                .send(CoreMessage::MatchPause {
+                    match_id: self.match_id,
+                    agent_ids: self.agent_ids.clone(),
+                    logs: self.game_logs.take().unwrap_or_default(),
+                })
+                .await?;
+            return Err(e);
+        }
+        let message = match &self.forfeit {
+            Some((agent_id, reason)) => {
+                let message = format!("agent {} forfeited: {}", agent_id, reason);
+                self.close_forfeited_turn().await?;
+                message
+            }
+            None => format!("all {} games finished", self.total_games),
+        };
+        self.notify(EventType::MatchUpdate(MatchUpdate {
+            current_status: "Completed".to_string(),
+            message,
+        }))
+        .await?;
+        self.core_tx
+            .send(CoreMessage::MatchSettle {
+                match_id: self.match_id,
+                settler: GameSettlement {
+                    match_id: self.match_id,
+                    agent_ids: self.agent_ids.clone(),
+                    logs: self.game_logs.take().unwrap(),
+                    forfeit: self.forfeit.as_ref().map(|(agent_id, _)| *agent_id),
+                },
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// 直接把状态发给 Agent 的客户端, 客户端已断开时该 Agent 按出错弃权
+    async fn send_state(
+        &mut self,
+        agent_id: Uuid,
+        seat: i32,
+        state: String,
+    ) -> Result<(), RunnerError> {
+        let msg = CoreMessage::GameState {
+            agent_id,
+            match_id: self.match_id,
+            seat,
+            i_turn: self.i_turn,
+            state,
+        };
+        let delivered = match self.routes.clients.get(&agent_id) {
+            Some(client) => client.send(msg).await.is_ok(),
+            None => false,
+        };
+        if delivered {
+            self.state_sent_at = Some(Instant::now());
+            return Ok(());
+        }
+        warn!(%agent_id, "agent unreachable, resigning");
+        self.process_core_message(CoreMessage::AgentResign {
+            agent_id,
+            match_id: self.match_id,
+            reason: "agent is not connected".to_string(),
+            error: true,
+        })
+        .await
+    }
+
+    async fn notify(&self, event: EventType) -> Result<(), RunnerError> {
+        self.core_tx
+            .send(CoreMessage::MatchEvent {
+                match_id: self.match_id,
+                event,
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn process_core_message(&mut self, msg: CoreMessage) -> Result<(), RunnerError> {
+        match msg {
+            CoreMessage::AgentAction {
+                agent_id, action, ..
+            } => {
+                // 只接受正在等待的 Agent 的一次动作, 轮外或重复的动作不发给 sponsor
+                if self.awaiting != Some(agent_id) {
+                    self.reject_action(agent_id);
+                    return Ok(());
+                }
+                self.awaiting = None;
+                if let Some(sent_at) = self.state_sent_at.take() {
+                    ACTION_LATENCY.observe(&[&self.game_type], sent_at.elapsed());
+                }
+                debug!(%agent_id, seat = self.current_seat, i_turn = self.i_turn, "agent action");
+                self.turn_log
+                    .get_or_insert(vec![])
+                    .push(GameStreamType::Action {
+                        seat: self.current_seat,
+                        action: action.clone(),
+                        at: Utc::now(),
+                    });
+                self.sponsor_tx
+                    .send(ProcessGameRequest {
+                        request_type: Some(RequestType::Action(PlayerAction { action })),
+                    })
+                    .await?;
+                self.sponsor_sent_at = Some(Instant::now());
+            }
+            CoreMessage::AgentLog {
+                agent_id, message, ..
+            } => {
+                if !self.agent_ids.contains(&agent_id)
+                    || self.turn_log_count >= MAX_AGENT_LOGS_PER_TURN
+                {
+                    return Ok(());
+                }
+                self.turn_log_count += 1;
+                let message = message.chars().take(MAX_AGENT_LOG_LEN).collect();
+                self.turn_log
+                    .get_or_insert(vec![])
+                    .push(GameStreamType::Log { agent_id, message });
+            }
+            CoreMessage::AgentResign {
+                agent_id,
+                reason,
+                error,
+                ..
+            } => {
+                if !self.agent_ids.contains(&agent_id) || self.forfeit.is_some() {
+                    return Ok(());
+                }
+                self.turn_log
+                    .get_or_insert(vec![])
+                    .push(GameStreamType::Resign {
+                        agent_id,
+                        reason: reason.clone(),
+                        error,
+                    });
+                info!(%agent_id, %reason, error, "agent forfeited");
+                self.forfeit = Some((agent_id, reason));
+            }
+            _ => return Err(RunnerError::Internal("unknow error".to_string())),
+        };
+        Ok(())
+    }
+
+    /// 告知发送方动作被丢弃, 不等待对方以免阻塞比赛
+    fn reject_action(&self, agent_id: Uuid) {
+        let reason = match self.awaiting {
+            Some(_) => format!(
+                "not your turn: seat {} is to act in game {}",
+                self.current_seat, self.i_turn
+            ),
+            None => format!("no action is expected in game {}", self.i_turn),
+        };
+        warn!(%agent_id, %reason, "agent action rejected");
+        if let Some(client) = self.routes.clients.get(&agent_id) {
+            let _ = client.try_send(CoreMessage::Rejected {
+                match_id: self.match_id,
+                reason,
+                closed: false,
+            });
+        }
+    }
+
+    /// 弃权时把未完成的一局以零分记入日志, 并让 sponsor 停止当前对局
+    async fn close_forfeited_turn(&mut self) -> Result<(), RunnerError> {
+        if let Some(logs) = self.turn_log.take() {
+            let turn_log = TurnLog {
+                logs,
+                payoffs: vec![0.0; self.agent_ids.len()],
+                start_time: self.turn_start_time,
+                end_time: Utc::now(),
+                seed: self.game_seed(),
+                seats: self.seating(),
+            };
+            self.game_logs.get_or_insert(vec![]).push(turn_log);
+        }
+        self.sponsor_tx
+            .send(ProcessGameRequest {
+                request_type: Some(RequestType::Control(GameControl {
+                    r#type: ControlType::Pause.into(),
+                    seed: None,
+                })),
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn process_sponsor_message(
+        &mut self,
+        resp: ProcessGameResponse,
+    ) -> Result<(), RunnerError> {
+        if let Some(sent_at) = self.sponsor_sent_at.take() {
+            SPONSOR_RTT.observe(&[&self.sponsor], sent_at.elapsed());
+        }
+        let ProcessGameResponse { response_type } = resp;
+        match response_type {
+            Some(ResponseType::StateUpdate(data)) => {
+                let GameStateUpdate {
+                    state,
+                    is_over,
+                    i_player,
+                } = data;
+                let seats = self.seating();
+                let agent_id = *seats.get(i_player as usize).ok_or_else(|| {
+                    RunnerError::Aborted(format!(
+                        "sponsor asked seat {} to act, but game {} has {} seats",
+                        i_player,
+                        self.i_turn,
+                        seats.len()
+                    ))
+                })?;
+                self.current_seat = i_player;
+                self.awaiting = (!is_over).then_some(agent_id);
+                self.turn_log
+                    .get_or_insert(vec![])
+                    .push(GameStreamType::State {
+                        seat: i_player,
+                        state: state.clone(),
+                        is_over,
+                        at: Utc::now(),
+                    });
+                if !is_over {
+                    self.send_state(agent_id, i_player, state).await?;
+                }
+            }
+            Some(ResponseType::EndStatus(data)) => {
+                let GameEndStatus { payoffs } = data;
+                self.awaiting = None;
+                let seats = self.seating();
+                // sponsor 按座位给出得分, 换回参赛者顺序
+                let payoffs: Vec<f32> = self
+                    .agent_ids
+                    .iter()
+                    .map(|id| {
+                        seats
+                            .iter()
+                            .position(|seated| seated == id)
+                            .and_then(|seat| payoffs.get(seat).copied())
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                let agent_scores = zip(&self.agent_ids, &payoffs)
+                    .map(|(id, payoff)| (id.to_string(), payoff.round() as i32))
+                    .collect();
+                self.notify(EventType::ScoreChange(ScoreChange {
+                    agent_scores,
+                    source_i_turn: self.i_turn.to_string(),
+                }))
+                .await?;
+                let turn_log = TurnLog {
+                    logs: self.turn_log.take().unwrap(),
+                    payoffs,
+                    start_time: self.turn_start_time,
+                    end_time: Utc::now(),
+                    seed: self.game_seed(),
+                    seats,
+                };
+                self.game_logs.get_or_insert(vec![]).push(turn_log);
+                self.turn_log_count = 0;
+                self.turn_start_time = Utc::now();
+                self.i_turn += 1;
+                if self.i_turn == self.total_games {
+                    self.sponsor_tx
+                        .send(ProcessGameRequest {
+                            request_type: Some(RequestType::Control(GameControl {
+                                r#type: ControlType::Pause.into(),
+                                seed: None,
+                            })),
+                        })
+                        .await?;
+                    // self.sponsor_tx.closed().await;
+                } else {
+                    self.sponsor_tx
+                        .send(ProcessGameRequest {
+                            request_type: Some(RequestType::Control(GameControl {
+                                r#type: ControlType::Resume.into(),
+                                seed: Some(self.game_seed() as u64),
+                            })),
+                        })
+                        .await?;
+                    self.sponsor_sent_at = Some(Instant::now());
+                }
+            }
+            // sponsor 在连接和初始化时各回复一次, 前者已在开始比赛时丢弃
+            Some(ResponseType::InitResponse(init)) => {
+                if init.r#type() == ResultType::Failed {
+                    return Err(RunnerError::Aborted(format!(
+                        "sponsor failed to init {}",
+                        self.game_type
+                    )));
+                }
+            }
+            _ => return Err(RunnerError::Internal("unknow error".to_string())),
+        };
+        Ok(())
+    }
+}
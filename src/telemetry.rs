@@ -0,0 +1,85 @@
+//! 日志与链路追踪: 按 EnvFilter 过滤, 输出文本或 JSON, 以 otlp feature 编译时可导出到 OTLP collector
+
+use tracing::warn;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::config::{ConfigError, LogConfig, LogFormat};
+
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+
+/// 在进程退出前调用 `shutdown` 以导出缓冲中的 span
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn init(config: &LogConfig) -> Result<Self, ConfigError> {
+        let json = (config.format == LogFormat::Json).then(|| {
+            fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+        });
+        let text = (config.format == LogFormat::Text).then(fmt::layer);
+        let registry = tracing_subscriber::registry()
+            .with(EnvFilter::new(&config.level))
+            .with(json)
+            .with(text);
+
+        #[cfg(feature = "otlp")]
+        {
+            let provider = config
+                .otlp_endpoint
+                .as_deref()
+                .map(otlp_provider)
+                .transpose()?;
+            let otel = provider.as_ref().map(|provider| {
+                tracing_opentelemetry::layer().with_tracer(provider.tracer("tackle_box"))
+            });
+            registry.with(otel).init();
+            Ok(Self { provider })
+        }
+        #[cfg(not(feature = "otlp"))]
+        {
+            registry.init();
+            if let Some(endpoint) = &config.otlp_endpoint {
+                warn!(
+                    "built without the otlp feature, spans are not exported to {}",
+                    endpoint
+                );
+            }
+            Ok(Self {})
+        }
+    }
+
+    pub fn shutdown(self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                warn!("failed to flush otlp spans: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_provider(endpoint: &str) -> Result<SdkTracerProvider, ConfigError> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| ConfigError::Invalid(format!("otlp exporter for {}: {}", endpoint, e)))?;
+    let resource = opentelemetry_sdk::Resource::builder()
+        .with_service_name("tackle_box")
+        .build();
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build())
+}